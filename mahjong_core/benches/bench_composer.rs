@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mahjong_core::composer::{Composer, Counter, Decomposition, compose_histogram};
+use mahjong_core::conversions::ConvertStrings;
+use mahjong_core::tiles::{Tile, Histogram, ToHistogram};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// counts every allocation, so the two composers can be compared by more than time
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let hands: Vec<Vec<Tile>> = ["p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4,p9", "m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8,s7",
+        "m1,m1,m1,m2,m3,m4,m5,m6,m7,m8,m9,m9,m9,m5", "m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn,wn"]
+        .iter().map(|h| h.to_tiles().unwrap()).collect();
+    let counted: Vec<Vec<(usize, Tile)>> = hands.iter().map(|h| h.clone().to_counted_tuples()).collect();
+    let histograms: Vec<Histogram> = hands.iter().map(|h| h.to_histogram()).collect();
+    let mut buffer: Vec<Decomposition> = Vec::with_capacity(64);
+
+    // once the buffer has grown, the fixed-size composer shouldn't allocate at all
+    let vec_allocations = allocations(|| for hand in &counted { black_box(hand.compose_tiles(Some(1), true)); });
+    let fixed_allocations = allocations(|| for hand in &histograms { compose_histogram(hand, Some(1), true, &mut buffer); });
+    println!("allocations for {} hands: vec composer {vec_allocations}, fixed composer {fixed_allocations}", hands.len());
+    assert_eq!(fixed_allocations, 0);
+
+    c.bench_function("vec composer", |b| b.iter(|| {
+        for hand in &counted { black_box(black_box(hand).compose_tiles(Some(1), true)); }
+    }));
+
+    c.bench_function("fixed composer", |b| b.iter(|| {
+        for hand in &histograms { compose_histogram(black_box(hand), Some(1), true, &mut buffer); black_box(&buffer); }
+    }));
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -1,74 +1,97 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mahjong_core::agari::is_agari;
+use mahjong_core::composer::Composer;
+use mahjong_core::conversions::ConvertStrings;
+use mahjong_core::tiles::{Tile, ToHistogram, Histogram};
     
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("jpml2022 scoring #1", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4", "", "p9", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4", "", "p9", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4", "", "p9", 's', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4", "", "p9", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4", "", "p9", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4", "", "p9", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4", "", "p9", 's', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4", "", "p9", 's', 'e', 'c', "", "", "", 0, "JPML2022");
     }));
 
     c.bench_function("jpml2022 scoring #2", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8", "", "s7", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8", "", "s7", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8", "", "s7", 's', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8", "", "s7", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8", "", "s7", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8", "", "s7", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8", "", "s7", 's', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8", "", "s7", 's', 'e', 'c', "", "", "", 0, "JPML2022");
     }));
 
     c.bench_function("jpml2022 scoring #3", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("m3,m5,m6,m7,m8,m8,m8", "p8,p8,p8|m2,m2,m2", "m3", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m3,m5,m6,m7,m8,m8,m8", "p8,p8,p8|m2,m2,m2", "m3", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m3,m5,m6,m7,m8,m8,m8", "p8,p8,p8|m2,m2,m2", "m3", 's', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m3,m5,m6,m7,m8,m8,m8", "p8,p8,p8|m2,m2,m2", "m3", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m3,m5,m6,m7,m8,m8,m8", "p8,p8,p8|m2,m2,m2", "m3", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m3,m5,m6,m7,m8,m8,m8", "p8,p8,p8|m2,m2,m2", "m3", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m3,m5,m6,m7,m8,m8,m8", "p8,p8,p8|m2,m2,m2", "m3", 's', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m3,m5,m6,m7,m8,m8,m8", "p8,p8,p8|m2,m2,m2", "m3", 's', 'e', 'c', "", "", "", 0, "JPML2022");
     }));
 
     c.bench_function("jpml2022 scoring #4", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("p2,p2,we,we", "m8,m8,m8|p3,p3,p3|s8,s8,s8", "p2", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p2,p2,we,we", "m8,m8,m8|p3,p3,p3|s8,s8,s8", "p2", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p2,p2,we,we", "m8,m8,m8|p3,p3,p3|s8,s8,s8", "p2", 's', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p2,p2,we,we", "m8,m8,m8|p3,p3,p3|s8,s8,s8", "p2", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p2,p2,we,we", "m8,m8,m8|p3,p3,p3|s8,s8,s8", "p2", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p2,p2,we,we", "m8,m8,m8|p3,p3,p3|s8,s8,s8", "p2", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p2,p2,we,we", "m8,m8,m8|p3,p3,p3|s8,s8,s8", "p2", 's', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p2,p2,we,we", "m8,m8,m8|p3,p3,p3|s8,s8,s8", "p2", 's', 'e', 'c', "", "", "", 0, "JPML2022");
     }));
 
     c.bench_function("jpml2022 scoring #5", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("p2,p3,p4,p5,p6,p7,p7,p7,we,we", "ws,ws,ws", "p1", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p2,p3,p4,p5,p6,p7,p7,p7,we,we", "ws,ws,ws", "p1", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p2,p3,p4,p5,p6,p7,p7,p7,we,we", "ws,ws,ws", "p1", 's', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p2,p3,p4,p5,p6,p7,p7,p7,we,we", "ws,ws,ws", "p1", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p2,p3,p4,p5,p6,p7,p7,p7,we,we", "ws,ws,ws", "p1", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p2,p3,p4,p5,p6,p7,p7,p7,we,we", "ws,ws,ws", "p1", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p2,p3,p4,p5,p6,p7,p7,p7,we,we", "ws,ws,ws", "p1", 's', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p2,p3,p4,p5,p6,p7,p7,p7,we,we", "ws,ws,ws", "p1", 's', 'e', 'c', "", "", "", 0, "JPML2022");
     }));
 
     c.bench_function("jpml2022 scoring #6", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("p3,p3,p4,p4,p5,p5,p2", "s8,s8,s8|!s7,s7,s7,s7", "p2", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p3,p3,p4,p4,p5,p5,p2", "s8,s8,s8|!s7,s7,s7,s7", "p2", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p3,p3,p4,p4,p5,p5,p2", "s8,s8,s8|!s7,s7,s7,s7", "p2", 's', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("p3,p3,p4,p4,p5,p5,p2", "s8,s8,s8|!s7,s7,s7,s7", "p2", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p3,p3,p4,p4,p5,p5,p2", "s8,s8,s8|!s7,s7,s7,s7", "p2", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p3,p3,p4,p4,p5,p5,p2", "s8,s8,s8|!s7,s7,s7,s7", "p2", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p3,p3,p4,p4,p5,p5,p2", "s8,s8,s8|!s7,s7,s7,s7", "p2", 's', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("p3,p3,p4,p4,p5,p5,p2", "s8,s8,s8|!s7,s7,s7,s7", "p2", 's', 'e', 'c', "", "", "", 0, "JPML2022");
     }));
 
     c.bench_function("jpml2022 scoring #7", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("m2,m2,m4,m4,m3,s7,s7,s7,ws,ws", "!wn,wn,wn,wn", "m3", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m2,m2,m4,m4,m3,s7,s7,s7,ws,ws", "!wn,wn,wn,wn", "m3", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m2,m2,m4,m4,m3,s7,s7,s7,ws,ws", "!wn,wn,wn,wn", "m3", 's', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m2,m2,m4,m4,m3,s7,s7,s7,ws,ws", "!wn,wn,wn,wn", "m3", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m2,m4,m4,m3,s7,s7,s7,ws,ws", "!wn,wn,wn,wn", "m3", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m2,m4,m4,m3,s7,s7,s7,ws,ws", "!wn,wn,wn,wn", "m3", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m2,m4,m4,m3,s7,s7,s7,ws,ws", "!wn,wn,wn,wn", "m3", 's', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m2,m4,m4,m3,s7,s7,s7,ws,ws", "!wn,wn,wn,wn", "m3", 's', 'e', 'c', "", "", "", 0, "JPML2022");
     }));
 
     c.bench_function("jpml2022 scoring #8", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("s1,s1,s1,s2,s4,we,we", "m9,m9,m9|!dr,dr,dr,dr", "s3", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("s1,s1,s1,s2,s4,we,we", "m9,m9,m9|!dr,dr,dr,dr", "s3", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("s1,s1,s1,s2,s4,we,we", "m9,m9,m9|!dr,dr,dr,dr", "s3", 's', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("s1,s1,s1,s2,s4,we,we", "m9,m9,m9|!dr,dr,dr,dr", "s3", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("s1,s1,s1,s2,s4,we,we", "m9,m9,m9|!dr,dr,dr,dr", "s3", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("s1,s1,s1,s2,s4,we,we", "m9,m9,m9|!dr,dr,dr,dr", "s3", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("s1,s1,s1,s2,s4,we,we", "m9,m9,m9|!dr,dr,dr,dr", "s3", 's', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("s1,s1,s1,s2,s4,we,we", "m9,m9,m9|!dr,dr,dr,dr", "s3", 's', 'e', 'c', "", "", "", 0, "JPML2022");
     }));
 
     c.bench_function("jpml2022 scoring #9", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("m7,m8,m9,p7,p8,p8,p8", "!ws,ws,ws,ws|!dg,dg,dg,dg", "p9", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m7,m8,m9,p7,p8,p8,p8", "!ws,ws,ws,ws|!dg,dg,dg,dg", "p9", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m7,m8,m9,p7,p8,p8,p8", "!ws,ws,ws,ws|!dg,dg,dg,dg", "p9", 's', 'e', 'd', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m7,m8,m9,p7,p8,p8,p8", "!ws,ws,ws,ws|!dg,dg,dg,dg", "p9", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m7,m8,m9,p7,p8,p8,p8", "!ws,ws,ws,ws|!dg,dg,dg,dg", "p9", 'e', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m7,m8,m9,p7,p8,p8,p8", "!ws,ws,ws,ws|!dg,dg,dg,dg", "p9", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m7,m8,m9,p7,p8,p8,p8", "!ws,ws,ws,ws|!dg,dg,dg,dg", "p9", 's', 'e', 'd', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m7,m8,m9,p7,p8,p8,p8", "!ws,ws,ws,ws|!dg,dg,dg,dg", "p9", 's', 'e', 'c', "", "", "", 0, "JPML2022");
     }));
 
     c.bench_function("jpml2022 scoring #10", |b| b.iter(|| {
-        let _ = core::score_hand_from_str("m2,m3,m4,m4,m5,m6,m7,s8,s8,s8", "we,we,we,we", "m1", 'e', 'e', 'k', "", "", "rinshan", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m2,m3,m4,m4,m5,m6,m7,s8,s8,s8", "we,we,we,we", "m1", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m2,m3,m4,m4,m5,m6,m7,s8,s8,s8", "we,we,we,we", "m1", 's', 'e', 'k', "", "", "rinshan", 0, "JPML2022");
-        let _ = core::score_hand_from_str("m2,m3,m4,m4,m5,m6,m7,s8,s8,s8", "we,we,we,we", "m1", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m3,m4,m4,m5,m6,m7,s8,s8,s8", "we,we,we,we", "m1", 'e', 'e', 'k', "", "", "rinshan", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m3,m4,m4,m5,m6,m7,s8,s8,s8", "we,we,we,we", "m1", 'e', 'e', 'c', "", "", "", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m3,m4,m4,m5,m6,m7,s8,s8,s8", "we,we,we,we", "m1", 's', 'e', 'k', "", "", "rinshan", 0, "JPML2022");
+        let _ = mahjong_core::score_hand_from_str("m2,m3,m4,m4,m5,m6,m7,s8,s8,s8", "we,we,we,we", "m1", 's', 'e', 'c', "", "", "", 0, "JPML2022");
+    }));
+
+    // the same complete and incomplete hands, checked by the lookup table and by the recursive composer
+    let hands: Vec<Vec<Tile>> = ["p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4,p9", "m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8,s7",
+        "m1,m1,m1,m2,m3,m4,m5,m6,m7,m8,m9,m9,m9,m5", "m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn,wn"]
+        .iter().map(|h| h.to_tiles().unwrap()).collect();
+    let histograms: Vec<Histogram> = hands.iter().map(|h| h.to_histogram()).collect();
+
+    c.bench_function("agari lookup", |b| b.iter(|| {
+        for hand in &histograms { black_box(is_agari(black_box(hand))); }
+    }));
+
+    c.bench_function("agari composer", |b| b.iter(|| {
+        for hand in &hands { black_box(black_box(hand).compose_tiles(None, true)); }
+    }));
+
+    #[cfg(feature = "shanten-tables")]
+    c.bench_function("shanten lookup", |b| b.iter(|| {
+        for hand in &histograms { black_box(mahjong_core::shanten::standard_shanten(black_box(hand), 0)); }
     }));
 }
 
@@ -0,0 +1,263 @@
+/*
+Decides between declaring riichi and staying dama with a tenpai hand, by comparing the expected points of each.
+
+Each winning tile is scored through Hand::new, by tsumo and by ron, with and without riichi; tiles are weighted by how
+many copies are still live. Riichi adds ura dora, estimated from the hand's tiles and the unseen tiles under the
+markers, and ippatsu, which is about as likely as winning within the first go-around. Dora count toward both values.
+
+Win chances come from a simple model: each remaining draw gives a tsumo chance of live / unseen tiles, and the three
+opponents each give a ron chance on the same odds, scaled by how willing they are to discard into the hand. Opponents
+play around a riichi, so riichi wins less often, and a larger share of its wins are by tsumo. Playing around it mostly
+means suji, which only protects against ryanmen: tiles the hand waits on any other way are dealt in more readily. The
+riichi stick is lost unless the hand wins. None of this accounts for having to push dangerous tiles after riichi, or for changing the wait.
+*/
+
+use crate::tiles::{Tile, TileId, Wind, DoraTrait, Histogram, TileVecTrait};
+use crate::state::{Game, Seat, TileType, WinType, SeatHelper, SeatAccess};
+use crate::hand::{Hand, HandTrait, Wait, WaitShape, read_waits};
+use crate::yaku::{Yaku, YAKUMAN, YakuHelpers};
+use crate::scoring::{Payment, calc_base_points, calc_player_split};
+use crate::errors::mahjong_errors::HandError;
+use crate::rulesets::RuleVariations;
+
+// Each opponent's chance to discard a waited tile, relative to the chance of drawing one.
+const DAMA_RON_RATE: f32 = 0.6;
+const RIICHI_RON_RATE: f32 = 0.25;
+// How much more readily a riichi's waited tile is dealt in when suji doesn't protect against it.
+const UNREAD_WAIT_RATE: f32 = 1.5;
+// Own draws in a hand, on average, before the live wall runs out.
+const DRAWS_PER_HAND: u8 = 18;
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+// What the advisor needs to know about the table, beyond the hand and the game state.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Table<'a> {
+    pub seen: &'a Histogram,    // every copy the player can see, as kept by tracker::TileTracker::seen()
+    pub turn: u8,               // how many times the player has drawn this hand
+    pub scores: [i32; 4],       // indexed by seat wind (east, south, west, north)
+    pub deposits: u8,           // riichi sticks already on the table
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Choice {Riichi, Dama}
+
+// How one choice is expected to play out. Points are the winner's total gain, including repeats and deposits.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Estimate {
+    pub win_chance: f32,
+    pub win_value: f32,     // average points for a win
+    pub ev: f32,            // expected points, after the riichi stick
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RiichiAdvice {
+    pub choice: Choice,
+    pub wait: Wait,
+    pub riichi: Option<Estimate>,   // None when riichi can't be declared: the hand is open, the player can't pay for it, or fewer than four tiles are left
+    pub dama: Estimate,
+    pub ura_dora: f32,              // expected ura dora, if the hand wins in riichi
+}
+
+///////////////
+// functions //
+///////////////
+
+// Advises whether to riichi a 13 tile tenpai hand.
+// seat_state.special_yaku shouldn't include Riichi; anything else in it (ie haitei) counts toward both choices.
+//
+// # Errors
+//
+// Returns ValueError if the hand isn't tenpai.
+pub fn advise_riichi(game_state: &Game, seat_state: &Seat, table: &Table) -> Result<RiichiAdvice, HandError> {
+    let wait = read_waits(&seat_state.closed_tiles, &seat_state.called_melds).ok_or(HandError::ValueError)?;
+    let live: Vec<(Tile, f32, &[WaitShape])> = wait.tiles.iter().zip(&wait.tile_shapes)
+        .map(|(t, shapes)| (*t, f32::from(4_u8.saturating_sub(table.seen[TileId::from(*t).index()])), shapes.as_slice()))
+        .filter(|(_, copies, _)| *copies > 0.0)
+        .collect();
+
+    let unseen = (136 - table.seen.iter().map(|c| u32::from(*c)).sum::<u32>()).max(1) as f32;
+    let live_total: f32 = live.iter().map(|(_, copies, _)| copies).sum();
+    // live copies as opponents see them after a riichi
+    let riichi_live: f32 = live.iter()
+        .map(|(_, copies, shapes)| if shapes.contains(&WaitShape::Ryanmen) { *copies } else { copies * UNREAD_WAIT_RATE })
+        .sum();
+    let draws = DRAWS_PER_HAND.saturating_sub(table.turn);
+    // the winning tile can be ura dora too, so the estimate is averaged over the live tiles
+    let held = seat_state.all_tiles();
+    let ura_dora = if live_total == 0.0 { expected_ura(game_state, &held, table.seen, unseen) }
+        else { live.iter().map(|(tile, copies, _)| copies * expected_ura(game_state, &[held.as_slice(), &[*tile]].concat(), table.seen, unseen)).sum::<f32>() / live_total };
+    let sticks = f32::from(table.deposits) * 1000.0;
+
+    // average points over the live tiles, by tsumo and by ron
+    let value = |extra: &[Yaku], extra_han: f32| -> [f32; 2] {
+        [TileType::Draw, TileType::Call].map(|tile_type| {
+            if live_total == 0.0 { return 0.0 }
+            live.iter().map(|(tile, copies, _)| {
+                let points = |han: u8| win_points(game_state, seat_state, *tile, tile_type, extra, han);
+                let (low, high) = (points(extra_han as u8), points(extra_han as u8 + 1));
+                copies * (low + (high - low) * extra_han.fract())
+            }).sum::<f32>() / live_total
+        })
+    };
+
+    let dama = {
+        let [tsumo, ron] = value(&[], 0.0);
+        estimate(live_total / unseen, live_total / unseen, draws, DAMA_RON_RATE, tsumo, ron, sticks, 0.0)
+    };
+
+    let can_riichi = !is_open(seat_state) && table.scores[seat_state.seat_wind as usize] >= 1000
+        && game_state.tiles_left.is_none_or(|tiles| tiles >= 4);
+    let riichi = can_riichi.then(|| {
+        let (per_draw, per_discard) = (live_total / unseen, riichi_live / unseen);
+        let [tsumo, ron] = value(&[Yaku::Riichi], ura_dora);
+        let without_ippatsu = estimate(per_draw, per_discard, draws, RIICHI_RON_RATE, tsumo, ron, sticks + 1000.0, 1000.0);
+        if !game_state.ruleset.allows_ippatsu() || without_ippatsu.win_chance == 0.0 { return without_ippatsu }
+
+        // ippatsu needs a win within one go-around, which is the first step of the same model
+        let ippatsu = estimate(per_draw, per_discard, 1, RIICHI_RON_RATE, 1.0, 1.0, 0.0, 0.0).win_chance / without_ippatsu.win_chance;
+        let [ippatsu_tsumo, ippatsu_ron] = value(&[Yaku::Riichi, Yaku::Ippatsu], ura_dora);
+        estimate(per_draw, per_discard, draws, RIICHI_RON_RATE,
+            tsumo + (ippatsu_tsumo - tsumo) * ippatsu, ron + (ippatsu_ron - ron) * ippatsu, sticks + 1000.0, 1000.0)
+    });
+
+    let choice = match riichi {
+        Some(riichi) if riichi.ev > dama.ev => Choice::Riichi,
+        _ => Choice::Dama,
+    };
+    Ok(RiichiAdvice { choice, wait, riichi, dama, ura_dora: if riichi.is_some() { ura_dora } else { 0.0 } })
+}
+
+// per_draw is the chance of drawing a waited tile, and per_discard the same for an opponent's discard, before ron_rate.
+#[allow(clippy::too_many_arguments)]
+fn estimate(per_draw: f32, per_discard: f32, draws: u8, ron_rate: f32, tsumo: f32, ron: f32, sticks: f32, cost: f32) -> Estimate {
+    let per_ron = 3.0 * ron_rate * per_discard;
+    let per_turn = (per_draw + per_ron).min(1.0);
+    let win_chance = 1.0 - (1.0 - per_turn).powi(i32::from(draws));
+    let tsumo_share = if per_turn == 0.0 { 1.0 } else { per_draw / (per_draw + per_ron) };
+    // a hand with no yaku on some tiles can still be worth nothing
+    let win_value = if tsumo == 0.0 && ron == 0.0 { 0.0 } else { tsumo * tsumo_share + ron * (1.0 - tsumo_share) + sticks };
+    let win_chance = if win_value == 0.0 { 0.0 } else { win_chance };
+    Estimate { win_chance, win_value, ev: win_chance * win_value - cost }
+}
+
+// Expected ura dora for each marker: the chance that a marker is the tile before one in hand, per copy held.
+// held should include the winning tile.
+fn expected_ura(game_state: &Game, held: &[Tile], seen: &Histogram, unseen: f32) -> f32 {
+    let markers = game_state.dora_markers.as_ref().map_or(1, Vec::len).max(1) as f32;
+    let per_marker: f32 = (0..34_u8).map(|id| {
+        let marker = Tile::from(TileId::new(id).expect("ids are under 34"));
+        f32::from(4_u8.saturating_sub(seen[usize::from(id)])) / unseen * held.count_occurrences(&marker.dora()) as f32
+    }).sum();
+    markers * per_marker
+}
+
+// Total points for winning on a tile, with extra yaku and extra han on top of the hand's own.
+// Hands without a yaku are worth nothing.
+fn win_points(game_state: &Game, seat_state: &Seat, tile: Tile, tile_type: TileType, extra: &[Yaku], extra_han: u8) -> f32 {
+    let mut special_yaku = seat_state.special_yaku.clone().unwrap_or_default();
+    special_yaku.extend_from_slice(extra);
+    let seat = Seat::new(seat_state.closed_tiles.clone(), seat_state.called_melds.clone(), seat_state.seat_wind,
+        Some(tile), Some(tile_type), Some(special_yaku));
+    let hand = Hand::new(game_state.clone(), seat);
+    if hand.han() == 0 { return 0.0 }
+
+    let yakuman = hand.yaku().contains_any(&YAKUMAN.to_vec());
+    let han = if yakuman { hand.han() } else { hand.han() + hand.dora() + extra_han };
+    let win_type = if tile_type == TileType::Draw { WinType::Tsumo } else { WinType::Ron };
+    let ruleset = game_state.ruleset;
+
+    let repeats = match win_type {
+        WinType::Tsumo => ruleset.repeat_payment_tsumo(game_state.repeats) * 3,
+        WinType::Ron => ruleset.repeat_payment_ron(game_state.repeats),
+    };
+    let total = calc_base_points(han, hand.fu(), hand.yaku(), ruleset)
+        .and_then(|base| calc_player_split(base, seat_state.seat_wind == Wind::East, win_type, 0))
+        .map_or(0, |payment| match payment {
+            Payment::DealerTsumo(each) => each * 3,
+            Payment::Tsumo { dealer, non_dealer } => dealer + non_dealer * 2,
+            Payment::Ron(points) => points,
+        });
+    total as f32 + repeats as f32
+}
+
+fn is_open(seat_state: &Seat) -> bool {
+    seat_state.called_melds.as_ref().is_some_and(|melds| melds.iter().any(|m| m.is_open))
+}
+
+///////////
+// tests //
+///////////
+
+mod tests {
+    use super::*;
+    use crate::conversions::ConvertStrings;
+    use crate::rulesets::RiichiRuleset;
+    use crate::state::GameHelper;
+    use crate::tiles::ToHistogram;
+
+    fn seat(closed: &str, calls: &str) -> Seat {
+        Seat::new(closed.to_tiles().unwrap(), calls.to_calls().ok(), Wind::South, None, None, None)
+    }
+
+    #[test]
+    fn test_riichi_advice(){
+        let game = Game::new(RiichiRuleset::Default, Wind::East, 0, "we".to_tiles().ok(), None);
+        let seen = |seat: &Seat, others: &str| [seat.all_tiles(), others.to_tiles().unwrap()].concat().to_histogram();
+
+        // a good wait, and no yaku without riichi besides menzen tsumo
+        let hand = seat("m1,m2,m3,m5,m6,p2,p3,p4,p7,p8,p9,s9,s9", "");
+        let visible = seen(&hand, "we");
+        let advice = advise_riichi(&game, &hand, &Table { seen: &visible, turn: 3, scores: [25000; 4], deposits: 0 }).unwrap();
+        assert_eq!((advice.choice, advice.wait.tiles.len()), (Choice::Riichi, 2));
+        assert!(advice.dama.win_value < advice.riichi.unwrap().win_value);
+        assert!(advice.riichi.unwrap().win_chance > 0.5 && advice.ura_dora > 0.0);
+
+        // a haneman dama gains less from riichi than it loses in win chance
+        let hand = seat("m2,m3,m4,m4,m5,m6,m6,m7,m8,m2,m2,m5,m7", "");
+        let visible = seen(&hand, "we,m6,m6");
+        let advice = advise_riichi(&game, &hand, &Table { seen: &visible, turn: 12, scores: [25000; 4], deposits: 0 }).unwrap();
+        assert_eq!(advice.choice, Choice::Dama);
+        assert!(advice.dama.win_value >= 12000.0);
+        assert!(advice.riichi.unwrap().win_chance < advice.dama.win_chance);
+
+        // open hands and players who can't pay for the stick stay dama
+        let open = seat("m2,m3,m4,p5,p6,dr,dr", "s2,s3,s4@w|dg,dg,dg@n");
+        let visible = seen(&open, "we");
+        let advice = advise_riichi(&game, &open, &Table { seen: &visible, turn: 3, scores: [25000; 4], deposits: 0 }).unwrap();
+        assert_eq!((advice.choice, advice.riichi), (Choice::Dama, None));
+        assert!(advice.dama.ev > 0.0);
+        let hand = seat("m1,m2,m3,m5,m6,p2,p3,p4,p7,p8,p9,s9,s9", "");
+        let visible = seen(&hand, "we");
+        let broke = advise_riichi(&game, &hand, &Table { seen: &visible, turn: 3, scores: [25000, 900, 25000, 25000], deposits: 0 }).unwrap();
+        assert_eq!((broke.choice, broke.riichi), (Choice::Dama, None));
+        // nor can riichi be declared without four tiles left to draw
+        let late = Game { tiles_left: Some(3), ..game.clone() };
+        let advice = advise_riichi(&late, &hand, &Table { seen: &visible, turn: 3, scores: [25000; 4], deposits: 0 }).unwrap();
+        assert_eq!((advice.choice, advice.riichi), (Choice::Dama, None));
+        let advice = advise_riichi(&Game { tiles_left: Some(4), ..game.clone() }, &hand, &Table { seen: &visible, turn: 3, scores: [25000; 4], deposits: 0 }).unwrap();
+        assert!(advice.riichi.is_some());
+
+        // the winning tile can be ura dora: a tanki on a tile the hand doesn't hold is worth more ura than the hand alone
+        let tanki = seat("m1,m2,m3,m4,m5,m6,p2,p3,p4,p7,p8,p9,we", "");
+        let visible = seen(&tanki, "we");
+        let advice = advise_riichi(&game, &tanki, &Table { seen: &visible, turn: 3, scores: [25000; 4], deposits: 0 }).unwrap();
+        let without_wait = expected_ura(&game, &tanki.all_tiles(), &visible, 136.0 - visible.iter().map(|c| f32::from(*c)).sum::<f32>());
+        assert!(advice.ura_dora > without_wait);
+
+        // suji doesn't help against a kanchan, so riichi keeps more of its win chance than on a ryanmen
+        let chance = |closed: &str, others: &str| {
+            let hand = seat(closed, "");
+            let visible = seen(&hand, others);
+            let advice = advise_riichi(&game, &hand, &Table { seen: &visible, turn: 3, scores: [25000; 4], deposits: 0 }).unwrap();
+            advice.riichi.unwrap().win_chance / advice.dama.win_chance
+        };
+        // four live copies each
+        assert!(chance("m1,m2,m3,m5,m7,p2,p3,p4,p7,p8,p9,s9,s9", "we,p1,p1,s1,s1") > chance("m1,m2,m3,m5,m6,p2,p3,p4,p7,p8,p9,s9,s9", "we,m4,m4,m7,m7"));
+
+        let noten = seat("m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn", "");
+        assert_eq!(advise_riichi(&game, &noten, &Table { seen: &visible, turn: 3, scores: [25000; 4], deposits: 0 }), Err(HandError::ValueError));
+    }
+}
@@ -0,0 +1,235 @@
+/*
+Fast agari (winning hand) checks, for when a simulation only needs to know whether a hand is complete.
+
+Each suit is encoded as a key, with one base-5 digit per number, and looked up in a table of every suit pattern that can
+be split into melds and at most one pair. A standard hand is complete when every suit's pattern is in the table and
+exactly one suit (or honor) holds the pair; honors don't form sequences, so they're checked directly.
+The table only keeps one decomposition per pattern; use Composer::compose_tiles when every reading is needed.
+*/
+
+use crate::tiles::{TileId, Histogram};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+// One way of splitting a suit into melds and a pair. Numbers are indexes within the suit, from 0 to 8.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+struct SuitShape {
+    pair: Option<u8>,
+    triplets: u16,          // bitmask of numbers
+    sequences: [u8; 7],     // number of sequences starting at each number
+}
+
+// A basic reading of a complete closed hand. Tiles are given by TileId; sequences by their lowest tile.
+#[derive(Debug, PartialEq, Clone)]
+pub enum AgariShape {
+    Standard {
+        pair: TileId,
+        triplets: Vec<TileId>,
+        sequences: Vec<TileId>,
+    },
+    Chiitoi([TileId; 7]),
+    Kokushi { pair: TileId },
+}
+
+static TERMINALS_AND_HONORS: [usize; 13] = [0, 8, 9, 17, 18, 26, 27, 28, 29, 30, 31, 32, 33];
+
+///////////////
+// functions //
+///////////////
+
+// Returns whether a closed hand (3n+2 tiles, without called melds) is complete, as a standard hand, chiitoi, or kokushi.
+pub fn is_agari(hand: &Histogram) -> bool {
+    is_standard(hand) || is_chiitoi(hand) || is_kokushi(hand)
+}
+
+// Returns one reading of a complete closed hand, preferring a standard hand over chiitoi.
+pub fn agari_shape(hand: &Histogram) -> Option<AgariShape> {
+    if let Some(shape) = standard_shape(hand) { Some(shape) }
+    else if is_chiitoi(hand) {
+        let pairs: Vec<TileId> = ids(hand).filter(|(_, count)| *count == 2).map(|(id, _)| id).collect();
+        Some(AgariShape::Chiitoi(pairs.try_into().ok()?))
+    }
+    else if is_kokushi(hand) {
+        Some(AgariShape::Kokushi { pair: ids(hand).find(|(_, count)| *count == 2).map(|(id, _)| id)? })
+    }
+    else { None }
+}
+
+fn is_standard(hand: &Histogram) -> bool {
+    let table = suit_table();
+    let mut pairs = 0;
+
+    for suit in hand[..27].chunks(9) {
+        match suit_key(suit) {
+            Some((_, 0)) => (),
+            Some((key, count)) if count % 3 != 1 && table.contains_key(&key) => if count % 3 == 2 { pairs += 1 },
+            _ => return false,
+        }
+    }
+    for count in &hand[27..] {
+        match count {
+            0 | 3 => (),
+            2 => pairs += 1,
+            _ => return false,
+        }
+    }
+    pairs == 1
+}
+
+fn standard_shape(hand: &Histogram) -> Option<AgariShape> {
+    if !is_standard(hand) { return None }
+    let table = suit_table();
+    let id = |index: usize| TileId::new(u8::try_from(index).ok()?);
+
+    let mut pair: Option<TileId> = None;
+    let mut triplets: Vec<TileId> = Vec::with_capacity(4);
+    let mut sequences: Vec<TileId> = Vec::with_capacity(4);
+
+    for (suit_index, suit) in hand[..27].chunks(9).enumerate() {
+        let (key, _) = suit_key(suit)?;
+        let Some(shape) = table.get(&key) else { continue };
+        let base = suit_index * 9;
+
+        if let Some(number) = shape.pair { pair = id(base + usize::from(number)) }
+        for number in 0..9 {
+            if shape.triplets & (1 << number) != 0 { triplets.push(id(base + number)?) }
+        }
+        for (number, count) in shape.sequences.iter().enumerate() {
+            for _ in 0..*count { sequences.push(id(base + number)?) }
+        }
+    }
+    for (index, count) in hand.iter().enumerate().skip(27) {
+        match count {
+            2 => pair = id(index),
+            3 => triplets.push(id(index)?),
+            _ => (),
+        }
+    }
+    Some(AgariShape::Standard { pair: pair?, triplets, sequences })
+}
+
+fn is_chiitoi(hand: &Histogram) -> bool {
+    hand.iter().all(|count| *count == 0 || *count == 2) && hand.iter().filter(|count| **count == 2).count() == 7
+}
+
+fn is_kokushi(hand: &Histogram) -> bool {
+    TERMINALS_AND_HONORS.iter().all(|i| hand[*i] >= 1)
+        && TERMINALS_AND_HONORS.iter().map(|i| hand[*i]).sum::<u8>() == 14
+        && hand.iter().sum::<u8>() == 14
+}
+
+fn ids(hand: &Histogram) -> impl Iterator<Item = (TileId, u8)> + '_ {
+    hand.iter().enumerate().filter_map(|(index, count)| Some((TileId::new(u8::try_from(index).ok()?)?, *count)))
+}
+
+// Encodes a suit's counts in base 5, returning the key and the number of tiles. None if any count is over 4.
+fn suit_key(suit: &[u8]) -> Option<(u32, u8)> {
+    suit.iter().try_fold((0, 0), |(key, total), count| {
+        if *count > 4 { None } else { Some((key * 5 + u32::from(*count), total + count)) }
+    })
+}
+
+// Every suit pattern of up to four melds and an optional pair, built on first use.
+fn suit_table() -> &'static HashMap<u32, SuitShape> {
+    static TABLE: OnceLock<HashMap<u32, SuitShape>> = OnceLock::new();
+
+    // melds 0-8 are triplets, 9-15 are sequences. they're added in order so each combination is only visited once.
+    fn add_melds(counts: &mut [u8; 9], shape: SuitShape, first_meld: usize, melds: u8, table: &mut HashMap<u32, SuitShape>) {
+        if let Some((key, _)) = suit_key(counts) { table.entry(key).or_insert(shape); }
+        if melds == 4 { return }
+
+        for meld in first_meld..16 {
+            let numbers: &[usize] = if meld < 9 { &[meld; 3] } else { &[meld - 9, meld - 8, meld - 7] };
+            for n in numbers { counts[*n] += 1 }
+
+            if numbers.iter().all(|n| counts[*n] <= 4) {
+                let mut next = shape;
+                if meld < 9 { next.triplets |= 1 << meld } else { next.sequences[meld - 9] += 1 }
+                add_melds(counts, next, meld, melds + 1, table);
+            }
+            for n in numbers { counts[*n] -= 1 }
+        }
+    }
+
+    TABLE.get_or_init(|| {
+        let mut table: HashMap<u32, SuitShape> = HashMap::new();
+        let mut counts = [0; 9];
+
+        add_melds(&mut counts, SuitShape::default(), 0, 0, &mut table);
+        for pair in 0..9 {
+            counts[pair] = 2;
+            add_melds(&mut counts, SuitShape { pair: u8::try_from(pair).ok(), ..SuitShape::default() }, 0, 0, &mut table);
+            counts[pair] = 0;
+        }
+        table
+    })
+}
+
+///////////
+// tests //
+///////////
+
+mod tests {
+    use super::*;
+    use crate::tiles::{Tile, ToHistogram, all_tile_kinds};
+    use crate::conversions::ConvertStrings;
+    use crate::hand::read_waits;
+
+    #[test]
+    fn test_agari_shapes(){
+        let hand = "m1,m1,m1,m2,m3,m4,m5,m6,m7,m8,m9,m9,m9,m5".to_tiles().unwrap().to_histogram();
+        assert!(is_agari(&hand));
+        assert!(matches!(agari_shape(&hand), Some(AgariShape::Standard { .. })));
+
+        let hand = "p2,p2,p3,p3,p4,p4,s5,s5,s6,s6,s7,s7,we,we".to_tiles().unwrap().to_histogram();
+        assert!(matches!(agari_shape(&hand), Some(AgariShape::Standard { .. })));
+
+        let hand = "p2,p2,p3,p3,p4,p4,s5,s5,s7,s7,dw,dw,we,ws".to_tiles().unwrap().to_histogram();
+        assert!(!is_agari(&hand));
+
+        let hand = "m1,m1,m9,p9,p1,s1,s9,dw,dg,dr,we,ws,ww,wn".to_tiles().unwrap().to_histogram();
+        assert_eq!(agari_shape(&hand), Some(AgariShape::Kokushi { pair: TileId::new(0).unwrap() }));
+
+        let hand = "m1,m1,m3,m3,p1,p1,s9,s9,dw,dw,dr,dr,wn,wn".to_tiles().unwrap().to_histogram();
+        assert!(matches!(agari_shape(&hand), Some(AgariShape::Chiitoi(_))));
+
+        // chiitoi doesn't allow the same pair twice
+        let hand = "m1,m1,m1,m1,p1,p1,s9,s9,dw,dw,dr,dr,wn,wn".to_tiles().unwrap().to_histogram();
+        assert!(!is_agari(&hand));
+
+        let hand = "dr,dr,dr,dg,dg,dg,dw,dw,dw,we,we,we,wn,wn".to_tiles().unwrap().to_histogram();
+        assert_eq!(agari_shape(&hand), Some(AgariShape::Standard {
+            pair: TileId::from("wn".to_tile().unwrap()),
+            triplets: ["we", "dw", "dg", "dr"].iter().map(|t| TileId::from(t.to_tile().unwrap())).collect(),
+            sequences: Vec::new(),
+        }));
+    }
+
+    // compares against the composer, over pseudo-random hands drawn from a few kinds so that many of them are tenpai
+    #[test]
+    fn test_agari_against_composer(){
+        let kinds: Vec<Tile> = "m1,m2,m3,m4,m5,m6,m7,m8,m9,p1,p2,dr".to_tiles().unwrap();
+        let mut seed: u64 = 0x5eed;
+        let mut next = || { seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407); (seed >> 33) as usize };
+
+        for _ in 0..300 {
+            let mut tiles: Vec<Tile> = Vec::with_capacity(13);
+            while tiles.len() < 13 {
+                let tile = kinds[next() % kinds.len()];
+                if tiles.iter().filter(|t| **t == tile).count() < 4 { tiles.push(tile) }
+            }
+            let waits = read_waits(&tiles, &None).map(|w| w.tiles).unwrap_or_default();
+
+            for tile in all_tile_kinds() {
+                let mut hand = tiles.to_histogram();
+                hand[TileId::from(tile).index()] += 1;
+                if hand.iter().any(|c| *c > 4) { continue }
+                assert_eq!(is_agari(&hand), waits.contains(&tile), "{tiles:?} + {tile}");
+            }
+        }
+    }
+}
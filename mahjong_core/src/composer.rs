@@ -1,7 +1,31 @@
-use crate::tiles::{Tile, Dragon, Wind, Suit, TileIs, TileRelations};
+use crate::tiles::{Tile, Dragon, Wind, Suit, TileId, Histogram, TileIs, TileRelations, HistogramTrait, ToHistogram};
 use crate::errors::mahjong_errors::{HandError, ParsingError};
 use crate::hand::{HandShape, Meld, Pair, Wait, PartialHand, PartialHandTrait};
 use crate::conversions::ConvertTiles;
+use arrayvec::ArrayVec;
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+// A meld found by compose_histogram: a sequence starting at a tile kind, or a triplet of one.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum ComposedMeld {
+    Sequence(TileId),
+    Triplet(TileId),
+}
+
+// One reading of a histogram, held entirely on the stack. Each list is kept sorted, so equal readings compare equal.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Default)]
+pub struct Decomposition {
+    pub hanging_tiles: ArrayVec<TileId, 14>,
+    pub melds: ArrayVec<ComposedMeld, 4>,
+    pub pairs: ArrayVec<TileId, 7>,
+}
+
+// TileIds in the same order as Tile's Ord (man, sou, pin, dragons, winds), so tiles are visited like the Vec composer does.
+static TILE_ORDER: [usize; 34] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    9, 10, 11, 12, 13, 14, 15, 16, 17, 31, 32, 33, 27, 28, 29, 30];
 
 ////////////
 // traits //
@@ -47,13 +71,40 @@ impl Counter for Vec<Tile> {
     }
 }
 
+impl Counter for Histogram {
+    type T = Tile;
+
+    fn to_counted_tuples(&mut self) -> Vec<(usize, Self::T)> {
+        let mut vec: Vec<(usize, Tile)> = self.iter().enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(id, count)| (usize::from(*count), Tile::from(TileId::new(id as u8).expect("histograms have 34 kinds"))))
+            .collect();
+        vec.sort_by_key(|(_, tile)| *tile);
+        vec
+    }
+}
+
 impl Composer for Vec<Tile> {
     fn compose_tiles(&self, consider_waits: Option<u8>, consider_kokushi: bool) -> Option<Vec<PartialHand>> {
-        self.clone().to_counted_tuples().compose_tiles(consider_waits, consider_kokushi)
+        // composition only compares kinds, so red fives are left to the seat's tiles
+        self.to_histogram().compose_tiles(consider_waits, consider_kokushi)
     }
     fn unpack(&self) -> Vec<Tile> { self.clone() }
 }
 
+impl Composer for Histogram {
+    fn compose_tiles(&self, consider_waits: Option<u8>, consider_kokushi: bool) -> Option<Vec<PartialHand>> {
+        let mut readings: Vec<Decomposition> = Vec::new();
+        compose_histogram(self, consider_waits, consider_kokushi, &mut readings);
+        if readings.is_empty() { return None }
+        let mut partials: Vec<PartialHand> = readings.iter().map(Decomposition::to_partial_hand).collect();
+        partials.sort();
+        Some(partials)
+    }
+    fn unpack(&self) -> Vec<Tile> { self.to_tile_vec() }
+}
+
+// The original recursive composer, kept as the reference compose_histogram is tested against.
 impl Composer for Vec<(usize, Tile)> {
     fn compose_tiles(&self, consider_waits: Option<u8>, consider_kokushi: bool) -> Option<Vec<PartialHand>> {
         if self.is_empty() { None }
@@ -157,6 +208,93 @@ impl Composer for Vec<(usize, Tile)> {
     }
 }
 
+// Finds the same readings as Composer::compose_tiles, but without allocating: it recurses on a copy of the histogram,
+// and writes each reading into `out`, which is cleared first and can be reused between calls.
+// Readings are deduplicated regardless of the order their melds were found in. Hands over 14 tiles give no readings.
+pub fn compose_histogram(hand: &Histogram, consider_waits: Option<u8>, consider_kokushi: bool, out: &mut Vec<Decomposition>) {
+    out.clear();
+    let tiles = hand.tile_count();
+    if tiles > 14 || hand.iter().any(|count| *count > 4) { return }
+
+    let mut hand = *hand;
+    compose_step(&mut hand, 0, tiles, consider_waits, consider_kokushi, &mut Decomposition::default(), out);
+    out.sort_unstable();
+    out.dedup();
+}
+
+// Mirrors the branches of the Vec<(usize, Tile)> composer, for the lowest tile left from `start` (an index into TILE_ORDER).
+// When a branch finds nothing deeper, the reading so far is kept with the rest of the hand left hanging, as the original does.
+fn compose_step(hand: &mut Histogram, start: usize, tiles: usize, consider_waits: Option<u8>, consider_kokushi: bool,
+    path: &mut Decomposition, out: &mut Vec<Decomposition>) {
+    let Some(position) = (start..34).find(|p| hand[TILE_ORDER[*p]] > 0) else { return };
+    let id = TILE_ORDER[position];
+    let depth = hand[id];
+    let tile = TileId::new(id as u8).expect("TILE_ORDER only has valid ids");
+    let is_simple = id < 27 && (1..=7).contains(&(id % 9));
+
+    if id < 27 && id % 9 < 7 && hand[id + 1] > 0 && hand[id + 2] > 0 { // check for sequence
+        (id..id + 3).for_each(|i| hand[i] -= 1);
+        path.melds.push(ComposedMeld::Sequence(tile));
+        let found = out.len();
+        compose_step(hand, position, tiles - 3, consider_waits, false, path, out);
+        if out.len() == found && (consider_waits.is_some() || tiles == 3) { push_reading(hand, path, out) }
+        path.melds.pop();
+        (id..id + 3).for_each(|i| hand[i] += 1);
+    }
+    if depth >= 2 { // pair is possible
+        hand[id] -= 2;
+        path.pairs.push(tile);
+        let found = out.len();
+        compose_step(hand, position, tiles - 2, consider_waits, consider_kokushi && !is_simple, path, out);
+        if out.len() == found && (consider_waits.is_some() || consider_kokushi || tiles == 2) { push_reading(hand, path, out) }
+        path.pairs.pop();
+        hand[id] += 2;
+    }
+    if depth >= 3 { // triplet is possible
+        hand[id] -= 3;
+        path.melds.push(ComposedMeld::Triplet(tile));
+        let found = out.len();
+        compose_step(hand, position, tiles - 3, consider_waits, false, path, out);
+        if out.len() == found && (consider_waits.is_some() || tiles == 3) { push_reading(hand, path, out) }
+        path.melds.pop();
+        hand[id] += 3;
+    }
+    if depth == 1 && (consider_waits.is_some() || (consider_kokushi && !is_simple)) {
+        hand[id] = 0;
+        path.hanging_tiles.push(tile);
+        compose_step(hand, position + 1, tiles - 1, consider_waits.and_then(|n| if n > 1 { Some(n - 1) } else { None }), !is_simple, path, out);
+        path.hanging_tiles.pop();
+        hand[id] = 1;
+    }
+}
+
+fn push_reading(hand: &Histogram, path: &Decomposition, out: &mut Vec<Decomposition>) {
+    let mut reading = path.clone();
+    for (id, count) in hand.iter().enumerate() {
+        for _ in 0..*count { reading.hanging_tiles.push(TileId::new(id as u8).expect("histograms have 34 kinds")) }
+    }
+    reading.hanging_tiles.sort_unstable();
+    reading.melds.sort_unstable();
+    reading.pairs.sort_unstable();
+    out.push(reading);
+}
+
+impl Decomposition {
+    pub fn to_partial_hand(&self) -> PartialHand {
+        let tile = |id: TileId| Tile::from(id);
+        let next = |id: TileId, step: u8| Tile::from(TileId::new(u8::try_from(id.index()).unwrap_or(0) + step).expect("sequences stay inside a suit"));
+
+        PartialHand::new(
+            self.hanging_tiles.iter().map(|id| tile(*id)).collect(),
+            self.melds.iter().map(|meld| match meld {
+                ComposedMeld::Sequence(id) => [tile(*id), next(*id, 1), next(*id, 2)].make_meld(false),
+                ComposedMeld::Triplet(id) => [tile(*id); 3].make_meld(false),
+            }.expect("composed melds are valid")).collect(),
+            self.pairs.iter().map(|id| [tile(*id); 2].make_pair().expect("composed pairs are valid")).collect(),
+        )
+    }
+}
+
 ///////////
 // tests //
 ///////////
@@ -176,4 +314,58 @@ mod tests {
         assert_eq!(("dw,dw,dw,we,we,we").to_tiles().unwrap().to_counted_tuples().compose_tiles(None, false),
                 compose_tiles(&("dw,dw,dw,we,we,we").to_tiles().unwrap(), false, None, false));
     }
+
+    // the fixed-size composer should find the same readings as the Vec one, once both are put in the same order
+    #[test]
+    fn test_composing_without_allocating() {
+        use crate::tiles::{ToHistogram, all_tile_kinds};
+
+        let normalize = |partials: Vec<PartialHand>| {
+            let mut partials: Vec<PartialHand> = partials.into_iter().map(|mut p| { p.sort(); p.hanging_tiles.sort(); p }).collect();
+            partials.sort();
+            partials.dedup();
+            partials
+        };
+        let kinds = all_tile_kinds();
+        let mut seed: u64 = 0x0c0ffee;
+        let mut next = || { seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407); (seed >> 33) as usize };
+        let mut hands: Vec<Vec<Tile>> = ["m1,m1,m1,m2,m3,m4,m5,m6,m7,m8,m9,m9,m9,m5", "m1,m9,p1,p9,s1,s9,dw,dr,dg,we,ws,wn,ww,ww",
+            "p2,p2,p3,p3,p4,p4,s5,s5,s6,s6,s7,s7,we,we", "m1,m1,m1,m1,m2,m2,m2,m2,m3,m3,m3,m3,p5,p5"]
+            .iter().map(|h| h.to_tiles().unwrap()).collect();
+        for _ in 0..200 {
+            // mostly one suit, so that the hands have plenty of readings
+            let mut tiles: Vec<Tile> = Vec::with_capacity(14);
+            while tiles.len() < 14 {
+                let tile = if next() % 4 == 0 { kinds[next() % kinds.len()] } else { kinds[next() % 9] };
+                if tiles.iter().filter(|t| **t == tile).count() < 4 { tiles.push(tile) }
+            }
+            tiles.sort();
+            hands.push(tiles);
+        }
+
+        let mut buffer: Vec<Decomposition> = Vec::new();
+        let mut readings = 0;
+        for hand in &hands {
+            for (waits, kokushi) in [(None, false), (None, true), (Some(1), false), (Some(2), true)] {
+                compose_histogram(&hand.to_histogram(), waits, kokushi, &mut buffer);
+                readings += buffer.len();
+                assert_eq!(normalize(buffer.iter().map(Decomposition::to_partial_hand).collect()),
+                    normalize(hand.clone().to_counted_tuples().compose_tiles(waits, kokushi).unwrap_or_default()), "{hand:?} {waits:?} {kokushi}");
+            }
+        }
+        assert!(readings > hands.len() * 4);
+    }
+
+    #[test]
+    fn test_composing_histograms() {
+        use crate::tiles::ToHistogram;
+
+        for hand in ["m1,m2,m3,m4,m5,m6,p7,p8,p9,s2,s2,s3,s4,s5", "dw,dw,dw,we,we,we,s5,s5", "m1,m1,m1,m2,m3,m4,m5,m6,m7,m8,m9,m9,m9"] {
+            let mut tiles = hand.to_tiles().unwrap();
+            tiles.sort();
+            assert_eq!(tiles.to_histogram().compose_tiles(Some(1), false), tiles.compose_tiles(Some(1), false));
+            assert_eq!(tiles.compose_tiles(None, true).map(|p| p.len()), tiles.clone().to_counted_tuples().compose_tiles(None, true).map(|p| p.len()));
+            assert_eq!(tiles.to_histogram().unpack(), tiles);
+        }
+    }
 }
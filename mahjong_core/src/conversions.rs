@@ -1,7 +1,7 @@
 use crate::errors::mahjong_errors::{HandError, ParsingError};
 use crate::tiles::{Tile, Suit, Dragon, Wind, TileIs, TileRelations, TileVecTrait};
-use crate::hand::{Meld, Pair};
-use crate::yaku::{Yaku, YakuHelpers};
+use crate::hand::{Meld, MeldHas, MeldIs, MeldKind, Pair};
+use crate::yaku::{Yaku, YakuHelpers, YakuMetadata, ALL_YAKU, YAKU_SPECIAL};
 use crate::state::TileType;
 use crate::rulesets::RiichiRuleset;
 
@@ -59,10 +59,9 @@ impl ConvertStrings for str {
         if self.is_empty() { Err(ParsingError::Empty) }
         else {
             let mut s = String::with_capacity(self.len());
-            let mut i = self.chars();
             let mut v: Vec<Tile> = Vec::with_capacity(self.len()/2);
 
-            while let Some(c) = i.next() {
+            for c in self.chars() {
                 if c == ',' { v.push(s.to_tile()?); s.clear() }
                 else { s.push(c) }
             }
@@ -72,19 +71,44 @@ impl ConvertStrings for str {
         }
     }
     fn to_meld(&self) -> Result<Meld, ParsingError> {
-        if self.is_empty() { Err(ParsingError::Empty) }
-        else if self.chars().nth(0) == Some('!') { self[1..].to_tiles()?.make_meld(false).ok_or(ParsingError::BadMeld) }
-            else { self.to_tiles()?.make_meld(true).ok_or(ParsingError::BadMeld) }
+        if self.is_empty() { return Err(ParsingError::Empty) }
+
+        // called from another seat, ie "dr,dr,dr@w"
+        let (tiles, from) = match self.split_once('@') { Some((tiles, from)) => (tiles, Some(from)), None => (self, None) };
+        // closed kans are prefixed with '!', added kans with '+'
+        let (prefix, tiles) = match tiles.chars().nth(0) { Some(c @ ('!' | '+')) => (Some(c), &tiles[1..]), _ => (None, tiles) };
+        // the called tile can be marked with '*', ie "p3*,p2,p4"
+        let called = tiles.split(',').position(|s| s.ends_with('*'));
+        let tiles = tiles.replace('*', "").to_tiles()?;
+
+        let mut meld = tiles.make_meld(prefix != Some('!')).ok_or(ParsingError::BadMeld)?;
+        meld.kind = match prefix {
+            Some('!') if meld.is_quad() && from.is_none() && called.is_none() => Some(MeldKind::Ankan),
+            Some('!') if from.is_none() && called.is_none() => None,
+            Some('+') if meld.is_quad() => Some(MeldKind::Shouminkan),
+            None if meld.is_quad() => Some(MeldKind::Daiminkan),
+            None if meld.is_trip() => Some(MeldKind::Pon),
+            None => Some(MeldKind::Chi),
+            _ => return Err(ParsingError::BadMeld),
+        };
+        // sequences are sorted by make_meld, everything else keeps its order
+        meld.called_index = called.and_then(|i| if meld.is_seq() { meld.tiles.iter().position(|t| *t == Some(tiles[i])) } else { Some(i) });
+
+        if let Some(from) = from {
+            let mut chars = from.chars();
+            meld.called_from = Some(chars.next().ok_or(ParsingError::Empty)?.to_wind()?);
+            if chars.next().is_some() { return Err(ParsingError::BadString) }
+        }
+        Ok(meld)
     }
     fn to_calls(&self) -> Result<Vec<Meld>, ParsingError> {
-        if self.is_empty() { return Ok(Vec::new()) }
+        if self.is_empty() { Ok(Vec::new()) }
         // else { return Ok(self.split('|').map(|s| s.to_meld().expect("tiles should be a valid meld")).collect()) }
         else {
             let mut s = String::with_capacity(self.len());
-            let mut i = self.chars();
             let mut m: Vec<Meld> = Vec::with_capacity(4);
 
-            while let Some(c) = i.next() {
+            for c in self.chars() {
                 if c == '|' { m.push(s.to_meld()?); s.clear() }
                 else { s.push(c) }
             }
@@ -93,25 +117,24 @@ impl ConvertStrings for str {
             Ok(m)
         }
     }
+    // Matches the romanized or English name of a yaku, or one of its aliases, ignoring case and spacing.
     fn to_yaku(&self) -> Result<Yaku,ParsingError> {
-        match self.to_lowercase().as_str() {
-            "riichi" => Ok(Yaku::Riichi),
-            "ippatsu" => Ok(Yaku::Ippatsu),
-            "doubleriichi" => Ok(Yaku::DoubleRiichi),
-            "undersea" | "underthesea" | "haiteiraoyue" | "haitei" => Ok(Yaku::UnderSea),
-            "underriver" | "undertheriver" | "houteiraoyui" | "houtei" => Ok(Yaku::UnderRiver),
-            "afterkan" | "rinshan" | "rinshankaiho" => Ok(Yaku::AfterKan),
-            "robbedkan" | "robbingakan" | "chankan" => Ok(Yaku::RobbedKan),
-            "nagashimangan" => Ok(Yaku::NagashiMangan),
-            "tenho" | "blessingofheaven" => Ok(Yaku::Tenho),
-            "chiho" | "blessingofearth" => Ok(Yaku::Chiho),
-            _ => Err(ParsingError::BadString)
-        }
+        let squash = |name: &str| name.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+        let wanted = squash(self);
+        ALL_YAKU.iter().find(|y| {
+            let info = y.info();
+            [info.name, info.english].iter().chain(info.aliases).any(|name| squash(name) == wanted)
+        }).copied().ok_or(ParsingError::BadString)
     }
+    // Only the special yaku can be given by hand; the rest are read from the tiles.
     fn to_yaku_vec(&self) -> Result<Vec<Yaku>, ParsingError> {
         if self.is_empty() { return Ok(Vec::new()) }
         let mut yaku: Vec<Yaku> = Vec::new();
-        self.split(',').map(|s| s.to_yaku().unwrap()).for_each(|y| yaku.push_checked(y));
+        for name in self.split(',') {
+            let y = name.to_yaku()?;
+            if !YAKU_SPECIAL.contains(&y) { return Err(ParsingError::BadString) }
+            yaku.push_checked(y);
+        }
         Ok(yaku)
     }
     fn to_ruleset(&self) -> Result<RiichiRuleset, ParsingError> {
@@ -169,7 +192,10 @@ impl ConvertTiles for Vec<Tile> {
             if self.count_occurrences(&self[0]) == 3 {
                 return Some(Meld{
                     tiles: pad_to_length(self),
-                    is_open: open
+                    is_open: open,
+                    kind: None,
+                    called_from: None,
+                    called_index: None
                 })
             } else {
                 let mut tiles = self.clone();
@@ -179,7 +205,10 @@ impl ConvertTiles for Vec<Tile> {
                     if adj.contains(&tiles[1]) && adj.contains(&tiles[2]) && tiles[1] != tiles[2] {
                         return Some(Meld{
                             tiles: pad_to_length(&tiles),
-                            is_open: open
+                            is_open: open,
+                            kind: None,
+                            called_from: None,
+                            called_index: None
                         })
                     }
                 }
@@ -187,7 +216,10 @@ impl ConvertTiles for Vec<Tile> {
         } else if self.len() == 4 && self.count_occurrences(&self[0]) == 4 {
             return Some(Meld{
                 tiles: pad_to_length(self),
-                is_open: open
+                is_open: open,
+                kind: None,
+                called_from: None,
+                called_index: None
             })
         } else { panic!("bad meld length!") }
         None
@@ -244,10 +276,28 @@ mod tests {
         assert_eq!("we,we,we,we".to_tiles(), Ok(vec!["we".to_tile().unwrap(); 4]));
         assert_eq!("dr,dr,dr".to_tiles(), Ok(vec!["dr".to_tile().unwrap(); 3]));
 
-        assert_eq!("we,we,we,we".to_meld().ok(), Some(Meld{is_open: true, tiles: [Some(Tile::Wind(Wind::East)); 4]}));
-        assert_eq!("!we,we,we,we".to_meld().ok(), Some(Meld{is_open: false, tiles: [Some(Tile::Wind(Wind::East)); 4]}));
-        assert_eq!("we,we,we".to_meld().ok(), Some(Meld{is_open: true, tiles: ["we".to_tile().ok(), "we".to_tile().ok(), "we".to_tile().ok(), None]}));
-        assert_eq!("p1,p2,p3".to_meld().ok(), Some(Meld{is_open: true, tiles: ["p1".to_tile().ok(), "p2".to_tile().ok(), "p3".to_tile().ok(), None]}));
+        assert_eq!("we,we,we,we".to_meld().ok(), Some(Meld{is_open: true, tiles: [Some(Tile::Wind(Wind::East)); 4], kind: Some(MeldKind::Daiminkan), called_from: None, called_index: None}));
+        assert_eq!("!we,we,we,we".to_meld().ok(), Some(Meld{is_open: false, tiles: [Some(Tile::Wind(Wind::East)); 4], kind: Some(MeldKind::Ankan), called_from: None, called_index: None}));
+        assert_eq!("we,we,we".to_meld().ok(), Some(Meld{is_open: true, tiles: ["we".to_tile().ok(), "we".to_tile().ok(), "we".to_tile().ok(), None], kind: Some(MeldKind::Pon), called_from: None, called_index: None}));
+        assert_eq!("p1,p2,p3".to_meld().ok(), Some(Meld{is_open: true, tiles: ["p1".to_tile().ok(), "p2".to_tile().ok(), "p3".to_tile().ok(), None], kind: Some(MeldKind::Chi), called_from: None, called_index: None}));
+
+        assert_eq!("dr,dr,dr@w".to_meld().ok(), Some(Meld{is_open: true, tiles: ["dr".to_tile().ok(), "dr".to_tile().ok(), "dr".to_tile().ok(), None], kind: Some(MeldKind::Pon), called_from: Some(Wind::West), called_index: None}));
+        assert_eq!("dr,dr,dr@x".to_meld(), Err(ParsingError::BadChar));
+
+        // melds compare by their tiles alone, so the kind and source are checked separately
+        assert_eq!(["we,we,we,we", "!we,we,we,we", "we,we,we", "p1,p2,p3"].map(|m| m.to_meld().unwrap().kind),
+            [Some(MeldKind::Daiminkan), Some(MeldKind::Ankan), Some(MeldKind::Pon), Some(MeldKind::Chi)]);
+        assert_eq!("dr,dr,dr@w".to_meld().unwrap().called_from, Some(Wind::West));
+        assert_eq!("dr,dr,dr@w".to_meld(), "dr,dr,dr*@e".to_meld());
+        assert_ne!("we,we,we,we".to_meld(), "!we,we,we,we".to_meld());
+
+        // called tile and kan kinds
+        let meld = "p3*,p2,p4@n".to_meld().unwrap();
+        assert_eq!((meld.kind, meld.called_from, meld.called_index, meld.called_tile()), (Some(MeldKind::Chi), Some(Wind::North), Some(1), "p3".to_tile().ok()));
+        assert_eq!("+dr,dr,dr*,dr@s".to_meld().map(|m| (m.kind, m.called_index)), Ok((Some(MeldKind::Shouminkan), Some(2))));
+        assert_eq!("+dr,dr,dr".to_meld(), Err(ParsingError::BadMeld));
+        assert_eq!("!dr,dr,dr,dr@s".to_meld(), Err(ParsingError::BadMeld));
+        for call in ["p2,p3*,p4@n", "+dr,dr,dr*,dr@s", "!we,we,we,we", "m1,m1,m1"] { assert_eq!(call.to_meld().unwrap().to_string(), call) }
 
         assert_eq!("we,we,we".to_calls().ok(), Some(vec!["we,we,we".to_meld().unwrap()]));
         assert_eq!("we,we,we|dr,dr,dr".to_calls().ok(), Some(vec!["we,we,we".to_meld().unwrap(), "dr,dr,dr".to_meld().unwrap()]));
@@ -262,20 +312,62 @@ mod tests {
         assert_eq!("we,we".to_tiles().unwrap().make_pair(), Some(Pair{tiles: ["we".to_tile().unwrap(); 2]}));
         assert_eq!("p5,p5".to_tiles().unwrap().make_pair(), Some(Pair{tiles: ["p5".to_tile().unwrap(); 2]}));
 
-        assert_eq!("we,we,we,we".to_tiles().unwrap().make_meld(true), Some(Meld{tiles: ["we".to_tile().ok(); 4], is_open: true}));
-        assert_eq!("we,we,we".to_tiles().unwrap().make_meld(false), Some(Meld{tiles: ["we".to_tile().ok(), "we".to_tile().ok(), "we".to_tile().ok(), None], is_open: false}));
-        assert_eq!("p2,p3,p4".to_tiles().unwrap().make_meld(false), Some(Meld{tiles: ["p2".to_tile().ok(), "p3".to_tile().ok(), "p4".to_tile().ok(), None], is_open: false}));
-        assert_eq!("p3,p2,p4".to_tiles().unwrap().make_meld(false), Some(Meld{tiles: ["p2".to_tile().ok(), "p3".to_tile().ok(), "p4".to_tile().ok(), None], is_open: false}));   
+        assert_eq!("we,we,we,we".to_tiles().unwrap().make_meld(true), Some(Meld{tiles: ["we".to_tile().ok(); 4], is_open: true, kind: None, called_from: None, called_index: None}));
+        assert_eq!("we,we,we".to_tiles().unwrap().make_meld(false), Some(Meld{tiles: ["we".to_tile().ok(), "we".to_tile().ok(), "we".to_tile().ok(), None], is_open: false, kind: None, called_from: None, called_index: None}));
+        assert_eq!("p2,p3,p4".to_tiles().unwrap().make_meld(false), Some(Meld{tiles: ["p2".to_tile().ok(), "p3".to_tile().ok(), "p4".to_tile().ok(), None], is_open: false, kind: None, called_from: None, called_index: None}));
+        assert_eq!("p3,p2,p4".to_tiles().unwrap().make_meld(false), Some(Meld{tiles: ["p2".to_tile().ok(), "p3".to_tile().ok(), "p4".to_tile().ok(), None], is_open: false, kind: None, called_from: None, called_index: None}));   
     }
 
     #[test]
     fn yaku_from_strings(){
         assert_eq!("riichi".to_yaku().ok(), Some(Yaku::Riichi));
         assert_eq!("riichi,ippatsu".to_yaku_vec().ok(), Some(vec![Yaku::Riichi, Yaku::Ippatsu]));
+        assert_eq!("Under the sea".to_yaku().ok(), Some(Yaku::UnderSea));
+        assert_eq!("haiteiraoyue".to_yaku().ok(), Some(Yaku::UnderSea));
+        assert_eq!("robbingakan".to_yaku().ok(), Some(Yaku::RobbedKan));
+        assert_eq!("Pinfu".to_yaku().ok(), Some(Yaku::Pinfu));
+        assert_eq!("dora".to_yaku(), Err(ParsingError::BadString));
 
         // should check for mutually exclusive yaku
         assert_eq!("riichi,ippatsu,nagashimangan".to_yaku_vec().ok(), Some(vec![Yaku::NagashiMangan]));
         assert_eq!("chiho,robbedkan".to_yaku_vec().ok(), Some(vec![Yaku::Chiho]));
         assert_eq!("robbedkan,chiho".to_yaku_vec().ok(), Some(vec![Yaku::Chiho]));
+
+        // regular yaku can't be given as special yaku
+        assert_eq!("chinitsu".to_yaku_vec(), Err(ParsingError::BadString));
+        assert_eq!("riichi,pinfu".to_yaku_vec(), Err(ParsingError::BadString));
+        assert_eq!("riichi,dora".to_yaku_vec(), Err(ParsingError::BadString));
+    }
+
+    #[test]
+    fn test_tile_ids(){
+        use crate::tiles::{TileId, PhysicalTile, ToHistogram, HistogramTrait, TileVecTrait, RedFiveTrait, all_physical_tiles};
+
+        assert_eq!(TileId::from("m1".to_tile().unwrap()).index(), 0);
+        assert_eq!(TileId::from("p5r".to_tile().unwrap()).index(), 13);
+        assert_eq!(TileId::from("s9".to_tile().unwrap()).index(), 26);
+        assert_eq!(TileId::from("we".to_tile().unwrap()).index(), 27);
+        assert_eq!(TileId::from("dr".to_tile().unwrap()).index(), 33);
+        assert_eq!(TileId::new(34), None);
+        for id in 0..34 { assert_eq!(TileId::from(Tile::from(TileId::new(id).unwrap())), TileId::new(id).unwrap()) }
+
+        let tiles = "m1,m1,p5r,s9,wn,dg".to_tiles().unwrap();
+        let histogram = tiles.to_histogram();
+        assert_eq!(histogram.tile_count(), 6);
+        assert_eq!(histogram[0], 2);
+        assert_eq!(histogram.count_suits(), tiles.count_suits());
+        assert_eq!(histogram.count_dora(&Some("m9,s8".to_tiles().unwrap())), tiles.count_dora(&Some("m9,s8".to_tiles().unwrap())));
+        // a tile pointed to by two markers counts twice
+        assert_eq!(tiles.count_dora(&Some("m9,m9,s8".to_tiles().unwrap())), 5);
+        assert_eq!(histogram.count_dora(&Some("m9,m9,s8".to_tiles().unwrap())), 5);
+
+        let wall = all_physical_tiles();
+        assert_eq!(wall.iter().map(|t| Tile::from(*t)).collect::<Vec<_>>().count_red(), 3);
+        assert_eq!(Tile::from(PhysicalTile::new(52).unwrap()).to_string(), "p5r");
+        assert_eq!(Tile::from(PhysicalTile::new(53).unwrap()).to_string(), "p5");
+        assert_ne!(PhysicalTile::new(52), PhysicalTile::new(53));
+        assert_eq!(PhysicalTile::new(52).unwrap().kind(), PhysicalTile::new(53).unwrap().kind());
+        assert_eq!(PhysicalTile::from_kind(TileId::new(13).unwrap(), 0), PhysicalTile::new(52));
+        assert_eq!(PhysicalTile::new(136), None);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,190 @@
+/*
+Defensive reads: how likely a tile is to deal into a given opponent, from what's visible on the table.
+
+A tile is genbutsu against a player if it's in their discards (they'd be furiten on it), or if they're in riichi and let
+it go by since declaring. Otherwise, for number tiles, each ryanmen wait the tile could complete is looked at separately:
+it's ruled out by suji when the other end of the wait is genbutsu, and by kabe when every copy of one of the shape's tiles
+is visible (no-chance), or nearly ruled out when three copies are (one-chance). Honors can only be waited on as a
+tanki or shanpon, so their danger comes down to how many copies are left.
+
+Scores are rough deal-in rates, in percent, against a player who is tenpai. They're meant for comparing tiles; none of
+this accounts for kanchan, penchan, shanpon or tanki waits on suji tiles, or for how a player's discards were ordered.
+*/
+
+use crate::tiles::{Tile, TileId, TileRelations, Histogram};
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+// What's known about one opponent. Red fives are the same tile as any other five here.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Opponent<'a> {
+    pub discards: &'a [Tile],   // everything they've discarded, including tiles which were called
+    pub riichi: bool,
+    pub passed: &'a [Tile],     // tiles others discarded after their riichi declaration, which they didn't ron on
+}
+
+// From safest to most dangerous, roughly; within a class, danger still depends on the tile's number.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum DangerClass {
+    Genbutsu,
+    Suji,           // every ryanmen wait on the tile is ruled out by suji
+    NoChance,       // ... by suji, or by a shape tile with all four copies visible
+    OneChance,      // ... or by a shape tile with three copies visible
+    Honor,          // an honor tile which isn't genbutsu
+    Dangerous,      // a number tile with at least one ryanmen wait left open
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Danger {
+    pub class: DangerClass,
+    pub score: f32,
+}
+
+// How one side of a tile's ryanmen waits stands.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+enum Side {
+    Closed,         // no such wait: the tile is too close to the end of the suit
+    Suji,
+    NoChance,
+    OneChance,
+    Open,
+}
+
+///////////////
+// functions //
+///////////////
+
+// Estimates the danger of discarding a tile to one opponent.
+// `visible` counts every copy the discarding player can see, as kept by tracker::TileTracker::seen().
+pub fn estimate_danger(tile: &Tile, opponent: &Opponent, visible: &Histogram) -> Danger {
+    if is_genbutsu(tile, opponent) { return Danger { class: DangerClass::Genbutsu, score: 0.0 } }
+
+    let Tile::Number { number, .. } = tile else {
+        let seen = visible[TileId::from(*tile).index()];
+        return Danger { class: DangerClass::Honor, score: match seen { 0 => 7.5, 1 => 6.0, 2 => 3.0, 3 => 0.8, _ => 0.0 } }
+    };
+
+    // below is the n-2/n-1 shape (waiting on n-3 and n), above is the n+1/n+2 shape (waiting on n and n+3)
+    let below = side(tile.adjacent_down(), suji_partner(tile, -3), opponent, visible);
+    let above = side(tile.adjacent_up(), suji_partner(tile, 3), opponent, visible);
+    let worst = below.max(above);
+    let half_suji = (below == Side::Suji) != (above == Side::Suji) && worst == Side::Open && (4..=6).contains(number);
+
+    // columns are 1/9, 2/8, 3/7 and 4/5/6
+    let column = (*number.min(&(10 - number)) - 1).min(3) as usize;
+    let (class, scores) = match worst {
+        Side::Closed | Side::Suji => (DangerClass::Suji, [1.8, 3.8, 5.3, 4.0]),
+        Side::NoChance => (DangerClass::NoChance, [1.5, 3.0, 4.0, 3.5]),
+        Side::OneChance => (DangerClass::OneChance, [4.0, 5.5, 6.5, 7.0]),
+        Side::Open if half_suji => (DangerClass::Dangerous, [6.5, 8.5, 9.5, 8.0]),
+        Side::Open => (DangerClass::Dangerous, [6.5, 8.5, 9.5, 12.5]),
+    };
+    Danger { class, score: scores[column] }
+}
+
+// Danger of every tile kind, indexed by TileId.
+pub fn danger_by_kind(opponent: &Opponent, visible: &Histogram) -> [Danger; 34] {
+    std::array::from_fn(|id| {
+        let tile = Tile::from(TileId::new(id as u8).expect("ids are under 34"));
+        estimate_danger(&tile, opponent, visible)
+    })
+}
+
+pub fn is_genbutsu(tile: &Tile, opponent: &Opponent) -> bool {
+    opponent.discards.contains(tile) || (opponent.riichi && opponent.passed.contains(tile))
+}
+
+fn suji_partner(tile: &Tile, offset: i8) -> Option<Tile> {
+    let Tile::Number { suit, number, .. } = tile else { return None };
+    (1..=9).contains(&(number + offset)).then_some(Tile::Number { suit: *suit, number: number + offset, red: false })
+}
+
+fn side(shape: Option<[Tile; 2]>, partner: Option<Tile>, opponent: &Opponent, visible: &Histogram) -> Side {
+    // without a partner the shape is a penchan, not a ryanmen
+    let (Some(shape), Some(partner)) = (shape, partner) else { return Side::Closed };
+    if is_genbutsu(&partner, opponent) { return Side::Suji }
+
+    match shape.iter().map(|t| visible[TileId::from(*t).index()]).max() {
+        Some(4..) => Side::NoChance,
+        Some(3) => Side::OneChance,
+        _ => Side::Open,
+    }
+}
+
+///////////
+// tests //
+///////////
+
+mod tests {
+    use super::*;
+    use crate::tiles::ToHistogram;
+    use crate::conversions::ConvertStrings;
+
+    fn class(tile: &str, opponent: &Opponent, visible: &Histogram) -> DangerClass {
+        estimate_danger(&tile.to_tile().unwrap(), opponent, visible).class
+    }
+
+    #[test]
+    fn test_tile_relations(){
+        let m5 = "m5".to_tile().unwrap();
+        assert_eq!(m5.adjacent_up(), Some(["m6".to_tile().unwrap(), "m7".to_tile().unwrap()]));
+        assert_eq!(m5.adjacent_down(), Some(["m4".to_tile().unwrap(), "m3".to_tile().unwrap()]));
+        assert_eq!(m5.adjacent_around(), Some(["m4".to_tile().unwrap(), "m6".to_tile().unwrap()]));
+        assert_eq!("p2".to_tile().unwrap().adjacent_down(), None);
+        assert_eq!("p1".to_tile().unwrap().adjacent_around(), None);
+        assert_eq!("s8".to_tile().unwrap().adjacent_up(), None);
+        assert_eq!("dr".to_tile().unwrap().adjacent_up(), None);
+    }
+
+    #[test]
+    fn test_danger_classes(){
+        let discards = "m4,p1,s5,we".to_tiles().unwrap();
+        let passed = "s9".to_tiles().unwrap();
+        let riichi = Opponent { discards: &discards, riichi: true, passed: &passed };
+        let nothing_seen: Histogram = [0; 34];
+
+        assert_eq!(class("m4", &riichi, &nothing_seen), DangerClass::Genbutsu);
+        assert_eq!(class("s9", &riichi, &nothing_seen), DangerClass::Genbutsu);
+        assert_eq!(class("s9", &Opponent { riichi: false, ..riichi.clone() }, &nothing_seen), DangerClass::Dangerous);
+
+        // 1/9, 2/8 and 3/7 only have one side to cover; 4/5/6 need both
+        assert_eq!(class("m1", &riichi, &nothing_seen), DangerClass::Suji);
+        assert_eq!(class("m7", &riichi, &nothing_seen), DangerClass::Suji);
+        assert_eq!(class("p4", &riichi, &nothing_seen), DangerClass::Dangerous);
+        assert_eq!(class("s2", &riichi, &nothing_seen), DangerClass::Suji);
+        assert_eq!(class("s8", &riichi, &nothing_seen), DangerClass::Suji);
+        assert_eq!(class("m5", &riichi, &nothing_seen), DangerClass::Dangerous);
+
+        // nakasuji is safer than half suji, which is safer than nothing
+        let nakasuji = "m1,m7".to_tiles().unwrap();
+        let opponent = Opponent { discards: &nakasuji, riichi: true, passed: &[] };
+        assert_eq!(class("m4", &opponent, &nothing_seen), DangerClass::Suji);
+        let half = estimate_danger(&"m5".to_tile().unwrap(), &Opponent { discards: &"m2".to_tiles().unwrap(), riichi: true, passed: &[] }, &nothing_seen);
+        let open = estimate_danger(&"m5".to_tile().unwrap(), &Opponent { discards: &[], riichi: true, passed: &[] }, &nothing_seen);
+        assert_eq!(half.class, DangerClass::Dangerous);
+        assert!(half.score < open.score);
+
+        // four p8 visible close off every ryanmen on p9, and p7's upper side
+        let visible = "p8,p8,p8,p8,m3,m3,m3".to_tiles().unwrap().to_histogram();
+        let none = Opponent { discards: &[], riichi: true, passed: &[] };
+        assert_eq!(class("p9", &none, &visible), DangerClass::NoChance);
+        assert_eq!(class("p7", &none, &visible), DangerClass::Dangerous);
+        assert_eq!(class("m1", &none, &visible), DangerClass::OneChance);
+        assert_eq!(class("m2", &none, &visible), DangerClass::OneChance);
+        assert_eq!(class("m4", &none, &visible), DangerClass::Dangerous);
+        // 6p's lower side is suji, and its upper side needs 8p
+        let p3 = "p3".to_tiles().unwrap();
+        assert_eq!(class("p6", &none, &visible), DangerClass::Dangerous);
+        assert_eq!(class("p6", &Opponent { discards: &p3, riichi: true, passed: &[] }, &visible), DangerClass::NoChance);
+
+        // honors only lose danger as copies show up
+        let table = danger_by_kind(&none, &"dr,dr,dr,dg".to_tiles().unwrap().to_histogram());
+        let honor = |tile: &str| table[TileId::from(tile.to_tile().unwrap()).index()];
+        assert_eq!(honor("dr").class, DangerClass::Honor);
+        assert!(honor("dr").score < honor("dg").score);
+        assert!(honor("dg").score < honor("dw").score);
+        assert_eq!(table[TileId::from("we".to_tile().unwrap()).index()].class, DangerClass::Honor);
+    }
+}
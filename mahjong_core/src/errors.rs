@@ -6,7 +6,9 @@ pub mod mahjong_errors {
         ValueError,
         ParseError(ParsingError),
         Unimplemented,
-        NotAgari
+        NotAgari,
+        IllegalCall,
+        ImpossibleYaku      // a special yaku the seat or the game state rules out, ie tenho for a non-dealer
     }
 
     #[derive(Debug, PartialEq)]
@@ -1,4 +1,4 @@
-use crate::tiles::{Tile, Dragon, Wind, Suit, TileIs, TileRelations, TileVecTrait};
+use crate::tiles::{Tile, TileId, Dragon, Wind, Suit, TileIs, TileRelations, TileVecTrait, RedFiveTrait, ToHistogram, Histogram, all_tile_kinds};
 use crate::state::{Game, Seat, Win, WinType, TileType, SeatAccess};
 use crate::errors::mahjong_errors::{HandError, ParsingError};
 use crate::yaku::{Yaku, YakuHelpers, FindYaku};
@@ -7,6 +7,8 @@ use crate::rulesets::{RiichiRuleset, RuleVariations};
 use crate::conversions::{ConvertTiles, ConvertStrings};
 use crate::composer::{Composer, Counter};
 use std::fmt;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 use std::iter::repeat;
 
 ///////////////////////
@@ -51,10 +53,23 @@ pub enum HandShape {
     Shanten // TODO
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+// Melds compare by their tiles and whether they're open; how they were called is provenance, not part of the meld.
+#[derive(Debug, Copy, Clone)]
 pub struct Meld {
 	pub tiles: [Option<Tile>; 4],
-	pub is_open: bool
+	pub is_open: bool,
+	pub kind: Option<MeldKind>,		// how the meld was called. None for melds formed inside the closed hand
+	pub called_from: Option<Wind>,	// seat which discarded the called tile, if known
+	pub called_index: Option<usize>,	// position of the called tile in tiles, if known
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum MeldKind {
+	Chi,
+	Pon,
+	Daiminkan,	// open kan called from a discard
+	Shouminkan,	// open kan made by adding a drawn tile to a pon
+	Ankan,		// closed kan
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
@@ -64,8 +79,28 @@ pub struct Pair {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Wait {
-    tiles: Vec<Tile>,
-    discard: Option<Tile>
+    pub tiles: Vec<Tile>,
+    pub discard: Option<Tile>,
+    pub shape: WaitShape,
+    pub tile_shapes: Vec<Vec<WaitShape>>,  // what each waited tile could complete, in the same order as tiles
+    pub live: u8,           // copies of the waited tiles that the hand doesn't hold itself
+}
+
+// The first five are what a winning tile can complete within one reading of a hand; the rest are combinations of
+// them, across every reading of a tenpai hand.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum WaitShape {
+    Ryanmen,        // open sequence wait, ie m2,m3 waiting on m1 or m4
+    Kanchan,        // middle of a sequence, ie m2,m4 waiting on m3
+    Penchan,        // edge of a sequence, ie m1,m2 waiting on m3
+    Shanpon,        // two pairs, one of which becomes a triplet
+    Tanki,          // a single tile, waiting to become the pair
+    Nobetan,        // four in a row, ie m1,m2,m3,m4 waiting on m1 or m4 as the pair
+    Sanmenchan,     // five in a row, ie m2,m3,m4,m5,m6 waiting on m1, m4 or m7
+    Entotsu,        // a triplet beside a ryanmen, and a pair, ie m4,m4,m4,m5,m6 + p2,p2 waiting on m4, m7 or p2
+    Chuuren,        // the nine-sided wait of nine gates
+    Kokushi,        // the thirteen-sided wait of thirteen orphans
+    MultiSided,     // any other combination
 }
 
 // Used for recursion; see fn compose_tiles()
@@ -97,6 +132,10 @@ pub trait HandShapeVecTrait {
     fn find_best(&self) -> Option<(HandShape, Vec<Yaku>, i8, i8)>;
 }
 
+pub trait ReadWait {
+    fn wait_shapes(&self, winning_tile: &Tile) -> Vec<WaitShape>;   // what the winning tile completed in a complete hand
+}
+
 pub trait MeldIs {
 	fn is_quad(&self) -> bool;
 	fn is_trip(&self) -> bool;
@@ -110,6 +149,7 @@ pub trait MeldHas {
     fn base_fu(&self) -> u8;
     fn contains(&self, tile: &Tile) -> bool;
     fn as_tiles(&self) -> Vec<Tile>;
+    fn called_tile(&self) -> Option<Tile>;
 }
 
 pub trait PairTrait {
@@ -169,12 +209,13 @@ impl HandTrait for Hand {
                         hand_shape: best_hand.clone(),
                         latest_tile: seat_state.latest_tile.unwrap(),
                         latest_type: seat_state.latest_type.unwrap(),
-                        dora: seat_state.all_tiles().count_dora(&game_state.dora_markers),
+                        dora: seat_state.all_tiles().count_dora(&game_state.dora_markers)
+                            + if game_state.ruleset.counts_akadora() { seat_state.all_tiles().count_red() } else { 0 },
                         han: best_yaku.han(is_open, game_state.ruleset),
                         fu: best_hand.fu(&game_state, &seat_state, &best_yaku).unwrap(),
                         yaku: best_yaku,
                         open: is_open,
-                        dealer: seat_state.seat_wind == game_state.round_wind,
+                        dealer: seat_state.seat_wind == Wind::East,
                     }
                 }
             }
@@ -183,14 +224,12 @@ impl HandTrait for Hand {
         if let Some(possible_tenpai) = read_tenpai(&seat_state.closed_tiles, &seat_state.called_melds, &seat_state.latest_tile) {
             if possible_tenpai.is_empty() {
                 panic!("read_tenpai() should not return Some(empty vec)")
-            } else {
             }
         }
 
         if let Some(shanten) = read_shanten(&seat_state.closed_tiles, &seat_state.called_melds, &seat_state.latest_tile) {
             if shanten.is_empty() {
                 panic!("read_shanten() should not return Some(empty vec)")
-            } else {
             }
         }
 
@@ -249,6 +288,24 @@ impl TileIs for Pair {
     fn dragon(&self) -> Option<Dragon> { self.tiles[0].dragon() }
 }
 
+impl PartialEq for Meld {
+    fn eq(&self, other: &Self) -> bool { (self.tiles, self.is_open) == (other.tiles, other.is_open) }
+}
+
+impl Eq for Meld {}
+
+impl Hash for Meld {
+    fn hash<H: Hasher>(&self, state: &mut H) { (self.tiles, self.is_open).hash(state) }
+}
+
+impl PartialOrd for Meld {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for Meld {
+    fn cmp(&self, other: &Self) -> Ordering { (self.tiles, self.is_open).cmp(&(other.tiles, other.is_open)) }
+}
+
 impl MeldIs for Meld {
 	fn is_quad(&self) -> bool { self.tiles[3].is_some() }
 	fn is_trip(&self) -> bool { self.tiles[3].is_none() && self.tiles[0] == self.tiles[2] }
@@ -269,10 +326,11 @@ impl MeldHas for Meld {
             // and the winning tile, so including it here would be cumbersome
         }
     }
-    fn contains(&self, tile: &Tile) -> bool { self.tiles.binary_search(&Some(*tile)).is_ok() }
+    fn contains(&self, tile: &Tile) -> bool { self.tiles.contains(&Some(*tile)) }
     fn as_tiles(&self) -> Vec<Tile> {
         self.tiles.into_iter().flatten().collect()
     } 
+    fn called_tile(&self) -> Option<Tile> { self.tiles[self.called_index?] }
 }
 
 impl PairTrait for Pair {
@@ -303,6 +361,25 @@ macro_rules! impl_MeldVecHas {
 
 impl_MeldVecHas!(for Vec<Meld>, Vec<&Meld>, [&Meld], [Meld], Vec<Pair>, [Pair]);
 
+impl ReadWait for HandShape {
+    fn wait_shapes(&self, winning_tile: &Tile) -> Vec<WaitShape> {
+        match self {
+            HandShape::Standard { melds, pair } => winning_tile_shapes(melds, pair, winning_tile),
+            HandShape::Chiitoi { .. } => vec![WaitShape::Tanki],
+            HandShape::Kokushi(yaku) if yaku.contains(&Yaku::SpecialWait) => vec![WaitShape::Kokushi],
+            HandShape::Kokushi(_) => vec![WaitShape::Tanki],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Wait {
+    // Copies of the waited tiles left, given everything the player has seen (see tracker::TileTracker::seen).
+    pub fn live_tiles(&self, seen: &Histogram) -> u8 {
+        self.tiles.iter().map(|t| 4_u8.saturating_sub(seen[TileId::from(*t).index()])).sum()
+    }
+}
+
 impl PartialHandTrait for PartialHand {
     fn new(tiles: Vec<Tile>, melds: Vec<Meld>, pairs: Vec<Pair>) -> Self where Self: Sized {
         PartialHand {
@@ -338,7 +415,7 @@ impl PartialHandTrait for PartialHand {
 
 // Returns only reads in which a hand is complete, ignoring yaku.
 // Attempts to dedup.
-fn read_win(closed_tiles: &[Tile], called_melds: &Option<Vec<Meld>>, latest_tile: &Option<Tile>) -> Option<Vec<HandShape>> {
+pub(crate) fn read_win(closed_tiles: &[Tile], called_melds: &Option<Vec<Meld>>, latest_tile: &Option<Tile>) -> Option<Vec<HandShape>> {
     fn compose_kokushi(all_tiles: &[Tile], latest_tile: Tile) -> Option<Vec<Yaku>> {
         // TODO: rewrite to use hashset?
         if !all_tiles.has_any_simple() && !latest_tile.is_simple() {
@@ -380,6 +457,8 @@ fn read_win(closed_tiles: &[Tile], called_melds: &Option<Vec<Meld>>, latest_tile
             } else if partial.hanging_tiles.is_empty() && partial.pairs.len() == 7 && partial.melds.is_empty() && called_melds.is_empty() {
                 let mut pairs = partial.pairs;
                 pairs.sort();
+                // four of a kind can't be counted as two of the pairs.
+                if pairs.windows(2).any(|p| p[0] == p[1]) { continue }
                 possible_hands.push(
                     HandShape::Chiitoi{ pairs: pairs.try_into().expect("Wrong number of pairs in chiitoi???") }
                 );
@@ -406,6 +485,80 @@ fn read_tenpai(closed_tiles: &[Tile], called_melds: &Option<Vec<Meld>>, latest_t
     panic!()
 }
 
+// Returns the tiles which would complete a 13 tile hand, ignoring yaku, along with the shape of the wait.
+// Tiles which the hand already holds all four copies of can't be won on, so they aren't counted as waits.
+pub fn read_waits(closed_tiles: &[Tile], called_melds: &Option<Vec<Meld>>) -> Option<Wait> {
+    let mut closed_tiles = closed_tiles.to_vec();
+    closed_tiles.sort();
+    let held_tiles = [closed_tiles.clone(), called_melds.clone().unwrap_or_default().iter().map(MeldHas::as_tiles).collect::<Vec<_>>().concat()].concat();
+
+    // every shape each waited tile could complete, over every reading of the hand
+    let waits: Vec<(Tile, Vec<WaitShape>)> = all_tile_kinds().into_iter()
+        .filter(|t| held_tiles.count_occurrences(t) < 4)
+        .filter_map(|t| read_win(&closed_tiles, called_melds, &Some(t)).map(|hands| {
+            let mut shapes: Vec<WaitShape> = hands.iter().flat_map(|h| h.wait_shapes(&t)).collect();
+            shapes.sort_by_key(|s| *s as u8);
+            shapes.dedup();
+            (t, shapes)
+        }))
+        .collect();
+
+    if waits.is_empty() { return None }
+    let tiles: Vec<Tile> = waits.iter().map(|(t, _)| *t).collect();
+    let live = tiles.iter().map(|t| 4 - held_tiles.count_occurrences(t)).sum::<usize>();
+    Some(Wait{
+        shape: classify_wait(&closed_tiles, called_melds.as_ref().is_some_and(|m| !m.is_empty()), &waits),
+        tile_shapes: waits.into_iter().map(|(_, shapes)| shapes).collect(),
+        live: u8::try_from(live).unwrap_or(u8::MAX),
+        tiles,
+        discard: None,
+    })
+}
+
+// Returns what the winning tile could have completed in one reading of a hand: more than one shape when the tile
+// fits in more than one place. Called melds can't be part of the wait.
+pub fn winning_tile_shapes(melds: &[Meld], pair: &Pair, tile: &Tile) -> Vec<WaitShape> {
+    let mut shapes: Vec<WaitShape> = Vec::with_capacity(2);
+    if pair.tile() == *tile { shapes.push(WaitShape::Tanki) }
+
+    for meld in melds.iter().filter(|m| !m.is_open && m.contains(tile)) {
+        let shape = if meld.is_trip() { WaitShape::Shanpon }
+            else if !meld.is_seq() { continue }
+            else if meld.tiles[1] == Some(*tile) { WaitShape::Kanchan }
+            else if meld.has_terminal() && !tile.is_terminal() { WaitShape::Penchan }
+            else { WaitShape::Ryanmen };
+        if !shapes.contains(&shape) { shapes.push(shape) }
+    }
+    shapes
+}
+
+// Names the wait of a whole tenpai hand, from what each waited tile could complete.
+fn classify_wait(closed_tiles: &[Tile], has_calls: bool, waits: &[(Tile, Vec<WaitShape>)]) -> WaitShape {
+    let all = |allowed: &[WaitShape]| waits.iter().all(|(_, shapes)| shapes.iter().all(|s| allowed.contains(s)));
+    let any = |shape: WaitShape| waits.iter().any(|(_, shapes)| shapes.contains(&shape));
+
+    if any(WaitShape::Kokushi) { return WaitShape::Kokushi }
+    if !has_calls && waits.len() == 9 && is_chuuren_shape(closed_tiles) { return WaitShape::Chuuren }
+
+    match waits {
+        [(_, shapes)] => [WaitShape::Kanchan, WaitShape::Penchan, WaitShape::Tanki, WaitShape::Ryanmen, WaitShape::Shanpon]
+            .into_iter().find(|s| shapes.contains(s)).unwrap_or(WaitShape::MultiSided),
+        [_, _] if all(&[WaitShape::Ryanmen]) => WaitShape::Ryanmen,
+        [_, _] if all(&[WaitShape::Shanpon]) => WaitShape::Shanpon,
+        [_, _] if all(&[WaitShape::Tanki]) => WaitShape::Nobetan,
+        [_, _, _] if all(&[WaitShape::Ryanmen]) => WaitShape::Sanmenchan,
+        [_, _, _] if all(&[WaitShape::Ryanmen, WaitShape::Shanpon]) && any(WaitShape::Ryanmen) && any(WaitShape::Shanpon) => WaitShape::Entotsu,
+        _ => WaitShape::MultiSided,
+    }
+}
+
+// 1112345678999 in a single suit
+fn is_chuuren_shape(closed_tiles: &[Tile]) -> bool {
+    let Some(Tile::Number { suit, .. }) = closed_tiles.first() else { return false };
+    closed_tiles.len() == 13 && closed_tiles.iter().all(|t| matches!(t, Tile::Number { suit: s, .. } if s == suit))
+        && (1..=9).all(|n| closed_tiles.iter().filter(|t| matches!(t, Tile::Number { number, .. } if *number == n)).count() == if n == 1 || n == 9 { 3 } else { 1 })
+}
+
 // Returns only shanten reads, ordered by a naive shanten count.
 // If latest_tile is present, also includes which tiles would need to be discarded for different scenarios.
 // Does not include information about how a hand might be completed.
@@ -497,6 +650,28 @@ pub fn compose_tiles(remaining_tiles: &Vec<Tile>, open: bool, consider_waits: Op
     partials.into()
 }
 
+////////////////
+// formatting //
+////////////////
+
+// matches the notation read by to_meld(), ie "p3*,p2,p4@n" or "!dr,dr,dr,dr"
+impl fmt::Display for Meld {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Some(MeldKind::Ankan) => write!(f, "!")?,
+            Some(MeldKind::Shouminkan) => write!(f, "+")?,
+            _ => (),
+        }
+        for (index, tile) in self.tiles.iter().flatten().enumerate() {
+            if index > 0 { write!(f, ",")? }
+            write!(f, "{tile}")?;
+            if self.called_index == Some(index) { write!(f, "*")? }
+        }
+        if let Some(wind) = self.called_from { write!(f, "@{wind}")? }
+        Ok(())
+    }
+}
+
 ///////////
 // tests //
 ///////////
@@ -505,6 +680,7 @@ mod tests {
     use super::*;
     use crate::tiles::{Tile, Dragon, Wind, Suit};
     use crate::conversions::ConvertStrings;
+    use crate::scoring::HandScore;
 
     #[test]
     #[allow(deprecated)]
@@ -552,52 +728,96 @@ mod tests {
     fn test_reading_hands(){
         let game = Game{
             ruleset: RiichiRuleset::Default, round_wind: Wind::East,
-            dora_markers: None, ura_dora_markers: None, repeats: 0 };
+            dora_markers: None, ura_dora_markers: None, repeats: 0,
+            last_action: None, tiles_left: None, turn: None };
         let seat = Seat{
             closed_tiles: "m2,m3,m4,p2,p3,p4,s2,s3,s4,dr,dr,dr,m9".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("m9".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("m9".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::SanshokuDoujun, Yaku::Yakuhai(1)]);
 
         let seat = Seat{
             closed_tiles: "p6,p7,p8,s1,s1,s2,s2,s2,s3,s3,s3,we,we".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("s1".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("s1".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::ClosedTsumo, Yaku::Sananko]);
 
         let seat = Seat{
             closed_tiles: "p6,p7,p8,s1,s2,s2,s3,s3,we,we,m1,m2,m3".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("s1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("s1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Ipeiko]);
 
         let seat = Seat{
             closed_tiles: "we,p2,p3,p4,p2,p3,p4,m1,m2,m3,m1,m2,m3".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("we".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("we".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Ryanpeiko]);
     }
 
+    #[test]
+    fn test_reading_waits(){
+        assert_eq!(read_waits(&"m2,m3,m4,p2,p3,p4,s2,s3,s4,dr,dr,m5,m6".to_tiles().unwrap(), &None).unwrap().tiles, "m1,m4,m7".to_tiles().unwrap());
+        assert_eq!(read_waits(&"m5,m6,dr,dr".to_tiles().unwrap(), &"s2,s3,s4|p2,p3,p4|m2,m3,m4".to_calls().ok()).unwrap().tiles, "m4,m7".to_tiles().unwrap());
+        assert_eq!(read_waits(&"m1,m1,m1,m2,m3,m4,m5,m6,m7,m8,m9,m9,m9".to_tiles().unwrap(), &None).unwrap().tiles, "m1,m2,m3,m4,m5,m6,m7,m8,m9".to_tiles().unwrap());
+        assert_eq!(read_waits(&"m1,m9,p1,p9,s1,s9,dw,dr,dg,we,ws,wn,ww".to_tiles().unwrap(), &None).unwrap().tiles.len(), 13);
+        assert_eq!(read_waits(&"p1,p2,p3,p5,p6,p7,s2,s3,s4,m5,m5,m5,m5".to_tiles().unwrap(), &None), None);
+        assert_eq!(read_waits(&"m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn".to_tiles().unwrap(), &None), None);
+        assert_eq!(read_waits(&"m1,m1,p3,p3,s4,s4,s6,s6,m9,m9,m9,m9,we".to_tiles().unwrap(), &None), None);
+    }
+
+    #[test]
+    fn test_wait_shapes(){
+        let wait = |closed: &str| read_waits(&closed.to_tiles().unwrap(), &None).unwrap();
+        let shape = |closed: &str| wait(closed).shape;
+
+        assert_eq!(shape("m1,m3,p1,p1,p1,s2,s3,s4,s5,s6,s7,dw,dw"), WaitShape::Kanchan);
+        assert_eq!(shape("m1,m2,p1,p1,p1,s2,s3,s4,s5,s6,s7,dw,dw"), WaitShape::Penchan);
+        assert_eq!(shape("m1,p1,p1,p1,s2,s3,s4,s5,s6,s7,dw,dw,dw"), WaitShape::Tanki);
+        assert_eq!(shape("m1,m1,p9,p9,s2,s3,s4,s5,s6,s7,dw,dw,dw"), WaitShape::Shanpon);
+        assert_eq!(shape("m1,m2,m3,m4,p2,p3,p4,s2,s3,s4,dr,dr,dr"), WaitShape::Nobetan);
+        assert_eq!(shape("p2,p2,m4,m4,m4,m5,m6,s1,s2,s3,s7,s8,s9"), WaitShape::Entotsu);
+        assert_eq!(shape("m1,m1,m1,m3,p1,p2,p3,s4,s5,s6,s7,s8,s9"), WaitShape::MultiSided);
+        assert_eq!(shape("m1,m1,m1,m2,m3,m4,m5,m6,m7,m8,m9,m9,m9"), WaitShape::Chuuren);
+        assert_eq!(shape("m1,m9,p1,p9,s1,s9,dw,dr,dg,we,ws,wn,ww"), WaitShape::Kokushi);
+        assert_eq!(shape("m1,m1,m9,m9,p1,p1,s3,s3,s9,s9,dw,dw,dr"), WaitShape::Tanki);
+
+        let sanmenchan = wait("m2,m3,m4,p2,p3,p4,s2,s3,s4,dr,dr,m5,m6");
+        assert_eq!((sanmenchan.shape, sanmenchan.live), (WaitShape::Sanmenchan, 11));
+        let ryanmen = read_waits(&"m5,m6,dr,dr".to_tiles().unwrap(), &"s2,s3,s4|p2,p3,p4|m2,m3,m4".to_calls().ok()).unwrap();
+        assert_eq!((ryanmen.shape, ryanmen.live), (WaitShape::Ryanmen, 7));
+        // copies seen elsewhere on the table count against the live tiles too
+        let seen = "m5,m6,dr,dr,s2,s3,s4,p2,p3,p4,m2,m3,m4,m7,m7".to_tiles().unwrap().to_histogram();
+        assert_eq!(ryanmen.live_tiles(&seen), 5);
+
+        let entotsu = wait("p2,p2,m4,m4,m4,m5,m6,s1,s2,s3,s7,s8,s9");
+        assert_eq!((entotsu.tiles, entotsu.live), ("m4,m7,p2".to_tiles().unwrap(), 7));
+        // each tile keeps what it could complete
+        assert_eq!(entotsu.tile_shapes, vec![vec![WaitShape::Ryanmen, WaitShape::Shanpon], vec![WaitShape::Ryanmen], vec![WaitShape::Shanpon]]);
+        assert_eq!(wait("m1,m2,m3,m4,p2,p3,p4,s2,s3,s4,dr,dr,dr").tile_shapes, vec![vec![WaitShape::Tanki]; 2]);
+    }
+
     #[test]
     fn test_reading_kokushi(){
         let game = Game{
             ruleset: RiichiRuleset::Default, round_wind: Wind::East,
-            dora_markers: None, ura_dora_markers: None, repeats: 0 };
+            dora_markers: None, ura_dora_markers: None, repeats: 0,
+            last_action: None, tiles_left: None, turn: None };
         let seat = Seat{
             closed_tiles: "m1,m1,p1,p9,s1,s9,dw,dr,dg,we,ws,wn,ww".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("m9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("m9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Kokushi]);
 
         let seat = Seat{
             closed_tiles: "m1,m9,p1,p9,s1,s9,dw,dr,dg,we,ws,wn,ww".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("m9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("m9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Kokushi, Yaku::SpecialWait]);
     }
@@ -606,33 +826,81 @@ mod tests {
     fn test_reading_chiitoi(){
         let game = Game{
             ruleset: RiichiRuleset::Default, round_wind: Wind::East,
-            dora_markers: None, ura_dora_markers: None, repeats: 0 };
+            dora_markers: None, ura_dora_markers: None, repeats: 0,
+            last_action: None, tiles_left: None, turn: None };
         let seat = Seat{
             closed_tiles: "m1,m2,m2,m4,m4,dw,dw,p6,p6,we,we,s5,s5".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("m1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("m1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Chiitoi]);
 
         let seat = Seat{
             closed_tiles: "m2,m3,m3,m4,m4,s2,s2,s5,s5,p3,p3,p6,p6".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("m2".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("m2".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Chiitoi, Yaku::Tanyao]);
 
         let seat = Seat{
             closed_tiles: "m1,m9,m9,p1,p1,we,we,ww,ww,dw,dw,dr,dr".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("m1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("m1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Chiitoi, Yaku::Honro]);
 
         let seat = Seat {
             closed_tiles: "dw,dr,dr,dg,dg,we,we,ww,ww,ws,ws,wn,wn".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("dw".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("dw".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Daichiishin]);   
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_red_fives(){
+        let game = Game{
+            ruleset: RiichiRuleset::MajSoul, round_wind: Wind::East,
+            dora_markers: None, ura_dora_markers: None, repeats: 0,
+            last_action: None, tiles_left: None, turn: None };
+        let seat = Seat{
+            closed_tiles: "m2,m3,m4,p4,p5r,p6,p5,p5,s2,s3,s4,dr,dr".to_tiles().unwrap(),
+            called_melds: None, seat_wind: Wind::East, special_yaku: None,
+            latest_tile: Some("p5".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
+        };
+        assert_eq!(seat.all_tiles().count_red(), 1);
+
+        let hand = Hand::new(game.clone(), seat.clone());
+        assert_eq!(hand.dora(), 1);
+        // composition compares kinds, so the red five is only counted from the seat's tiles
+        let Hand::Agari { hand_shape: HandShape::Standard { melds, .. }, .. } = hand else { panic!() };
+        assert_eq!(melds.iter().map(|m| m.as_tiles().count_red()).sum::<u8>(), 0);
+
+        // a red five in a called meld counts too
+        let called = Seat { closed_tiles: "m2,m3,m4,p4,p5,p6,p5,p5,s2,s3".to_tiles().unwrap(),
+            called_melds: Some(vec!["dr,dr,dr".to_tiles().unwrap().make_meld(true).unwrap(), "s5r,s5,s5".to_tiles().unwrap().make_meld(true).unwrap()]),
+            latest_tile: Some("s4".to_tile().unwrap()), ..seat.clone() };
+        assert_eq!(called.all_tiles().count_red(), 1);
+
+        let hand = Hand::new(Game { ruleset: RiichiRuleset::Default, ..game }, seat);
+        assert_eq!(hand.dora(), 0);
+    }
+
+    #[test]
+    fn test_dealer(){
+        // the dealer is whoever sits east, whatever the round wind
+        let game = Game{
+            ruleset: RiichiRuleset::MajSoul, round_wind: Wind::South,
+            dora_markers: None, ura_dora_markers: None, repeats: 0,
+            last_action: None, tiles_left: None, turn: None };
+        let seat = |seat_wind: Wind| Seat{
+            closed_tiles: "m2,m3,m4,p4,p5,p6,s2,s3,s4,s6,s7,dr,dr".to_tiles().unwrap(),
+            called_melds: None, seat_wind, special_yaku: None,
+            latest_tile: Some("s8".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
+        };
+        assert!(Hand::new(game.clone(), seat(Wind::East)).is_dealer());
+        assert!(!Hand::new(game.clone(), seat(Wind::South)).is_dealer());
+        assert_eq!(Hand::new(game.clone(), seat(Wind::East)).payment_split(game.ruleset, 0), Ok(Payment::DealerTsumo(500)));
+        assert_eq!(Hand::new(game.clone(), seat(Wind::South)).payment_split(game.ruleset, 0), Ok(Payment::Tsumo{dealer: 500, non_dealer: 300}));
+    }
+}
@@ -0,0 +1,172 @@
+/*
+Rule variations which decide whether a discard, a call or a win is allowed at all, rather than how it's scored.
+
+Kuikae is discarding, straight after a chi or pon, the tile that was just called; for a chi, throwing the tile on the
+other end of the sequence counts too (calling m4 with m5,m6, then throwing m7). A call is only allowed if it leaves at
+least one legal discard.
+
+Atozuke is winning on a tile which gives the hand a yaku when some of its other waits wouldn't, ie a shanpon on haku and
+m2 with no other yaku. Rulesets which require a yaku at tenpai go further, and don't let an open hand discard into a
+tenpai where none of the waits would have a yaku.
+*/
+
+use crate::tiles::{Tile, TileIs};
+use crate::state::{Game, Seat, TileType, SeatHelper};
+use crate::hand::{Meld, MeldKind, MeldHas, MeldIs, read_win, read_waits};
+use crate::yaku::{Yaku, FindYaku};
+use crate::scoring::CountHan;
+use crate::rulesets::RuleVariations;
+use crate::errors::mahjong_errors::HandError;
+
+///////////////
+// functions //
+///////////////
+
+// Is discarding this tile straight after calling the meld a swap call?
+pub fn is_kuikae(meld: &Meld, discard: &Tile) -> bool {
+    let Some(called) = meld.called_tile() else { return false };
+    if !matches!(meld.kind, Some(MeldKind::Chi | MeldKind::Pon)) { return false }
+    if *discard == called { return true }
+
+    // the other end of a chi, when the called tile was on one end
+    let (Tile::Number { suit, number, .. }, Tile::Number { suit: discard_suit, number: discard_number, .. }) = (called, discard) else { return false };
+    let low = meld.tiles.iter().flatten().filter_map(TileIs::number).min().unwrap_or(number);
+    meld.is_seq() && suit == *discard_suit
+        && ((number == low && *discard_number == number + 3) || (number == low + 2 && *discard_number == number - 3))
+}
+
+// Checks a discard made straight after calling the meld. seat_state.closed_tiles still hold the discard, and
+// seat_state.called_melds already hold the meld.
+//
+// # Errors
+//
+// Returns IllegalCall for kuikae, or for discarding into a tenpai with no yaku when the ruleset requires one.
+pub fn check_discard_after_call<R: RuleVariations>(ruleset: &R, game_state: &Game, seat_state: &Seat, meld: &Meld, discard: &Tile) -> Result<(), HandError> {
+    if !ruleset.allows_kuikae() && is_kuikae(meld, discard) { return Err(HandError::IllegalCall) }
+    if !ruleset.requires_yaku_at_tenpai() { return Ok(()) }
+
+    let mut closed = seat_state.closed_tiles.clone();
+    let position = closed.iter().position(|t| t == discard).ok_or(HandError::TileCount)?;
+    closed.remove(position);
+    let Some(wait) = read_waits(&closed, &seat_state.called_melds) else { return Ok(()) };
+    let after = Seat { closed_tiles: closed, ..seat_state.clone() };
+    if wait.tiles.iter().any(|t| has_yaku(game_state, &after, *t, TileType::Call)) { Ok(()) }
+    else { Err(HandError::IllegalCall) }
+}
+
+// The tiles that may be discarded straight after calling the meld, in hand order.
+pub fn legal_discards_after_call<R: RuleVariations>(ruleset: &R, game_state: &Game, seat_state: &Seat, meld: &Meld) -> Vec<Tile> {
+    seat_state.closed_tiles.iter().filter(|t| check_discard_after_call(ruleset, game_state, seat_state, meld, t).is_ok()).copied().collect()
+}
+
+// Checks a call, from the seat as it would be once the meld is made.
+//
+// # Errors
+//
+// Returns IllegalCall if no discard would be legal afterwards.
+pub fn check_call<R: RuleVariations>(ruleset: &R, game_state: &Game, seat_state: &Seat, meld: &Meld) -> Result<(), HandError> {
+    if legal_discards_after_call(ruleset, game_state, seat_state, meld).is_empty() { Err(HandError::IllegalCall) }
+    else { Ok(()) }
+}
+
+// Checks a winning hand against the ruleset's atozuke rule. Whether the hand has a yaku at all is left to scoring.
+// Only open hands are held to it; a closed hand with a yaku on some of its waits is an ordinary kataagari.
+//
+// # Errors
+//
+// Returns IllegalCall if atozuke isn't allowed and some other wait of the open hand would have no yaku.
+pub fn check_win<R: RuleVariations>(ruleset: &R, game_state: &Game, seat_state: &Seat) -> Result<(), HandError> {
+    if ruleset.allows_atozuke() { return Ok(()) }
+    // closed hands may win kataagari, and riichi is a yaku on every wait anyway
+    if !seat_state.called_melds.as_ref().is_some_and(|melds| melds.iter().any(|m| m.is_open)) || seat_state.riichi.is_some() || seat_state.special_yaku.as_ref().is_some_and(|y| y.contains(&Yaku::Riichi) || y.contains(&Yaku::DoubleRiichi)) { return Ok(()) }
+
+    let latest_type = seat_state.latest_type.ok_or(HandError::ValueError)?;
+    let Some(wait) = read_waits(&seat_state.closed_tiles, &seat_state.called_melds) else { return Ok(()) };
+    if wait.tiles.iter().all(|t| has_yaku(game_state, seat_state, *t, latest_type)) { Ok(()) }
+    else { Err(HandError::IllegalCall) }
+}
+
+// Would winning on the tile give the hand a yaku?
+fn has_yaku(game_state: &Game, seat_state: &Seat, tile: Tile, latest_type: TileType) -> bool {
+    let seat = Seat::new(seat_state.closed_tiles.clone(), seat_state.called_melds.clone(), seat_state.seat_wind,
+        Some(tile), Some(latest_type), seat_state.special_yaku.clone());
+    let is_open = seat.called_melds.as_ref().is_some_and(|melds| melds.iter().any(|m| m.is_open));
+    read_win(&seat.closed_tiles, &seat.called_melds, &seat.latest_tile)
+        .is_some_and(|hands| hands.iter().any(|h| h.yaku(game_state, &seat).han(is_open, game_state.ruleset) > 0))
+}
+
+///////////
+// tests //
+///////////
+
+mod tests {
+    use super::*;
+    use crate::tiles::Wind;
+    use crate::state::GameHelper;
+    use crate::rulesets::RiichiRuleset;
+    use crate::conversions::{ConvertStrings, ConvertTiles};
+    use crate::hand::{Hand, HandTrait};
+    use crate::scoring::settle_win;
+
+    fn called(tiles: &str, kind: MeldKind, called_index: usize) -> Meld {
+        let mut meld = tiles.to_tiles().unwrap().make_meld(true).unwrap();
+        meld.kind = Some(kind);
+        meld.called_index = Some(called_index);
+        meld
+    }
+
+    #[test]
+    fn test_kuikae(){
+        let tile = |t: &str| t.to_tile().unwrap();
+        let chi = called("m4,m5,m6", MeldKind::Chi, 0);
+        assert!(is_kuikae(&chi, &tile("m4")) && is_kuikae(&chi, &tile("m7")));
+        assert!(!is_kuikae(&chi, &tile("m1")) && !is_kuikae(&chi, &tile("p7")));
+        let chi = called("m4,m5,m6", MeldKind::Chi, 2);
+        assert!(is_kuikae(&chi, &tile("m3")) && !is_kuikae(&chi, &tile("m7")));
+        // a kanchan call only forbids the called tile
+        let chi = called("m4,m5,m6", MeldKind::Chi, 1);
+        assert!(is_kuikae(&chi, &tile("m5")) && !is_kuikae(&chi, &tile("m2")) && !is_kuikae(&chi, &tile("m8")));
+        assert!(is_kuikae(&called("dw,dw,dw", MeldKind::Pon, 0), &tile("dw")));
+
+        // a call that leaves only swap discards can't be made
+        let game = Game::new(RiichiRuleset::Default, Wind::East, 0, None, None);
+        let chi = called("m4,m5,m6", MeldKind::Chi, 0);
+        let seat = Seat::new("m4,m7".to_tiles().unwrap(), Some([vec![chi], "p1,p1,p1|s2,s2,s2|s9,s9,s9".to_calls().unwrap()].concat()),
+            Wind::South, None, None, None);
+        assert_eq!(check_call(&RiichiRuleset::Default, &game, &seat, &chi), Err(HandError::IllegalCall));
+        assert_eq!(check_call(&RiichiRuleset::WRC2022, &game, &seat, &chi), Ok(()));
+        let seat = Seat::new("m4,m7,m9,m9,dr".to_tiles().unwrap(), Some([vec![chi], "p1,p1,p1|s2,s2,s2".to_calls().unwrap()].concat()),
+            Wind::South, None, None, None);
+        assert_eq!(legal_discards_after_call(&RiichiRuleset::Default, &game, &seat, &chi), "m9,m9,dr".to_tiles().unwrap());
+        assert_eq!(legal_discards_after_call(&RiichiRuleset::WRC2022, &game, &seat, &chi), "m4,m7,m9,m9,dr".to_tiles().unwrap());
+    }
+
+    #[test]
+    fn test_yaku_at_tenpai(){
+        let game = Game::new(RiichiRuleset::Default, Wind::East, 0, None, None);
+        let pon = called("p2,p2,p2", MeldKind::Pon, 0);
+        let seat = Seat::new("m2,m3,m4,s5,s6,s7,dw,dw,we,m9,m9".to_tiles().unwrap(), Some(vec![pon]), Wind::South, None, None, None);
+
+        // throwing the east leaves a shanpon on haku (yakuhai) and m9 (nothing)
+        assert_eq!(check_discard_after_call(&RiichiRuleset::Default, &game, &seat, &pon, &"we".to_tile().unwrap()), Ok(()));
+        assert_eq!(check_discard_after_call(&RiichiRuleset::JPML2023, &game, &seat, &pon, &"we".to_tile().unwrap()), Ok(()));
+        // throwing a haku from this one leaves m7 and m8, without a yaku on either; throwing an m9 isn't tenpai at all
+        let seat = Seat::new("m2,m3,m4,s5,s6,s7,dw,m8,m9,m9,m9".to_tiles().unwrap(), Some(vec![pon]), Wind::South, None, None, None);
+        assert_eq!(check_discard_after_call(&RiichiRuleset::Default, &game, &seat, &pon, &"dw".to_tile().unwrap()), Ok(()));
+        assert_eq!(check_discard_after_call(&RiichiRuleset::JPML2023, &game, &seat, &pon, &"dw".to_tile().unwrap()), Err(HandError::IllegalCall));
+        assert_eq!(check_discard_after_call(&RiichiRuleset::JPML2023, &game, &seat, &pon, &"m9".to_tile().unwrap()), Ok(()));
+
+        // winning on the haku is atozuke
+        let win = |tile: &str| Seat::new("m2,m3,m4,s5,s6,s7,dw,dw,m9,m9".to_tiles().unwrap(), Some(vec![pon]), Wind::South,
+            tile.to_tile().ok(), Some(TileType::Call), None);
+        assert_eq!(check_win(&RiichiRuleset::Default, &game, &win("dw")), Ok(()));
+        assert_eq!(check_win(&RiichiRuleset::JPML2023, &game, &win("dw")), Err(HandError::IllegalCall));
+        let closed = Seat { called_melds: "!p2,p2,p2,p2".to_calls().ok(), ..win("dw") };
+        assert_eq!(check_win(&RiichiRuleset::JPML2023, &game, &Seat { special_yaku: Some(vec![Yaku::Riichi]), ..closed }), Ok(()));
+        // the hand still reads its yaku, but the win can't be settled
+        let jpml = Game { ruleset: RiichiRuleset::JPML2023, ..game.clone() };
+        assert!(Hand::new(jpml.clone(), win("dw")).han() > 0);
+        assert_eq!(settle_win(&jpml, &win("dw"), Some(Wind::West), 0), Err(HandError::IllegalCall));
+        assert!(settle_win(&game, &win("dw"), Some(Wind::West), 0).is_ok());
+    }
+}
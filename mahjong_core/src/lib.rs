@@ -4,13 +4,23 @@
 
 pub mod tiles;
 pub mod yaku;
-mod errors;
+pub mod errors;
 pub mod scoring;
 pub mod hand;
 pub mod rulesets;
 pub mod state;
 pub mod conversions;
 pub mod composer;
+pub mod ryuukyoku;
+pub mod tenhou;
+pub mod agari;
+pub mod danger;
+pub mod tracker;
+pub mod advisor;
+pub mod legality;
+pub mod wall;
+#[cfg(feature = "shanten-tables")]
+pub mod shanten;
 
 use scoring::HandScore;
 
@@ -34,7 +44,7 @@ use std::io;
 #[allow(clippy::too_many_arguments)]
 pub fn score_hand_from_str(
     closed_tiles: &str,     // comma-separated tiles, ie "p1,p2,p3"
-    called_tiles: &str,     // comma *and* pipe separated, with closed kans additionally enclosed in '()', ie "dw,dw,dw|m1,m2,m3|(p5,p5,p5,p5r)"
+    called_tiles: &str,     // comma *and* pipe separated, with closed kans prefixed by '!' and added kans by '+', ie "dw,dw,dw@w|m1*,m2,m3|!p5,p5,p5,p5r"
     latest_tile: &str,      // single tile, ie "m5r"
     seat_wind: char,        // single char, ie 'e' = east
     round_wind: char,
@@ -58,9 +68,12 @@ pub fn score_hand_from_str(
         seat_wind.to_wind().map_err(HandError::ParseError)?,
         latest_tile.to_tile().ok(),
         latest_type.to_tile_type().ok(),
-        special_yaku.to_yaku_vec().ok(),
+        Some(special_yaku.to_yaku_vec().map_err(HandError::ParseError)?),
     );
 
+    yaku::find_riichi_yaku(&game_state, &seat_state)?;
+    yaku::find_situational_yaku(&game_state, &seat_state)?;
+    yaku::find_first_turn_yaku(&game_state, &seat_state)?;
     Hand::new(game_state.clone(), seat_state).payment_split(game_state.ruleset, game_state.repeats)
 }
 
@@ -111,4 +124,13 @@ pub fn score_hand_from_str(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::errors::mahjong_errors::ParsingError;
+
+    #[test]
+    fn special_yaku_from_str(){
+        let score = |special: &str| score_hand_from_str("m2,m3,m4,p5,p6,p7,s3,s4,s5,dr,dr,m7,m8", "", "m9", 's', 'e', 'c', "", "", special, 0, "MajSoul");
+        assert_eq!(score("riichi"), Ok(Payment::Ron(1300)));
+        assert_eq!(score("chinitsu"), Err(HandError::ParseError(ParsingError::BadString)));
+        assert_eq!(score("pinfu"), Err(HandError::ParseError(ParsingError::BadString)));
+    }
 }
\ No newline at end of file
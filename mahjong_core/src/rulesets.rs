@@ -7,6 +7,7 @@ TODO: option for custom ruleset loaded from JSON; wrap local yaku support into t
 
 use std::env;
 use std::cell::RefCell;
+use crate::hand::MeldKind;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum RiichiRuleset {
@@ -24,7 +25,8 @@ pub trait RuleVariations {
     fn has_double_yakuman(&self) -> bool {true}     // are special yakuman worth 2x limit?
     fn kazoe_yakuman_score(&self) -> u32 {8000}     // do 13+ han hands count as yakuman?
     fn double_wind_fu(&self) -> u8 {4}              // fu for seat+round wind pairs
-    fn is_rinshan_tsumo(&self) -> bool {false}      // do rinshan winds score fu as a ron or tsumo?
+    fn is_rinshan_tsumo(&self, kan: Option<MeldKind>) -> bool {false}  // do rinshan wins score fu as a ron or tsumo? kan is the kind of kan drawn for, if known
+    fn flips_open_kandora_immediately(&self) -> bool {false} // otherwise after the replacement draw's discard (see wall.rs)
     fn repeat_payment_ron(&self, counters: u8) -> i32 {i32::from(counters) * 300}
     fn repeat_payment_tsumo(&self, counters: u8) -> i32 {i32::from(counters) * 100}
     fn allows_all_green_hatsu(&self) -> bool {true} // is hatsu permitted in all green yakuman?
@@ -32,8 +34,34 @@ pub trait RuleVariations {
     fn allows_ippatsu(&self) -> bool {false}
     fn allows_double_riichi(&self) -> bool {true}
     fn allows_nagashi_mangan(&self) -> bool {true}
+    fn renho_han(&self) -> u8 {0}                   // han for renho, a local yaku; 0 if it isn't played
+    fn nagashi_mangan_is_tsumo(&self) -> bool {true}   // is nagashi mangan paid as a tsumo (with repeats) instead of tenpai payments?
     fn counts_akadora(&self) -> bool {true}
     fn allows_open_tanyao(&self) -> bool {true}
+
+    // legality (see legality.rs)
+    fn allows_kuikae(&self) -> bool {false}           // discarding the called tile, or its suji across a chi, right after calling
+    fn allows_atozuke(&self) -> bool {true}           // winning on a tile that gives yaku when some of the other waits wouldn't
+    fn requires_yaku_at_tenpai(&self) -> bool {false} // an open hand can't discard into a tenpai with no yaku on any wait
+    fn allows_ankan_chankan(&self) -> bool {true}     // can kokushi rob a closed kan?
+
+    // abortive draws
+    fn allows_kyuushu_kyuuhai(&self) -> bool {true}   // nine different terminals/honors on the first draw
+    fn has_suufon_renda(&self) -> bool {true}         // four identical wind discards on the first go-around
+    fn has_suucha_riichi(&self) -> bool {true}        // all four players declare riichi
+    fn has_suukaikan(&self) -> bool {true}            // four kans declared by more than one player
+    fn suukaikan_on_discard(&self) -> bool {true}     // the hand aborts once the fourth kan's replacement discard passes; otherwise as soon as its replacement draw doesn't win
+    fn max_kans(&self) -> usize {4}                   // kans per hand, across the table; the dead wall has no more than four replacement draws
+    fn has_sanchahou(&self) -> bool {true}            // three players ron the same discard
+
+    // multiple ron
+    fn has_atamahane(&self) -> bool {false}           // only the first player in turn order after the discarder wins
+    fn pays_repeats_to_all_winners(&self) -> bool {false} // otherwise repeats only go to the first winner in turn order
+
+    // pao (liability payments)
+    fn has_pao(&self) -> bool {true}                  // feeding the final dragon/wind meld of daisangen/daisushi
+    fn pao_splits_ron(&self) -> bool {true}           // on ron, liable player pays half. otherwise the discarder pays it all
+    fn has_daiminkan_pao(&self) -> bool {false}       // feeding a daiminkan makes that player pay for a rinshan win
 }
 
 impl RuleVariations for RiichiRuleset {
@@ -47,8 +75,13 @@ impl RuleVariations for RiichiRuleset {
         RiichiRuleset::MajSoul => 8000, _ => 6000, } }
     fn double_wind_fu(&self) -> u8 { match self {
         RiichiRuleset::MajSoul | RiichiRuleset::JPML2022 => 4, _ => 2, } } // TODO: verify EMA rules
-    fn is_rinshan_tsumo(&self) -> bool { 
-        !matches!(self, RiichiRuleset::JPML2022) } // TODO: verify MajSoul rules
+    // JPML2023 has the seat which fed a daiminkan pay for a rinshan win on it, and scores it like a ron.
+    fn is_rinshan_tsumo(&self, kan: Option<MeldKind>) -> bool { match self {
+        RiichiRuleset::JPML2022 => false,
+        RiichiRuleset::JPML2023 => kan != Some(MeldKind::Daiminkan),
+        _ => true, } } // TODO: verify MajSoul rules
+    fn flips_open_kandora_immediately(&self) -> bool {
+        matches!(self, RiichiRuleset::EMA2016 | RiichiRuleset::WRC2022) } // TODO: verify WRC rules
     fn allows_all_green_hatsu(&self) -> bool { true }
     fn requires_all_green_hatsu(&self) -> bool { 
         matches!(self, RiichiRuleset::JPML2022) }
@@ -58,6 +91,37 @@ impl RuleVariations for RiichiRuleset {
         !matches!(self, RiichiRuleset::EMA2016) }
     fn counts_akadora(&self) -> bool {
         matches!(self, RiichiRuleset::MajSoul | RiichiRuleset::WRC2022) }
+    // none of the tournament or online rulesets play renho; where it's played locally, it's most often a mangan.
+    fn renho_han(&self) -> u8 { match self {
+        RiichiRuleset::Default => 5, _ => 0, } }
+    // EMA and WRC don't have abortive draws at all.
+    fn allows_kyuushu_kyuuhai(&self) -> bool {
+        !matches!(self, RiichiRuleset::EMA2016 | RiichiRuleset::WRC2022) } // TODO: verify JPML rules
+    fn has_suufon_renda(&self) -> bool {
+        !matches!(self, RiichiRuleset::EMA2016 | RiichiRuleset::WRC2022) }
+    fn has_suucha_riichi(&self) -> bool {
+        !matches!(self, RiichiRuleset::EMA2016 | RiichiRuleset::WRC2022) }
+    fn has_suukaikan(&self) -> bool {
+        !matches!(self, RiichiRuleset::EMA2016 | RiichiRuleset::WRC2022) }
+    fn has_sanchahou(&self) -> bool {
+        !matches!(self, RiichiRuleset::EMA2016 | RiichiRuleset::WRC2022) } // TODO: verify MajSoul rules
+    fn has_atamahane(&self) -> bool {
+        matches!(self, RiichiRuleset::JPML2022 | RiichiRuleset::JPML2023 | RiichiRuleset::WRC2022) } // TODO: verify EMA rules
+    fn pays_repeats_to_all_winners(&self) -> bool {
+        matches!(self, RiichiRuleset::EMA2016 | RiichiRuleset::MajSoul) } // TODO: verify MajSoul rules
+    fn has_pao(&self) -> bool {
+        !matches!(self, RiichiRuleset::EMA2016) } // TODO: verify EMA rules
+    fn has_daiminkan_pao(&self) -> bool {
+        matches!(self, RiichiRuleset::JPML2022 | RiichiRuleset::JPML2023) } // TODO: verify JPML rules
+    fn allows_kuikae(&self) -> bool {
+        matches!(self, RiichiRuleset::EMA2016 | RiichiRuleset::WRC2022) } // TODO: verify EMA rules
+    // JPML plays strict sakizuke: the yaku has to be there at tenpai, on every wait.
+    fn allows_atozuke(&self) -> bool {
+        !matches!(self, RiichiRuleset::JPML2022 | RiichiRuleset::JPML2023) } // TODO: verify JPML rules
+    fn requires_yaku_at_tenpai(&self) -> bool {
+        matches!(self, RiichiRuleset::JPML2022 | RiichiRuleset::JPML2023) } // TODO: verify JPML rules
+    fn allows_ankan_chankan(&self) -> bool {
+        !matches!(self, RiichiRuleset::EMA2016) } // TODO: verify WRC rules
 }
 
 mod tests {
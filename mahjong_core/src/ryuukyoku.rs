@@ -0,0 +1,318 @@
+/*
+When the wall runs out without anyone winning, the hand ends in an exhaustive draw. Players who are tenpai split a
+3000 point pool paid by the players who aren't; a player whose discards were all terminals and honors (and were never
+called) may instead be paid for nagashi mangan, depending on the ruleset.
+
+A hand can also be abandoned partway through. Which abortive draws exist varies between rulesets; EMA and WRC don't use any.
+*/
+
+use crate::tiles::{Tile, Wind, TileIs};
+use crate::state::{Game, Seat, Discard, WinType};
+use crate::hand::{read_waits, MeldIs};
+use crate::yaku::Yaku;
+use crate::errors::mahjong_errors::HandError;
+use crate::scoring::{Payment, calc_player_split};
+use crate::rulesets::RuleVariations;
+
+static NOTEN_POOL: i32 = 3000;
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Ryuukyoku {
+    Exhaustive {
+        tenpai: Vec<Wind>,
+        nagashi: Vec<Wind>,
+        points: [i32; 4],   // point changes for each seat, indexed by seat wind (east, south, west, north)
+    },
+    Abortive(AbortiveDraw),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AbortiveDraw {
+    KyuushuKyuuhai, // nine different terminals/honors on a player's first draw. optional.
+    SuufonRenda,    // everyone discards the same wind on the first go-around
+    SuuchaRiichi,   // all four players declare riichi
+    Suukaikan,      // four kans, declared by more than one player
+    Sanchahou,      // three players ron the same discard
+}
+
+///////////////
+// functions //
+///////////////
+
+// Resolves an exhaustive draw from the final state of every seat.
+// Each seat should hold 13 tiles (closed tiles plus called melds); latest_tile is ignored.
+//
+// # Errors
+//
+// Returns ValueError if the seats don't cover each wind exactly once, and TileCount if any seat has the wrong number of tiles.
+pub fn exhaustive_draw(game_state: &Game, seats: &[Seat]) -> Result<Ryuukyoku, HandError> {
+    if seats.len() != 4 || [Wind::East, Wind::South, Wind::West, Wind::North].iter().any(|w| !seats.iter().any(|s| s.seat_wind == *w)) {
+        return Err(HandError::ValueError)
+    }
+
+    let mut tenpai: Vec<Wind> = Vec::with_capacity(4);
+    for seat in seats {
+        if is_tenpai(seat)? { tenpai.push(seat.seat_wind) }
+    }
+
+    let nagashi: Vec<Wind> = if game_state.ruleset.allows_nagashi_mangan() {
+        seats.iter().filter(|s| is_nagashi(s)).map(|s| s.seat_wind).collect()
+    } else { Vec::new() };
+
+    let mut points: [i32; 4] = [0; 4];
+
+    for winner in &nagashi {
+        let payment = calc_player_split(2000, *winner == Wind::East, WinType::Tsumo, 0)?;
+        for payer in seats.iter().map(|s| s.seat_wind).filter(|w| w != winner) {
+            let mut owed = i32::try_from(match payment {
+                Payment::DealerTsumo(value) => value,
+                Payment::Tsumo{dealer, non_dealer} => if payer == Wind::East { dealer } else { non_dealer },
+                Payment::Ron(_) => unreachable!("tsumo split should never be a ron"),
+            }).map_err(|_| HandError::ValueError)?;
+            if game_state.ruleset.nagashi_mangan_is_tsumo() { owed += game_state.ruleset.repeat_payment_tsumo(game_state.repeats) }
+
+            points[payer as usize] -= owed;
+            points[*winner as usize] += owed;
+        }
+    }
+
+    // nagashi mangan normally stands in for a tsumo, so there's nothing left to settle.
+    if (nagashi.is_empty() || !game_state.ruleset.nagashi_mangan_is_tsumo()) && !tenpai.is_empty() && tenpai.len() < seats.len() {
+        let noten_count = i32::try_from(seats.len() - tenpai.len()).map_err(|_| HandError::ValueError)?;
+        let tenpai_count = i32::try_from(tenpai.len()).map_err(|_| HandError::ValueError)?;
+
+        for seat in seats {
+            if tenpai.contains(&seat.seat_wind) { points[seat.seat_wind as usize] += NOTEN_POOL / tenpai_count }
+            else { points[seat.seat_wind as usize] -= NOTEN_POOL / noten_count }
+        }
+    }
+
+    Ok(Ryuukyoku::Exhaustive { tenpai, nagashi, points })
+}
+
+// Checks whether the table calls for an abortive draw. Meant to be called after each discard, riichi, or kan;
+// ron_count is the number of players who called ron on the latest discard.
+// Kyuushu kyuuhai is up to the player, so it's checked separately by can_declare_kyuushu_kyuuhai().
+pub fn abortive_draw(game_state: &Game, seats: &[Seat], ron_count: usize) -> Option<Ryuukyoku> {
+    let ruleset = game_state.ruleset;
+
+    if ruleset.has_sanchahou() && ron_count >= 3 {
+        return Some(Ryuukyoku::Abortive(AbortiveDraw::Sanchahou))
+    }
+    // a ron ends the hand before riichi is finalized or any other abortive draw can happen.
+    if ron_count > 0 { return None }
+
+    if ruleset.has_suucha_riichi() && seats.len() == 4 && seats.iter().all(|s| s.riichi.is_some() || s.special_yaku.as_ref()
+        .is_some_and(|y| y.contains(&Yaku::Riichi) || y.contains(&Yaku::DoubleRiichi))) {
+        return Some(Ryuukyoku::Abortive(AbortiveDraw::SuuchaRiichi))
+    }
+
+    let kans: Vec<usize> = seats.iter().map(|s| s.called_melds.as_ref().map_or(0, |melds| melds.iter().filter(|m| m.is_quad()).count())).collect();
+    if ruleset.has_suukaikan() && kans.iter().sum::<usize>() >= ruleset.max_kans() && kans.iter().filter(|k| **k > 0).count() > 1 {
+        return Some(Ryuukyoku::Abortive(AbortiveDraw::Suukaikan))
+    }
+
+    if ruleset.has_suufon_renda() && seats.len() == 4 && is_uninterrupted(seats) {
+        let first_discards: Vec<Tile> = seats.iter().filter_map(|s| s.discards.as_ref()
+            .filter(|d| d.len() == 1).map(|d| d[0].tile)).collect();
+        if first_discards.len() == 4 && first_discards[0].is_wind() && first_discards.iter().all(|t| *t == first_discards[0]) {
+            return Some(Ryuukyoku::Abortive(AbortiveDraw::SuufonRenda))
+        }
+    }
+
+    None
+}
+
+// Checks whether the seat can abandon the hand after its first draw. The seat's latest_tile should be the drawn tile.
+pub fn can_declare_kyuushu_kyuuhai(game_state: &Game, seats: &[Seat], seat: &Seat) -> bool {
+    if !game_state.ruleset.allows_kyuushu_kyuuhai() || !is_uninterrupted(seats)
+    || seat.discards.as_ref().is_some_and(|d| !d.is_empty()) {
+        return false
+    }
+
+    let mut tiles: Vec<Tile> = seat.closed_tiles.iter().chain(seat.latest_tile.iter()).filter(|t| !t.is_simple()).copied().collect();
+    tiles.sort();
+    tiles.dedup();
+    tiles.len() >= 9
+}
+
+// Nobody has called anything (including closed kans), so the first go-around hasn't been interrupted.
+fn is_uninterrupted(seats: &[Seat]) -> bool {
+    seats.iter().all(|s| s.called_melds.as_ref().is_none_or(Vec::is_empty))
+}
+
+// Checks whether a seat's 13 tiles are waiting on anything.
+fn is_tenpai(seat: &Seat) -> Result<bool, HandError> {
+    let called_count: usize = seat.called_melds.as_ref().map_or(0, |melds| melds.len() * 3);
+    if seat.closed_tiles.len() + called_count != 13 { return Err(HandError::TileCount) }
+
+    Ok(read_waits(&seat.closed_tiles, &seat.called_melds).is_some())
+}
+
+// Nagashi mangan requires that every discard was a terminal or honor, and that none of them were called.
+fn is_nagashi(seat: &Seat) -> bool {
+    seat.discards.as_ref().is_some_and(|discards| {
+        !discards.is_empty() && discards.iter().all(|d| !d.called && !d.tile.is_simple())
+    })
+}
+
+///////////
+// tests //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{SeatHelper, Riichi};
+    use crate::rulesets::RiichiRuleset;
+    use crate::conversions::ConvertStrings;
+
+    fn seat(closed: &str, calls: &str, wind: Wind, discards: &str) -> Seat {
+        let mut seat = Seat::new(closed.to_tiles().unwrap(), calls.to_calls().ok(), wind, None, None, None);
+        seat.discards = discards.to_tiles().ok().map(|tiles| tiles.into_iter().map(|tile| Discard{tile, called: false}).collect());
+        seat
+    }
+
+    fn game(ruleset: RiichiRuleset, repeats: u8) -> Game {
+        Game{ ruleset, round_wind: Wind::East, repeats, dora_markers: None, ura_dora_markers: None, last_action: None, tiles_left: None, turn: None }
+    }
+
+    #[test]
+    fn test_tenpai_payments() {
+        let tenpai = "m2,m3,m4,p2,p3,p4,s2,s3,s4,dr,dr,m5,m6";
+        let noten = "m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn";
+
+        let seats = [seat(tenpai, "", Wind::East, "p5"), seat(noten, "", Wind::South, "p5"),
+                    seat(noten, "", Wind::West, "p5"), seat(noten, "", Wind::North, "p5")];
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::Default, 0), &seats), Ok(Ryuukyoku::Exhaustive{
+            tenpai: vec![Wind::East], nagashi: Vec::new(), points: [3000, -1000, -1000, -1000] }));
+
+        let seats = [seat(tenpai, "", Wind::East, "p5"), seat(noten, "", Wind::South, "p5"),
+                    seat(tenpai, "", Wind::West, "p5"), seat(noten, "", Wind::North, "p5")];
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::Default, 0), &seats), Ok(Ryuukyoku::Exhaustive{
+            tenpai: vec![Wind::East, Wind::West], nagashi: Vec::new(), points: [1500, -1500, 1500, -1500] }));
+
+        let seats = [seat(tenpai, "", Wind::East, "p5"), seat(tenpai, "", Wind::South, "p5"),
+                    seat(tenpai, "", Wind::West, "p5"), seat(noten, "", Wind::North, "p5")];
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::Default, 0), &seats), Ok(Ryuukyoku::Exhaustive{
+            tenpai: vec![Wind::East, Wind::South, Wind::West], nagashi: Vec::new(), points: [1000, 1000, 1000, -3000] }));
+
+        let seats = [seat(noten, "", Wind::East, "p5"), seat(noten, "", Wind::South, "p5"),
+                    seat(noten, "", Wind::West, "p5"), seat(noten, "", Wind::North, "p5")];
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::Default, 0), &seats), Ok(Ryuukyoku::Exhaustive{
+            tenpai: Vec::new(), nagashi: Vec::new(), points: [0; 4] }));
+
+        // open hands are fine, but waiting on a tile the hand already holds all four of isn't tenpai.
+        let seats = [seat("m5,m6,p2,p3,p4,dr,dr", "s2,s3,s4|m2,m3,m4", Wind::East, "p5"), seat("p1,p2,p3,p5,p6,p7,s2,s3,s4,m5,m5,m5,m5", "", Wind::South, "p5"),
+                    seat(noten, "", Wind::West, "p5"), seat(noten, "", Wind::North, "p5")];
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::Default, 0), &seats), Ok(Ryuukyoku::Exhaustive{
+            tenpai: vec![Wind::East], nagashi: Vec::new(), points: [3000, -1000, -1000, -1000] }));
+    }
+
+    #[test]
+    fn test_nagashi_mangan() {
+        let tenpai = "m2,m3,m4,p2,p3,p4,s2,s3,s4,dr,dr,m5,m6";
+        let noten = "m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn";
+        let seats = [seat(tenpai, "", Wind::East, "p5"), seat(noten, "", Wind::South, "m1,we,dr,s9"),
+                    seat(noten, "", Wind::West, "p1,p2"), seat(noten, "", Wind::North, "p5")];
+
+        // paid as a non-dealer mangan tsumo instead of tenpai payments
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::Default, 1), &seats), Ok(Ryuukyoku::Exhaustive{
+            tenpai: vec![Wind::East], nagashi: vec![Wind::South], points: [-4100, 8300, -2100, -2100] }));
+
+        // not a valid yaku in EMA
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::EMA2016, 1), &seats), Ok(Ryuukyoku::Exhaustive{
+            tenpai: vec![Wind::East], nagashi: Vec::new(), points: [3000, -1000, -1000, -1000] }));
+
+        // a called discard breaks nagashi
+        let mut seats = seats;
+        seats[1].discards.as_mut().unwrap()[2].called = true;
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::Default, 1), &seats), Ok(Ryuukyoku::Exhaustive{
+            tenpai: vec![Wind::East], nagashi: Vec::new(), points: [3000, -1000, -1000, -1000] }));
+    }
+
+    #[test]
+    fn test_abortive_draws() {
+        let noten = "m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn";
+        let game_state = game(RiichiRuleset::Default, 0);
+
+        // suufon renda
+        let seats = [seat(noten, "", Wind::East, "wn"), seat(noten, "", Wind::South, "wn"),
+                    seat(noten, "", Wind::West, "wn"), seat(noten, "", Wind::North, "wn")];
+        assert_eq!(abortive_draw(&game_state, &seats, 0), Some(Ryuukyoku::Abortive(AbortiveDraw::SuufonRenda)));
+        assert_eq!(abortive_draw(&game(RiichiRuleset::EMA2016, 0), &seats, 0), None);
+        let seats = [seat(noten, "", Wind::East, "wn"), seat(noten, "", Wind::South, "wn"),
+                    seat(noten, "", Wind::West, "wn"), seat(noten, "", Wind::North, "ww")];
+        assert_eq!(abortive_draw(&game_state, &seats, 0), None);
+        let seats = [seat(noten, "", Wind::East, "dr"), seat(noten, "", Wind::South, "dr"),
+                    seat(noten, "", Wind::West, "dr"), seat(noten, "", Wind::North, "dr")];
+        assert_eq!(abortive_draw(&game_state, &seats, 0), None);
+
+        // suucha riichi
+        let mut seats = [seat(noten, "", Wind::East, "p5"), seat(noten, "", Wind::South, "p5"),
+                    seat(noten, "", Wind::West, "p5"), seat(noten, "", Wind::North, "p5")];
+        seats.iter_mut().for_each(|s| s.special_yaku = Some(vec![Yaku::Riichi]));
+        seats[2].special_yaku = Some(vec![Yaku::DoubleRiichi]);
+        assert_eq!(abortive_draw(&game_state, &seats, 0), Some(Ryuukyoku::Abortive(AbortiveDraw::SuuchaRiichi)));
+        assert_eq!(abortive_draw(&game_state, &seats, 1), None);
+        seats[3].special_yaku = None;
+        assert_eq!(abortive_draw(&game_state, &seats, 0), None);
+        // seats which only track their riichi declaration count too
+        seats[3].riichi = Some(Riichi::new(3, "p5".to_tile().unwrap(), false));
+        assert_eq!(abortive_draw(&game_state, &seats, 0), Some(Ryuukyoku::Abortive(AbortiveDraw::SuuchaRiichi)));
+        seats.iter_mut().for_each(|s| s.special_yaku = None);
+        assert_eq!(abortive_draw(&game_state, &seats, 0), None);
+        seats.iter_mut().enumerate().for_each(|(i, s)| s.riichi = Some(Riichi::new(i as u8, "p5".to_tile().unwrap(), false)));
+        assert_eq!(abortive_draw(&game_state, &seats, 0), Some(Ryuukyoku::Abortive(AbortiveDraw::SuuchaRiichi)));
+
+        // suukaikan, but only if more than one player called the kans
+        let seats = [seat("m1,m4,m7,p2,p5", "!dr,dr,dr,dr|s1,s1,s1,s1", Wind::East, "p5"), seat("m1,m4,m7,p2,p5,p8,s3,s6,s9", "we,we,we,we", Wind::South, "p5"),
+                    seat("m1,m4,m7,p2,p5,p8,s3,s6,s9", "wn,wn,wn,wn", Wind::West, "p5"), seat(noten, "", Wind::North, "p5")];
+        assert_eq!(abortive_draw(&game_state, &seats, 0), Some(Ryuukyoku::Abortive(AbortiveDraw::Suukaikan)));
+        let seats = [seat("m1", "!dr,dr,dr,dr|s1,s1,s1,s1|we,we,we,we|wn,wn,wn,wn", Wind::East, "p5"), seat(noten, "", Wind::South, "p5"),
+                    seat(noten, "", Wind::West, "p5"), seat(noten, "", Wind::North, "p5")];
+        assert_eq!(abortive_draw(&game_state, &seats, 0), None);
+
+        // sanchahou
+        assert_eq!(abortive_draw(&game_state, &seats, 3), Some(Ryuukyoku::Abortive(AbortiveDraw::Sanchahou)));
+        assert_eq!(abortive_draw(&game(RiichiRuleset::WRC2022, 0), &seats, 3), None);
+    }
+
+    #[test]
+    fn test_kyuushu_kyuuhai() {
+        let game_state = game(RiichiRuleset::Default, 0);
+        let mut dealer = seat("m1,m9,p1,p9,s1,s9,s3,s4,s5,dr,dr,we,m5", "", Wind::East, "");
+        dealer.latest_tile = "wn".to_tile().ok();
+        let others = [seat("m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn", "", Wind::South, ""),
+                    seat("m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn", "", Wind::West, ""),
+                    seat("m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn", "", Wind::North, "")];
+        let seats = [dealer.clone(), others[0].clone(), others[1].clone(), others[2].clone()];
+        assert!(can_declare_kyuushu_kyuuhai(&game_state, &seats, &dealer));
+        assert!(!can_declare_kyuushu_kyuuhai(&game(RiichiRuleset::EMA2016, 0), &seats, &dealer));
+
+        // eight kinds isn't enough
+        let mut short = dealer.clone();
+        short.latest_tile = "dr".to_tile().ok();
+        assert!(!can_declare_kyuushu_kyuuhai(&game_state, &seats, &short));
+
+        // a call interrupts the first go-around
+        let mut seats = seats;
+        seats[2] = seat("m1,m4,m7,p2,p5,p8,s3,s6,s9,dr", "dg,dg,dg", Wind::West, "");
+        assert!(!can_declare_kyuushu_kyuuhai(&game_state, &seats, &dealer));
+    }
+
+    #[test]
+    fn test_bad_draws() {
+        let noten = "m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn";
+        let seats = [seat(noten, "", Wind::East, "p5"), seat(noten, "", Wind::South, "p5"), seat(noten, "", Wind::West, "p5")];
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::Default, 0), &seats), Err(HandError::ValueError));
+
+        let seats = [seat(noten, "", Wind::East, "p5"), seat(noten, "", Wind::South, "p5"),
+                    seat(noten, "", Wind::West, "p5"), seat("m1,m4,m7", "", Wind::North, "p5")];
+        assert_eq!(exhaustive_draw(&game(RiichiRuleset::Default, 0), &seats), Err(HandError::TileCount));
+    }
+}
@@ -1,9 +1,11 @@
-use crate::state::{Win, TileType, WinType, Game, Seat, InferWin};
+use crate::state::{Win, TileType, WinType, Game, Seat, InferWin, LastAction};
 use crate::errors::mahjong_errors::HandError;
-use crate::yaku::{Yaku, YAKUMAN, YakuHelpers};
+use crate::yaku::{Yaku, YAKUMAN, YakuHelpers, YakuMetadata, find_riichi_yaku, find_situational_yaku, find_first_turn_yaku};
 use crate::tiles::{Tile, Dragon, Wind, Suit, TileIs, TileRelations};
-use crate::hand::{Hand, HandShape, Meld, Pair, MeldHas, MeldIs, PairTrait, MeldVecHas};
+use crate::hand::{Hand, HandShape, HandTrait, Meld, MeldKind, Pair, MeldHas, MeldIs, PairTrait, MeldVecHas, ReadWait, WaitShape};
 use crate::rulesets::{RiichiRuleset, RuleVariations};
+use crate::ryuukyoku::AbortiveDraw;
+use crate::legality::check_win;
 
 ///////////////////////
 // structs and enums //
@@ -19,6 +21,17 @@ pub enum Payment{
     Ron(u32)
 }
 
+// The combined result of every ron called on a single discard.
+#[derive(Debug, PartialEq, Clone)]
+pub enum RonSettlement {
+    Wins {
+        winners: Vec<Wind>,     // in turn order after the discarder
+        payments: Vec<Payment>, // each winner's hand value, without repeats or deposits
+        points: [i32; 4],       // point changes for each seat, indexed by seat wind (east, south, west, north)
+    },
+    Abortive(AbortiveDraw),
+}
+
 ////////////
 // traits //
 ////////////
@@ -80,6 +93,7 @@ impl CountFu for HandShape {
             HandShape::Standard {melds, pair} => {
                 let mut fu: u8 = 20;    // 20 fu for winning
                 let winning_tile: Tile = seat_state.latest_tile.unwrap();
+                let last_kan = if let Some(LastAction::KanDraw(_, kind)) = game_state.last_action { Some(kind) } else { None };
     
                 if let Some(tile_type) = seat_state.latest_type {
                     match tile_type {
@@ -87,7 +101,7 @@ impl CountFu for HandShape {
                         TileType::Call if melds.iter().all(|m| !m.is_open ) => fu += 10,// 10 fu for a ron with a closed han
                         TileType::Draw if yaku.contains(&Yaku::Pinfu) => return Ok(20), // 20 fu for closed pinfu
                         TileType::Draw => fu += 2, // closed pinfu is already considered, so a tsumo gains 2 fu.
-                        TileType::Kan if game_state.ruleset.is_rinshan_tsumo() => fu += 2, // rinshan fu rule
+                        TileType::Kan if game_state.ruleset.is_rinshan_tsumo(last_kan) => fu += 2, // rinshan fu rule
                         _ => (),
                     }
                 } else { panic!("latest_type set to none during win?") }
@@ -98,35 +112,16 @@ impl CountFu for HandShape {
                     else if wind == game_state.round_wind || wind == seat_state.seat_wind { fu += 2 }
                 }
     
-                if pair.tile() == winning_tile { fu += 2 } // 2 fu for a pair wait
+                // 2 fu for a middle, edge or pair wait, if the winning tile can be read as one
+                let wait_shapes = self.wait_shapes(&winning_tile);
+                if wait_shapes.iter().any(|s| matches!(s, WaitShape::Kanchan | WaitShape::Penchan | WaitShape::Tanki)) { fu += 2 }
 
-                let mut awarded_wait: bool = false;
                 for meld in melds {
-                    if meld.is_seq() && !meld.is_open && meld.contains(&winning_tile) {
-                        // sequences only get fu for middle waits and single-sided waits (ie 1,2 waiting on 3)
-                        if !awarded_wait && 
-                            ( (meld.tiles[1].is_some_and(|t| t == winning_tile) && pair.tile() != winning_tile)
-                            || (meld.has_terminal() && !winning_tile.is_terminal()) ) {
-                            awarded_wait = true;
-                            fu += 2;
-                        }
-                    } else if meld.is_trip() {
-                        if meld.is_open { fu += meld.base_fu() }
-                        else if let Some(tile_type) = seat_state.latest_type { // check whether the meld was opened by the winning call
-                            match tile_type {
-                                TileType::Call => {
-                                    if meld.contains(&winning_tile) { // check if the win could have opened it.
-                                        // check if there are any sequences which could have been the wait instead.
-                                        if melds.iter().filter(|m| m.is_seq()).any(|m| !m.is_open && m.contains(&winning_tile)) {
-                                            fu += meld.base_fu() * 2;    // if so, it should have stayed closed
-                                            // TODO: are there any yaku or edge cases to check for?
-                                        } else { fu += meld.base_fu() } // otherwise, it was opened.
-                                    }
-                                    else { fu += meld.base_fu() * 2 }
-                                }
-                                _ => fu += meld.base_fu() * 2,
-                            }
-                        }
+                    if meld.is_trip() {
+                        // a ron opens the triplet it completes, unless the winning tile could have gone somewhere else
+                        if meld.is_open || (seat_state.latest_type == Some(TileType::Call) && meld.contains(&winning_tile)
+                            && wait_shapes.iter().all(|s| *s == WaitShape::Shanpon)) { fu += meld.base_fu() }
+                        else { fu += meld.base_fu() * 2 }
                     } else if meld.is_quad() {
                         if meld.is_open { fu += meld.base_fu() }
                         else { fu += meld.base_fu() * 2 }
@@ -146,49 +141,26 @@ impl CountHan for Vec<Yaku> {
     fn han(&self, is_open: bool, ruleset: RiichiRuleset) -> u8 {
         let mut has_yakuman: bool = false;
         self.iter().fold(0, |han_count, y|{
-            if !has_yakuman || YAKUMAN.contains(y) {
+            let info = y.info();
+            if info.yakuman > 0 {
+                let han = if ruleset.has_double_yakuman() { 13 * info.yakuman } else { 13 };
+                if has_yakuman && ruleset.has_yakuman_stacking() { han_count + han }
+                // without stacking, only the highest yakuman counts
+                else if has_yakuman { han_count.max(han) }
+                else { has_yakuman = true; han }
+            } else if has_yakuman {
+                han_count
+            } else {
                 match y {
-                    // special criteria
-                    Yaku::Chiitoi => han_count + 2,
-    
-                    // based on luck
-                    Yaku::ClosedTsumo => han_count + 1,
-    
-                    // based on sequences
-                    Yaku::Pinfu if !is_open => han_count + 1,
-                    Yaku::Ipeiko => han_count + 1,
-                    Yaku::SanshokuDoujun | Yaku::Ittsuu => han_count + if is_open { 1 } else { 2 },
-                    Yaku::Ryanpeiko => han_count + 3,
-    
-                    // based on triplets/quads
-                    Yaku::Toitoi | Yaku::Sananko | Yaku::SanshokuDouko | Yaku::Sankantsu => han_count + 2,
-    
-                    // based on terminal/honor
-                    Yaku::Tanyao if !is_open || ruleset.allows_open_tanyao() => han_count + 1,
-                    Yaku::Yakuhai(count) => han_count + count,
-                    Yaku::Chanta => han_count + if is_open { 1 } else { 2 },
-                    Yaku::Junchan => han_count + if is_open { 2 } else { 3 },
-                    Yaku::Honro | Yaku::Shosangen => han_count + 2,
-    
-                    // based on suits
-                    Yaku::Honitsu => han_count + if is_open { 2 } else { 3 },
-                    Yaku::Chinitsu => han_count + if is_open { 5 } else { 6 },
-    
-                    // special yaku
-                    Yaku::Riichi | Yaku::UnderRiver | Yaku::UnderSea | Yaku::AfterKan | Yaku::RobbedKan => han_count + 1,
-                    Yaku::Ippatsu => if ruleset.allows_ippatsu() { han_count + 1 } else { han_count },
-                    Yaku::DoubleRiichi => han_count + if ruleset.allows_double_riichi() { 2 } else { 1 },
-                    Yaku::NagashiMangan => han_count, // this should only happen if nagashi mangan isn't a valid yaku in the active ruleset.
-    
-                    // yakuman hands
-                    Yaku::Daisushi | Yaku::Daichiishin | Yaku::SuuankouTanki if ruleset.has_double_yakuman() => { // double yakuman
-                        if has_yakuman { han_count + 26 } else { has_yakuman = true; 26 } }
-                    _ if YAKUMAN.contains(y) => { // yakuman
-                        if has_yakuman && ruleset.has_yakuman_stacking() { han_count + 13
-                        } else { has_yakuman = true; 13 } }
-                    _ => han_count,
+                    // where rulesets differ from the table
+                    Yaku::Tanyao if is_open && !ruleset.allows_open_tanyao() => han_count,
+                    Yaku::Ippatsu if !ruleset.allows_ippatsu() => han_count,
+                    Yaku::DoubleRiichi if !ruleset.allows_double_riichi() => han_count + Yaku::Riichi.info().han_closed,
+                    Yaku::Renho => han_count + ruleset.renho_han(),
+                    // nagashi mangan has no han, and should only get here if it isn't a valid yaku in the active ruleset.
+                    _ => han_count + match (is_open, info.han_open) { (true, Some(han)) => han, (true, None) => 0, (false, _) => info.han_closed },
                 }
-            } else { han_count }
+            }
         })
     }
 }
@@ -235,6 +207,141 @@ pub fn calc_player_split(
     }
 }
 
+// Settles every ron called on one discard, applying the ruleset's multiple ron policy.
+// Repeat counters come from game_state; deposits is the number of riichi sticks on the table, which go to the first winner.
+//
+// # Errors
+//
+// Returns ValueError if no one (or the discarder) is winning, if a winner appears twice, or if a seat didn't win by ron.
+// Errors from scoring the individual hands are passed along.
+pub fn settle_ron(game_state: &Game, discarder: Wind, winners: &[Seat], deposits: u8) -> Result<RonSettlement, HandError> {
+    let ruleset = game_state.ruleset;
+    // how far each seat sits after the discarder in turn order
+    let distance = |wind: Wind| (wind as usize + 4 - discarder as usize) % 4;
+
+    let mut winners: Vec<&Seat> = winners.iter().collect();
+    winners.sort_by_key(|s| distance(s.seat_wind));
+    if winners.is_empty() || winners.iter().any(|s| s.seat_wind == discarder || s.latest_type != Some(TileType::Call))
+    || winners.windows(2).any(|w| w[0].seat_wind == w[1].seat_wind) {
+        return Err(HandError::ValueError)
+    }
+
+    // with head bump, only one player ever wins, so there's never a triple ron to abort
+    if ruleset.has_atamahane() { winners.truncate(1) }
+    if winners.len() >= 3 && ruleset.has_sanchahou() {
+        return Ok(RonSettlement::Abortive(AbortiveDraw::Sanchahou))
+    }
+
+    let mut payments: Vec<Payment> = Vec::with_capacity(winners.len());
+    let mut points: [i32; 4] = [0; 4];
+
+    for (index, seat) in winners.iter().enumerate() {
+        let hand = Hand::new(game_state.clone(), (*seat).clone());
+        let gets_repeats = index == 0 || ruleset.pays_repeats_to_all_winners();
+        let hand_points = win_points(game_state, seat, &hand, Some(discarder), gets_repeats)?;
+        for (total, change) in points.iter_mut().zip(hand_points) { *total += change }
+        if index == 0 { points[seat.seat_wind as usize] += i32::from(deposits) * 1000 }
+
+        payments.push(hand.payment_split(ruleset, if gets_repeats { game_state.repeats } else { 0 })?);
+    }
+
+    Ok(RonSettlement::Wins { winners: winners.iter().map(|s| s.seat_wind).collect(), payments, points })
+}
+
+// Settles a single winning hand, including repeats, deposits, and pao. discarder should be None for a tsumo.
+// Returns the point changes for each seat, indexed by seat wind (east, south, west, north).
+//
+// # Errors
+//
+// Returns ValueError if the discarder is the winner or the win couldn't have followed game_state.last_action;
+// ImpossibleYaku if the special yaku contradict seat_state.riichi, game_state.last_action or game_state.turn;
+// IllegalCall if the ruleset doesn't allow the win (see legality::check_win). Errors from scoring the hand are passed along.
+pub fn settle_win(game_state: &Game, seat_state: &Seat, discarder: Option<Wind>, deposits: u8) -> Result<[i32; 4], HandError> {
+    if discarder == Some(seat_state.seat_wind) { return Err(HandError::ValueError) }
+
+    let hand = Hand::new(game_state.clone(), seat_state.clone());
+    let mut points = win_points(game_state, seat_state, &hand, discarder, true)?;
+    points[seat_state.seat_wind as usize] += i32::from(deposits) * 1000;
+    Ok(points)
+}
+
+// Finds the seat liable for a winning hand, if any, along with the yakuman they're liable for.
+// The yakuman is None for a rinshan win after a daiminkan, in which case the liable seat covers the entire hand.
+// Expects called_melds to be in the order they were called.
+pub fn find_pao(ruleset: RiichiRuleset, seat_state: &Seat, yaku: &[Yaku]) -> Option<(Wind, Option<Yaku>)> {
+    let called_melds = seat_state.called_melds.as_ref()?;
+
+    if ruleset.has_pao() {
+        for (pao_yaku, count) in [(Yaku::Daisangen, 3), (Yaku::Daisushi, 4)] {
+            if !yaku.contains(&pao_yaku) { continue }
+            let melds: Vec<&Meld> = called_melds.iter()
+                .filter(|m| !m.is_seq() && if pao_yaku == Yaku::Daisangen { m.is_dragon() } else { m.is_wind() }).collect();
+            // the whole set has to have been visible on the table when the last one was fed.
+            if melds.len() == count {
+                if let Some(wind) = melds.last().and_then(|m| m.called_from) { return Some((wind, Some(pao_yaku))) }
+            }
+        }
+    }
+
+    if ruleset.has_daiminkan_pao() && seat_state.latest_type == Some(TileType::Kan) {
+        if let Some(meld) = called_melds.last().filter(|m| m.kind == Some(MeldKind::Daiminkan)) {
+            return meld.called_from.map(|wind| (wind, None))
+        }
+    }
+
+    None
+}
+
+// Point changes for one winning hand, without deposits.
+fn win_points(game_state: &Game, seat_state: &Seat, hand: &Hand, discarder: Option<Wind>, pays_repeats: bool) -> Result<[i32; 4], HandError> {
+    let ruleset = game_state.ruleset;
+    let winner = seat_state.seat_wind;
+    let dealer = hand.is_dealer();
+    let repeats = if pays_repeats { game_state.repeats } else { 0 };
+    let to_points = |value: u32| i32::try_from(value).map_err(|_| HandError::ValueError);
+    find_riichi_yaku(game_state, seat_state)?;
+    find_situational_yaku(game_state, seat_state)?;
+    find_first_turn_yaku(game_state, seat_state)?;
+    check_win(&ruleset, game_state, seat_state)?;
+
+    let base = hand.base_points(ruleset)?;
+    let (liable, pao_base) = match find_pao(ruleset, seat_state, hand.yaku()) {
+        Some((wind, Some(yaku))) => (Some(wind), base.min(if yaku == Yaku::Daisushi && ruleset.has_double_yakuman() { 16000 } else { 8000 })),
+        Some((wind, None)) => (Some(wind), base),
+        None => (None, 0),
+    };
+    let rest = base - pao_base;
+    let pao_total = (pao_base * if dealer { 6 } else { 4 }).round_to_hundreds();
+
+    let mut points: [i32; 4] = [0; 4];
+    let mut pay = |payer: Wind, amount: i32| {
+        points[payer as usize] -= amount;
+        points[winner as usize] += amount;
+    };
+
+    if let Some(discarder) = discarder {
+        pay(discarder, to_points((rest * if dealer { 6 } else { 4 }).round_to_hundreds())? + ruleset.repeat_payment_ron(repeats));
+        if let Some(liable) = liable {
+            if ruleset.pao_splits_ron() && liable != discarder {
+                let half = (pao_total / 2).round_to_hundreds();
+                pay(liable, to_points(half)?);
+                pay(discarder, to_points(pao_total - half)?);
+            } else { pay(discarder, to_points(pao_total)?) }
+        }
+    } else {
+        for payer in [Wind::East, Wind::South, Wind::West, Wind::North].into_iter().filter(|w| *w != winner) {
+            if rest > 0 {
+                pay(payer, to_points((rest * if dealer || payer == Wind::East { 2 } else { 1 }).round_to_hundreds())?);
+            }
+            // the liable player covers every repeat counter
+            if liable.is_none() { pay(payer, ruleset.repeat_payment_tsumo(repeats)) }
+        }
+        if let Some(liable) = liable { pay(liable, to_points(pao_total)? + ruleset.repeat_payment_ron(repeats)) }
+    }
+
+    Ok(points)
+}
+
 pub trait ScoreRounding {
     #[must_use]
     fn round_to_tens(&self) -> Self;
@@ -270,10 +377,22 @@ mod tests {
 
     #[test]
     fn han_counts(){
-        assert_eq!(vec![Yaku::Chiitoi, Yaku::Riichi].han(true, RiichiRuleset::MajSoul), 3);
+        assert_eq!(vec![Yaku::Chiitoi, Yaku::Riichi].han(false, RiichiRuleset::MajSoul), 3);
+        // closed-only yaku are worth nothing in an open hand
+        assert_eq!(vec![Yaku::Chiitoi, Yaku::Riichi].han(true, RiichiRuleset::MajSoul), 0);
+        assert_eq!(vec![Yaku::Ipeiko, Yaku::Pinfu, Yaku::Tanyao].han(true, RiichiRuleset::MajSoul), 1);
+        assert_eq!(vec![Yaku::Ipeiko, Yaku::Honitsu].han(true, RiichiRuleset::MajSoul), 2);
         assert_eq!(vec![Yaku::Chinroto, Yaku::Riichi].han(true, RiichiRuleset::MajSoul), 13);
         assert_eq!(vec![Yaku::Chinroto, Yaku::Honitsu, Yaku::Daisangen, Yaku::Riichi].han(true, RiichiRuleset::MajSoul), 26);
         assert_eq!(vec![Yaku::Daisushi, Yaku::Riichi].han(true, RiichiRuleset::MajSoul), 26);
+
+        // yakuman stack where the ruleset allows it, but double yakuman are only worth double where it has them
+        assert_eq!(vec![Yaku::Daisangen, Yaku::Daichiishin].han(false, RiichiRuleset::Default), 26);
+        assert_eq!(vec![Yaku::Daisushi, Yaku::Daichiishin].han(false, RiichiRuleset::Default), 26);
+        assert_eq!(vec![Yaku::Daisushi, Yaku::Daichiishin].han(false, RiichiRuleset::MajSoul), 52);
+        // otherwise a hand is worth a single yakuman, however many it has
+        assert_eq!(vec![Yaku::Daisangen, Yaku::Daichiishin, Yaku::Riichi].han(false, RiichiRuleset::EMA2016), 13);
+        assert_eq!(vec![Yaku::Riichi, Yaku::Daisushi, Yaku::Daichiishin].han(false, RiichiRuleset::EMA2016), 13);
     }
 
     #[test]
@@ -285,6 +404,32 @@ mod tests {
         assert_eq!("m1,m1,m1,m1".to_meld().unwrap().base_fu(), 16);
     }
 
+    #[test]
+    fn wait_fu(){
+        use crate::score_hand_from_str;
+
+        // m3 is an edge wait or a pair wait, but only earns 2 fu: 30 + 8 + 2 rounds to 40, not 50
+        assert_eq!(score_hand_from_str("m1,m2,m3,m3,p9,p9,p9,s7,s8,s9,p2,p3,p4", "", "m3", 's', 'e', 'c', "", "", "riichi", 0, "default"), Ok(Payment::Ron(1300)));
+        // an edge wait isn't pinfu
+        assert_eq!(score_hand_from_str("m1,m2,p2,p3,p4,s4,s5,s6,s6,s7,s8,p8,p8", "", "m3", 's', 'e', 'c', "", "", "riichi", 0, "default"), Ok(Payment::Ron(1300)));
+        assert_eq!(score_hand_from_str("m2,m3,p2,p3,p4,s4,s5,s6,s6,s7,s8,p8,p8", "", "m1", 's', 'e', 'c', "", "", "riichi", 0, "default"), Ok(Payment::Ron(2000)));
+    }
+
+    #[test]
+    fn rinshan_fu(){
+        use crate::state::SeatHelper;
+
+        // 20 + 8 for the open kan + 2 for the open pon, and 2 more if the rinshan counts as a tsumo
+        let game = |ruleset: RiichiRuleset| Game{ ruleset, round_wind: Wind::East, repeats: 0, dora_markers: None, ura_dora_markers: None,
+            last_action: Some(LastAction::KanDraw(Wind::South, MeldKind::Daiminkan)), tiles_left: None, turn: None };
+        let seat = Seat::new("m2,m3,p5,p6,p7,s5,s5".to_tiles().unwrap(), "p2,p2,p2@n|s2,s2,s2,s2@w".to_calls().ok(),
+            Wind::South, "m4".to_tile().ok(), Some(TileType::Kan), None);
+        assert_eq!(Hand::new(game(RiichiRuleset::MajSoul), seat.clone()).fu(), 40);
+        assert_eq!(Hand::new(game(RiichiRuleset::JPML2023), seat.clone()).fu(), 30);
+        let after_ankan = Game { last_action: Some(LastAction::KanDraw(Wind::South, MeldKind::Ankan)), ..game(RiichiRuleset::JPML2023) };
+        assert_eq!(Hand::new(after_ankan, seat).fu(), 40);
+    }
+
     #[test]
     fn base_point_calc(){
         // TODO: update test cases for traits
@@ -302,6 +447,79 @@ mod tests {
         assert_eq!(calc_base_points(0, 10, &Vec::new(), RiichiRuleset::Default), Err(HandError::ValueError));
     }
 
+    #[test]
+    fn multiple_ron(){
+        use crate::state::SeatHelper;
+
+        let game = |ruleset: RiichiRuleset| Game{ ruleset, round_wind: Wind::East, repeats: 1, dora_markers: None, ura_dora_markers: None, last_action: None, tiles_left: None, turn: None };
+        let winner = |wind: Wind| Seat::new("p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4".to_tiles().unwrap(), None, wind,
+            "p9".to_tile().ok(), Some(TileType::Call), None);
+
+        // double ron; the deposits and repeats go to west, who sits closest to the discarder
+        assert_eq!(settle_ron(&game(RiichiRuleset::Default), Wind::South, &[winner(Wind::East), winner(Wind::West)], 2),
+            Ok(RonSettlement::Wins{ winners: vec![Wind::West, Wind::East], payments: vec![Payment::Ron(3900), Payment::Ron(5800)],
+                points: [5800, -10000, 6200, 0] }));
+        assert_eq!(settle_ron(&game(RiichiRuleset::MajSoul), Wind::South, &[winner(Wind::East), winner(Wind::West)], 2),
+            Ok(RonSettlement::Wins{ winners: vec![Wind::West, Wind::East], payments: vec![Payment::Ron(3900), Payment::Ron(5800)],
+                points: [6100, -10300, 6200, 0] }));
+
+        // head bump
+        assert_eq!(settle_ron(&game(RiichiRuleset::JPML2022), Wind::South, &[winner(Wind::East), winner(Wind::West)], 2),
+            Ok(RonSettlement::Wins{ winners: vec![Wind::West], payments: vec![Payment::Ron(3900)], points: [0, -4200, 6200, 0] }));
+        assert_eq!(settle_ron(&game(RiichiRuleset::JPML2022), Wind::South, &[winner(Wind::East), winner(Wind::West), winner(Wind::North)], 2),
+            Ok(RonSettlement::Wins{ winners: vec![Wind::West], payments: vec![Payment::Ron(3900)], points: [0, -4200, 6200, 0] }));
+
+        // triple ron
+        assert_eq!(settle_ron(&game(RiichiRuleset::Default), Wind::South, &[winner(Wind::East), winner(Wind::West), winner(Wind::North)], 0),
+            Ok(RonSettlement::Abortive(AbortiveDraw::Sanchahou)));
+        assert_eq!(settle_ron(&game(RiichiRuleset::EMA2016), Wind::North, &[winner(Wind::East), winner(Wind::West), winner(Wind::South)], 0),
+            Ok(RonSettlement::Wins{ winners: vec![Wind::East, Wind::South, Wind::West], payments: vec![Payment::Ron(5800), Payment::Ron(3900), Payment::Ron(3900)],
+                points: [6100, 4200, 4200, -14500] }));
+
+        // bad input
+        assert_eq!(settle_ron(&game(RiichiRuleset::Default), Wind::South, &[winner(Wind::South)], 0), Err(HandError::ValueError));
+        assert_eq!(settle_ron(&game(RiichiRuleset::Default), Wind::South, &[], 0), Err(HandError::ValueError));
+        assert_eq!(settle_ron(&game(RiichiRuleset::Default), Wind::South, &[winner(Wind::East), winner(Wind::East)], 0), Err(HandError::ValueError));
+    }
+
+    #[test]
+    fn pao(){
+        use crate::state::SeatHelper;
+
+        let game = |ruleset: RiichiRuleset| Game{ ruleset, round_wind: Wind::East, repeats: 1, dora_markers: None, ura_dora_markers: None, last_action: None, tiles_left: None, turn: None };
+        let daisangen = |latest_type: TileType| Seat::new("m2,m3,m4,p5".to_tiles().unwrap(), "dw,dw,dw@e|dg,dg,dg@w|dr,dr,dr@n".to_calls().ok(),
+            Wind::South, "p5".to_tile().ok(), Some(latest_type), None);
+
+        assert_eq!(find_pao(RiichiRuleset::Default, &daisangen(TileType::Draw), &[Yaku::Daisangen]), Some((Wind::North, Some(Yaku::Daisangen))));
+        assert_eq!(find_pao(RiichiRuleset::EMA2016, &daisangen(TileType::Draw), &[Yaku::Daisangen]), None);
+
+        // tsumo: the liable player pays everything, including repeats
+        assert_eq!(settle_win(&game(RiichiRuleset::Default), &daisangen(TileType::Draw), None, 1), Ok([0, 33300, 0, -32300]));
+        assert_eq!(settle_win(&game(RiichiRuleset::EMA2016), &daisangen(TileType::Draw), None, 0), Ok([-16100, 32300, -8100, -8100]));
+
+        // ron: the liable player and discarder split it
+        assert_eq!(settle_win(&game(RiichiRuleset::Default), &daisangen(TileType::Call), Some(Wind::West), 0), Ok([0, 32300, -16300, -16000]));
+        assert_eq!(settle_win(&game(RiichiRuleset::Default), &daisangen(TileType::Call), Some(Wind::North), 0), Ok([0, 32300, 0, -32300]));
+
+        // a dragon triplet which was concealed in hand means nobody is liable
+        let seat = Seat::new("m2,m3,m4,p5,dr,dr,dr".to_tiles().unwrap(), "dw,dw,dw@e|dg,dg,dg@w".to_calls().ok(),
+            Wind::South, "p5".to_tile().ok(), Some(TileType::Draw), None);
+        assert_eq!(settle_win(&game(RiichiRuleset::Default), &seat, None, 0), Ok([-16100, 32300, -8100, -8100]));
+
+        // only the pao yakuman is covered when others are stacked on top of it
+        let seat = Seat::new("we,we,we,wn".to_tiles().unwrap(), "dw,dw,dw@e|dg,dg,dg@w|dr,dr,dr@n".to_calls().ok(),
+            Wind::South, "wn".to_tile().ok(), Some(TileType::Draw), None);
+        assert_eq!(settle_win(&game(RiichiRuleset::Default), &seat, None, 0), Ok([-16000, 64300, -8000, -40300]));
+
+        // feeding a daiminkan covers a rinshan win on it, rounded like a ron
+        let rinshan = Seat::new("m2,m3,m4,p5,p6,p7,s2,s3,we,we".to_tiles().unwrap(), "s9,s9,s9,s9@w".to_calls().ok(),
+            Wind::South, "s4".to_tile().ok(), Some(TileType::Kan), "rinshan".to_yaku_vec().ok());
+        assert_eq!(find_pao(RiichiRuleset::JPML2023, &rinshan, &[Yaku::AfterKan]), Some((Wind::West, None)));
+        assert_eq!(find_pao(RiichiRuleset::MajSoul, &rinshan, &[Yaku::AfterKan]), None);
+        assert_eq!(settle_win(&game(RiichiRuleset::JPML2023), &rinshan, None, 0), Ok([0, 1600, -1600, 0]));
+        assert_eq!(settle_win(&game(RiichiRuleset::MajSoul), &rinshan, None, 0), Ok([-800, 1800, -500, -500]));
+    }
+
     #[test]
     fn bp_and_split_calc(){
         // TODO: update test cases for traits
@@ -0,0 +1,210 @@
+/*
+Standard-form shanten in constant time, from lookup tables built on first use. Enabled by the `shanten-tables` feature.
+
+For every suit pattern (and every honor pattern), the tables hold how many of its tiles can be kept towards a target of
+m melds and h pairs, for m up to 4 and h up to 1. Combining the three suits and honors is then a handful of max-plus merges:
+a hand needing M more melds is (3M + 1) - kept tiles away from tenpai.
+
+The suit table is indexed directly by the base-5 key of the suit's counts, so it takes about 20MB once built.
+Chiitoi and kokushi aren't covered; only the four-melds-and-a-pair shape.
+*/
+
+use crate::tiles::Histogram;
+use std::sync::OnceLock;
+
+///////////////
+// functions //
+///////////////
+
+// Returns the standard-form shanten of a closed hand, where called_melds is how many melds have been called.
+// -1 is a complete hand and 0 is tenpai. A hand of 3n+2 tiles is counted as if its least useful tile were discarded.
+pub fn standard_shanten(hand: &Histogram, called_melds: u8) -> i8 {
+    let melds = usize::from(4 - called_melds.min(4));
+    let suits = suit_table();
+    let honors = honor_table();
+
+    let mut kept = [0; 10];
+    for (part, table) in [(&hand[0..9], suits), (&hand[9..18], suits), (&hand[18..27], suits), (&hand[27..34], honors)] {
+        let Some(part_kept) = key(part).and_then(|k| table.get(k)) else { return i8::MAX };
+        kept = merge(&kept, part_kept);
+    }
+    (3 * melds as i8 + 1) - kept[melds * 2 + 1] as i8
+}
+
+// Tiles kept for each target (melds * 2 + pairs), when the targets are split between two parts of the hand.
+fn merge(a: &[u8; 10], b: &[u8; 10]) -> [u8; 10] {
+    let mut merged = [0; 10];
+    for m in 0..5 {
+        for m1 in 0..=m {
+            let (a_melds, b_melds) = (m1 * 2, (m - m1) * 2);
+            merged[m * 2] = merged[m * 2].max(a[a_melds] + b[b_melds]);
+            merged[m * 2 + 1] = merged[m * 2 + 1].max(a[a_melds] + b[b_melds + 1]).max(a[a_melds + 1] + b[b_melds]);
+        }
+    }
+    merged
+}
+
+fn key(counts: &[u8]) -> Option<usize> {
+    counts.iter().try_fold(0, |key, count| if *count > 4 { None } else { Some(key * 5 + usize::from(*count)) })
+}
+
+fn suit_table() -> &'static Vec<[u8; 10]> {
+    static TABLE: OnceLock<Vec<[u8; 10]>> = OnceLock::new();
+    TABLE.get_or_init(|| build_table(9, true))
+}
+
+fn honor_table() -> &'static Vec<[u8; 10]> {
+    static TABLE: OnceLock<Vec<[u8; 10]>> = OnceLock::new();
+    TABLE.get_or_init(|| build_table(7, false))
+}
+
+// Builds the kept-tile table for a part of the hand with `kinds` tile kinds, which form sequences if `sequences` is set.
+// Only patterns of up to 14 tiles are filled in, since no closed hand has more.
+fn build_table(kinds: usize, sequences: bool) -> Vec<[u8; 10]> {
+    let size = 5_usize.pow(kinds as u32);
+    let weights: Vec<usize> = (0..kinds).map(|i| 5_usize.pow((kinds - 1 - i) as u32)).collect();
+    let digit = |key: usize, i: usize| (key / weights[i]) % 5;
+
+    // the number of tiles in each pattern, and the patterns of each size
+    let mut sizes: Vec<u8> = vec![0; size];
+    for key in 1..size { sizes[key] = sizes[key / 5] + (key % 5) as u8 }
+    let mut by_size: Vec<Vec<usize>> = vec![Vec::new(); 15];
+    for (key, tiles) in sizes.iter().enumerate() {
+        if *tiles <= 14 { by_size[usize::from(*tiles)].push(key) }
+    }
+
+    // which targets each pattern is part of: first the complete targets, then everything below them
+    let mut partial: Vec<u16> = vec![0; size];
+    let mut counts = vec![0_u8; kinds];
+    mark_targets(&mut counts, 0, 0, false, sequences, &weights, &mut partial);
+    for tiles in (1..=14).rev() {
+        for key in &by_size[tiles] {
+            let mask = partial[*key];
+            if mask == 0 { continue }
+            for i in 0..kinds {
+                if digit(*key, i) > 0 { partial[*key - weights[i]] |= mask }
+            }
+        }
+    }
+
+    // the most tiles of each pattern that fit inside a target
+    let mut kept: Vec<[u8; 10]> = vec![[0; 10]; size];
+    for (tiles, keys) in by_size.iter().enumerate().skip(1) {
+        for key in keys {
+            let mut best = [0; 10];
+            for (class, value) in best.iter_mut().enumerate() {
+                *value = if partial[*key] & (1 << class) != 0 { tiles as u8 }
+                    else { (0..kinds).filter(|i| digit(*key, *i) > 0).map(|i| kept[*key - weights[i]][class]).max().unwrap_or(0) };
+            }
+            kept[*key] = best;
+        }
+    }
+    kept
+}
+
+// Marks every target of up to four melds and one pair, adding melds in order so each combination is visited once.
+// Melds 0..kinds are triplets; after that, sequences starting at each kind.
+fn mark_targets(counts: &mut Vec<u8>, first_meld: usize, melds: usize, pair: bool, sequences: bool, weights: &[usize], partial: &mut [u16]) {
+    let kinds = counts.len();
+    let key: usize = counts.iter().zip(weights).map(|(c, w)| usize::from(*c) * w).sum();
+    partial[key] |= 1 << (melds * 2 + usize::from(pair));
+
+    if !pair {
+        for kind in 0..kinds {
+            counts[kind] += 2;
+            if counts[kind] <= 4 { mark_targets(counts, first_meld, melds, true, sequences, weights, partial) }
+            counts[kind] -= 2;
+        }
+    }
+    if melds == 4 { return }
+
+    let total_melds = if sequences { kinds + kinds - 2 } else { kinds };
+    for meld in first_meld..total_melds {
+        let tiles: Vec<usize> = if meld < kinds { vec![meld; 3] } else { (meld - kinds..meld - kinds + 3).collect() };
+        tiles.iter().for_each(|t| counts[*t] += 1);
+        if tiles.iter().all(|t| counts[*t] <= 4) { mark_targets(counts, meld, melds + 1, pair, sequences, weights, partial) }
+        tiles.iter().for_each(|t| counts[*t] -= 1);
+    }
+}
+
+///////////
+// tests //
+///////////
+
+mod tests {
+    use super::*;
+    use crate::tiles::{ToHistogram, TileId};
+    use crate::composer::{Decomposition, compose_histogram};
+    use crate::conversions::ConvertStrings;
+
+    // whether the composer can read a hand as four melds and a pair
+    fn is_standard_agari(hand: &Histogram, buffer: &mut Vec<Decomposition>) -> bool {
+        compose_histogram(hand, None, false, buffer);
+        buffer.iter().any(|d| d.hanging_tiles.is_empty() && d.pairs.len() == 1 && d.melds.len() == 4)
+    }
+
+    #[test]
+    fn test_shanten_values(){
+        let shanten = |tiles: &str| standard_shanten(&tiles.to_tiles().unwrap().to_histogram(), 0);
+
+        assert_eq!(shanten("m1,m1,m1,m2,m3,m4,m5,m6,m7,m8,m9,m9,m9,m5"), -1);
+        assert_eq!(shanten("m1,m1,m1,m2,m3,m4,m5,m6,m7,m8,m9,m9,m9"), 0);
+        assert_eq!(shanten("m1,m2,m3,p4,p5,p6,s7,s8,s9,dr,dr,we,we"), 0);
+        assert_eq!(shanten("m1,m2,m3,p4,p5,p6,s7,s8,s9,dr,dr,we,wn"), 1);
+        assert_eq!(shanten("m1,m2,m3,p4,p5,p6,s7,s8,dr,dr,we,wn,ws"), 2);
+        assert_eq!(shanten("m1,m4,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn"), 8);
+        assert_eq!(shanten("m1,m3,m7,p2,p5,p8,s3,s6,s9,dr,dg,we,wn"), 7);
+        assert_eq!(shanten("m1,m9,p1,p9,s1,s9,dw,dr,dg,we,ws,wn,ww"), 8);
+        assert_eq!(standard_shanten(&"p2,p3,dr,dr".to_tiles().unwrap().to_histogram(), 3), 0);
+    }
+
+    // the tables are checked against the composer where it can answer directly (complete and tenpai hands),
+    // and every other hand has to be one more than the best hand a single swap away.
+    #[test]
+    fn test_shanten_against_composer(){
+        let mut seed: u64 = 0xfeed;
+        let mut next = || { seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407); (seed >> 33) as usize };
+        let mut buffer: Vec<Decomposition> = Vec::new();
+
+        for _ in 0..150 {
+            // two suits and a few honors, so that a fair share of hands are close to complete
+            let mut hand: Histogram = [0; 34];
+            while hand.iter().sum::<u8>() < 13 {
+                let id = match next() % 5 { 0 => 27 + next() % 7, 1 | 2 => next() % 9, _ => 9 + next() % 9 };
+                if hand[id] < 4 { hand[id] += 1 }
+            }
+            let shanten = standard_shanten(&hand, 0);
+
+            let tenpai = (0..34).filter(|id| hand[*id] < 4).any(|id| {
+                let mut complete = hand;
+                complete[id] += 1;
+                assert_eq!(standard_shanten(&complete, 0) == -1, is_standard_agari(&complete, &mut buffer), "{hand:?} + {id}");
+                is_standard_agari(&complete, &mut buffer)
+            });
+            assert_eq!(shanten == 0, tenpai, "{hand:?}");
+
+            if shanten > 0 {
+                let mut best = i8::MAX;
+                for discard in (0..34).filter(|id| hand[*id] > 0) {
+                    for draw in (0..34).filter(|id| *id != discard && hand[*id] < 4) {
+                        let mut swapped = hand;
+                        swapped[discard] -= 1;
+                        swapped[draw] += 1;
+                        best = best.min(standard_shanten(&swapped, 0));
+                    }
+                }
+                assert_eq!(shanten, best + 1, "{hand:?}");
+            }
+
+            // a 14 tile hand is as good as its best discard
+            let draw = TileId::new((next() % 34) as u8).unwrap().index();
+            if hand[draw] < 4 {
+                let mut drawn = hand;
+                drawn[draw] += 1;
+                let best = (0..34).filter(|id| drawn[*id] > 0)
+                    .map(|id| { let mut discarded = drawn; discarded[id] -= 1; standard_shanten(&discarded, 0) }).min().unwrap();
+                assert_eq!(standard_shanten(&drawn, 0), if is_standard_agari(&drawn, &mut buffer) { -1 } else { best });
+            }
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use crate::rulesets::RiichiRuleset;
 use crate::tiles::{Tile, Wind};
 use crate::yaku::Yaku;
-use crate::hand::{Meld, MeldHas};
+use crate::hand::{Meld, MeldHas, MeldKind, read_waits};
+use crate::errors::mahjong_errors::HandError;
 use crate::scoring::Payment;
 
 ///////////////////////
@@ -15,6 +16,9 @@ pub struct Game {
 	pub repeats: u8,
 	pub dora_markers: Option<Vec<Tile>>,
 	pub ura_dora_markers: Option<Vec<Tile>>,
+	pub last_action: Option<LastAction>,	// what happened just before the win, if known; see yaku::find_situational_yaku()
+	pub tiles_left: Option<u8>,				// draws left in the live wall, if known
+	pub turn: Option<Turn>,				// how far the hand has got, if known; see yaku::find_first_turn_yaku()
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -26,6 +30,17 @@ pub struct Seat {
     pub latest_type: Option<TileType>,
 	pub special_yaku: Option<Vec<Yaku>>,
 	pub all_tiles: Option<Vec<Tile>>,
+	pub discards: Option<Vec<Discard>>,
+	pub riichi: Option<Riichi>,		// None if the seat hasn't declared riichi, or if that isn't known
+}
+
+// A riichi declaration; see RiichiState. Turns count the seat's own discards, from 0.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Riichi {
+	pub turn: u8,			// the turn of the declaring discard
+	pub discard: Tile,		// the tile turned sideways
+	pub double: bool,		// declared on the first go-around, before any call
+	pub ippatsu: bool,		// the ippatsu window is still open
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -34,9 +49,33 @@ pub struct Win {
 	pub winning_tile: Tile,
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Discard {
+	pub tile: Tile,
+	pub called: bool,	// was it called by another player?
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum TileType {Call, Draw, Kan}
 
+// The last thing that happened at the table. Seats are given by their wind.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LastAction {
+	Draw(Wind),			// a draw from the live wall
+	KanDraw(Wind, MeldKind),	// a replacement draw after the seat's own kan, of the given kind
+	Discard(Wind),
+	Shouminkan(Wind),	// a tile added to a pon, which other seats can rob
+	Ankan(Wind),		// a closed kan, which can only be robbed for kokushi, if the ruleset allows it
+}
+
+// How far the hand has got. A seat's first draw comes after as many discards as it sits places from the dealer, if
+// nobody has called (closed kans included) in the meantime.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Turn {
+	pub discards: u8,		// discards made at the table so far, including a discard being won on
+	pub called: bool,		// has anyone called, or declared a closed kan?
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum WinType {Tsumo, Ron}
 
@@ -65,6 +104,11 @@ pub trait GameHelper {
 	) -> Self where Self: Sized;
 }
 
+pub trait RiichiState {
+	fn declare_riichi(&mut self, turn: u8, discard: Tile, uninterrupted: bool) -> Result<(), HandError>;
+	fn end_ippatsu(&mut self);		// after the seat's next discard, or when anyone calls (including closed kans)
+}
+
 pub trait SeatAccess {
 	fn all_tiles(&self) -> Vec<Tile>;
 }
@@ -90,7 +134,10 @@ impl GameHelper for Game {
 			round_wind,
 			repeats,
 			dora_markers,
-			ura_dora_markers
+			ura_dora_markers,
+			last_action: None,
+			tiles_left: None,
+			turn: None,
 		}
 	}
 }
@@ -113,22 +160,52 @@ impl SeatHelper for Seat {
 			special_yaku,
 			all_tiles: {
 				let mut tiles = [closed_tiles, called_melds.unwrap_or_default().iter().map(super::hand::MeldHas::as_tiles).collect::<Vec<_>>().concat(), {
-					if latest_tile.is_some() { vec![latest_tile.unwrap()] } else { Vec::new() }
+					if let Some(tile) = latest_tile { vec![tile] } else { Vec::new() }
 				}].concat();
 				tiles.sort();
 				Some(tiles)
-			}
+			},
+			discards: None,
+			riichi: None,
 		}
 	}
 }
 
+impl Riichi {
+	// A declaration made just now, which opens the ippatsu window.
+	pub fn new(turn: u8, discard: Tile, uninterrupted: bool) -> Riichi {
+		Riichi { turn, discard, double: turn == 0 && uninterrupted, ippatsu: true }
+	}
+}
+
+impl RiichiState for Seat {
+	// Declares riichi with the discard just made, which should already be gone from closed_tiles.
+	// uninterrupted means no call has been made this hand yet.
+	//
+	// # Errors
+	//
+	// Returns ValueError if the seat already declared, has an open meld, or isn't tenpai after the discard.
+	fn declare_riichi(&mut self, turn: u8, discard: Tile, uninterrupted: bool) -> Result<(), HandError> {
+		if self.riichi.is_some() || self.called_melds.as_ref().is_some_and(|m| m.iter().any(|m| m.is_open))
+		|| read_waits(&self.closed_tiles, &self.called_melds).is_none() {
+			return Err(HandError::ValueError)
+		}
+		self.riichi = Some(Riichi::new(turn, discard, uninterrupted));
+		Ok(())
+	}
+
+	fn end_ippatsu(&mut self) {
+		if let Some(riichi) = self.riichi.as_mut() { riichi.ippatsu = false }
+	}
+}
+
 impl SeatAccess for Seat {
 	fn all_tiles(&self) -> Vec<Tile> {
 		if let Some(tiles) = self.all_tiles.clone() {
 			tiles
 		} else {
 			let mut all_tiles = [self.closed_tiles.clone(), self.called_melds.clone().unwrap_or_default().iter().map(super::hand::MeldHas::as_tiles).collect::<Vec<_>>().concat(), {
-				if self.latest_tile.is_some() { vec![self.latest_tile.unwrap()] } else { Vec::new() }
+				if let Some(tile) = self.latest_tile { vec![tile] } else { Vec::new() }
 			}].concat();
 			all_tiles.sort();
 			all_tiles
@@ -0,0 +1,477 @@
+/*
+Reads Tenhou's mjlog (XML) replays into a sequence of events, so recorded games can be replayed and their wins
+rescored. Tenhou identifies each physical tile with an id from 0 to 135: id / 4 is the kind (man, pin, sou, then
+east south west north, then white green red) and the fives with a copy of 0 are red unless the game was played without them.
+
+Players are referred to by their index in the log (0-3), which stays fixed for the whole game; seat winds are derived
+from the dealer of each hand.
+*/
+
+use crate::tiles::{Tile, Suit, Dragon, Wind, PhysicalTile};
+use crate::state::{Game, Seat, Discard, TileType, SeatHelper};
+use crate::hand::{Meld, MeldKind, MeldIs};
+use crate::yaku::Yaku;
+use crate::conversions::ConvertTiles;
+use crate::ryuukyoku::AbortiveDraw;
+use crate::errors::mahjong_errors::ParsingError;
+use crate::rulesets::RiichiRuleset;
+
+static WINDS: [Wind; 4] = [Wind::East, Wind::South, Wind::West, Wind::North];
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum MjlogEvent {
+    Init {
+        game: Game,
+        dealer: usize,
+        deposits: u8,       // riichi sticks left on the table from earlier hands
+        scores: [i32; 4],   // indexed by player
+        hands: [Vec<Tile>; 4],
+    },
+    Draw { player: usize, tile: Tile },
+    Discard { player: usize, tile: Tile, tsumogiri: bool },
+    Call { player: usize, meld: Meld },
+    Riichi { player: usize, step: u8 },    // step 1 is the declaration, step 2 once the riichi discard goes through
+    Dora(Tile),
+    Agari(MjlogAgari),
+    Ryuukyoku {
+        game: Game,
+        seats: Vec<Seat>,                   // indexed by player
+        abortive: Option<AbortiveDraw>,     // None for an exhaustive draw
+    },
+}
+
+// A win, along with everything needed to score it through Hand::new() and what Tenhou recorded for it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MjlogAgari {
+    pub game: Game,
+    pub seat: Seat,
+    pub winner: usize,
+    pub discarder: Option<usize>,   // None for tsumo
+    pub fu: u8,
+    pub points: u32,                // hand value, without repeats or deposits
+    pub yaku: Vec<(u8, u8)>,        // Tenhou yaku ids and their han, including dora
+    pub yakuman: Vec<u8>,           // Tenhou yaku ids
+}
+
+// Tracks each player's tiles while the log is read, so snapshots can be taken along the way.
+#[derive(Debug, Default)]
+struct Table {
+    game: Option<Game>,
+    dealer: usize,
+    hands: [Vec<u8>; 4],
+    melds: [Vec<Meld>; 4],
+    discards: [Vec<Discard>; 4],
+    riichi: [bool; 4],
+    last_draw: [Option<u8>; 4],
+    last_discard: Option<usize>,
+}
+
+/////////////////////
+// implementations //
+/////////////////////
+
+// Reads an entire mjlog. Each hand starts with an Init event.
+//
+// # Errors
+//
+// Returns an error for malformed tags or tile ids, events before the first INIT, and three player games.
+pub fn read_mjlog(log: &str, ruleset: RiichiRuleset) -> Result<Vec<MjlogEvent>, ParsingError> {
+    let mut events: Vec<MjlogEvent> = Vec::new();
+    let mut table = Table::default();
+    let mut red = true;
+
+    for (name, attrs) in read_tags(log) {
+        let attr = |key: &str| attrs.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+
+        match name {
+            "GO" => {
+                let game_type: u16 = parse_number(attr("type").unwrap_or("0"))?;
+                if game_type & 0x10 != 0 { return Err(ParsingError::Unimplemented) } // sanma
+                red = game_type & 0x02 == 0;
+            },
+            "INIT" => {
+                let seed: Vec<u8> = parse_list(attr("seed").ok_or(ParsingError::Empty)?)?;
+                if seed.len() < 6 { return Err(ParsingError::BadString) }
+                let dealer: usize = parse_number(attr("oya").ok_or(ParsingError::Empty)?)?;
+                if dealer > 3 { return Err(ParsingError::BadInteger) }
+                let ten: Vec<i32> = parse_list(attr("ten").unwrap_or("0,0,0,0"))?;
+                let scores: [i32; 4] = ten.iter().map(|t| t * 100).collect::<Vec<_>>().try_into().map_err(|_| ParsingError::BadString)?;
+
+                let game = Game {
+                    ruleset,
+                    round_wind: WINDS[usize::from(seed[0] / 4) % 4],
+                    repeats: seed[1],
+                    dora_markers: Some(vec![tile_from_id(seed[5], red)?]),
+                    ura_dora_markers: None,
+                    last_action: None,
+                    tiles_left: None,
+                    turn: None,
+                };
+                table = Table { game: Some(game.clone()), dealer, ..Table::default() };
+                let mut hands: [Vec<Tile>; 4] = Default::default();
+                for (player, (ids, tiles)) in table.hands.iter_mut().zip(hands.iter_mut()).enumerate() {
+                    *ids = parse_list(attr(&format!("hai{player}")).ok_or(ParsingError::Empty)?)?;
+                    *tiles = ids.iter().map(|id| tile_from_id(*id, red)).collect::<Result<Vec<_>, _>>()?;
+                }
+                events.push(MjlogEvent::Init { game, dealer, deposits: seed[2], scores, hands });
+            },
+            "N" => {
+                let player: usize = parse_number(attr("who").ok_or(ParsingError::Empty)?)?;
+                if player > 3 { return Err(ParsingError::BadInteger) }
+                let (meld, ids) = decode_meld(parse_number(attr("m").ok_or(ParsingError::Empty)?)?, player, table.dealer, red)?;
+                table.call(player, meld, &ids)?;
+                events.push(MjlogEvent::Call { player, meld });
+            },
+            "REACH" => {
+                let player: usize = parse_number(attr("who").ok_or(ParsingError::Empty)?)?;
+                let step: u8 = parse_number(attr("step").unwrap_or("1"))?;
+                if player > 3 { return Err(ParsingError::BadInteger) }
+                if step == 2 { table.riichi[player] = true }
+                events.push(MjlogEvent::Riichi { player, step });
+            },
+            "DORA" => {
+                let tile = tile_from_id(parse_number(attr("hai").ok_or(ParsingError::Empty)?)?, red)?;
+                if let Some(game) = table.game.as_mut() { game.dora_markers.get_or_insert_with(Vec::new).push(tile) }
+                events.push(MjlogEvent::Dora(tile));
+            },
+            "AGARI" => events.push(MjlogEvent::Agari(table.agari(&attr, red)?)),
+            "RYUUKYOKU" => {
+                let abortive = match attr("type") {
+                    Some("yao9") => Some(AbortiveDraw::KyuushuKyuuhai),
+                    Some("kaze4") => Some(AbortiveDraw::SuufonRenda),
+                    Some("reach4") => Some(AbortiveDraw::SuuchaRiichi),
+                    Some("kan4") => Some(AbortiveDraw::Suukaikan),
+                    Some("ron3") => Some(AbortiveDraw::Sanchahou),
+                    _ => None,
+                };
+                let game = table.game.clone().ok_or(ParsingError::BadString)?;
+                let seats = (0..4).map(|player| table.seat(player, red)).collect::<Result<Vec<_>, _>>()?;
+                events.push(MjlogEvent::Ryuukyoku { game, seats, abortive });
+            },
+            // draws and discards are the tag name followed by a tile id, ie <T45/> or <D45/>
+            _ => {
+                let mut chars = name.chars();
+                let Some(first) = chars.next() else { continue };
+                let Ok(id) = chars.as_str().parse::<u8>() else { continue };
+
+                if let Some(player) = "TUVW".find(first) {
+                    table.hands[player].push(id);
+                    table.last_draw[player] = Some(id);
+                    events.push(MjlogEvent::Draw { player, tile: tile_from_id(id, red)? });
+                } else if let Some(player) = "DEFG".find(first) {
+                    let tsumogiri = table.last_draw[player] == Some(id);
+                    table.discard(player, id, red)?;
+                    events.push(MjlogEvent::Discard { player, tile: tile_from_id(id, red)?, tsumogiri });
+                }
+            },
+        }
+    }
+    Ok(events)
+}
+
+// Converts a Tenhou tile id (0-135) into a tile.
+//
+// # Errors
+//
+// Returns BadInteger for ids past 135.
+pub fn tile_from_id(id: u8, red: bool) -> Result<Tile, ParsingError> {
+    let tile = PhysicalTile::new(id).ok_or(ParsingError::BadInteger)?;
+    Ok(if red { Tile::from(tile) } else { Tile::from(tile.kind()) })
+}
+
+// Converts a Tenhou yaku id into a yaku. Dora return None.
+// Every yakuhai id counts as one Yakuhai; sum them to compare against a hand.
+pub fn yaku_from_id(id: u8) -> Option<Yaku> {
+    match id {
+        0 => Some(Yaku::ClosedTsumo),
+        1 => Some(Yaku::Riichi),
+        2 => Some(Yaku::Ippatsu),
+        3 => Some(Yaku::RobbedKan),
+        4 => Some(Yaku::AfterKan),
+        5 => Some(Yaku::UnderSea),
+        6 => Some(Yaku::UnderRiver),
+        7 => Some(Yaku::Pinfu),
+        8 => Some(Yaku::Tanyao),
+        9 => Some(Yaku::Ipeiko),
+        10..=20 => Some(Yaku::Yakuhai(1)),
+        21 => Some(Yaku::DoubleRiichi),
+        22 => Some(Yaku::Chiitoi),
+        23 => Some(Yaku::Chanta),
+        24 => Some(Yaku::Ittsuu),
+        25 => Some(Yaku::SanshokuDoujun),
+        26 => Some(Yaku::SanshokuDouko),
+        27 => Some(Yaku::Sankantsu),
+        28 => Some(Yaku::Toitoi),
+        29 => Some(Yaku::Sananko),
+        30 => Some(Yaku::Shosangen),
+        31 => Some(Yaku::Honro),
+        32 => Some(Yaku::Ryanpeiko),
+        33 => Some(Yaku::Junchan),
+        34 => Some(Yaku::Honitsu),
+        35 => Some(Yaku::Chinitsu),
+        36 => Some(Yaku::Renho),
+        37 => Some(Yaku::Tenho),
+        38 => Some(Yaku::Chiho),
+        39 => Some(Yaku::Daisangen),
+        40 => Some(Yaku::Suuankou),
+        41 => Some(Yaku::SuuankouTanki),
+        42 => Some(Yaku::Tsuiso),
+        43 => Some(Yaku::Ryuiso),
+        44 => Some(Yaku::Chinroto),
+        45 | 46 => Some(Yaku::ChurenPoto),
+        47 | 48 => Some(Yaku::Kokushi),
+        49 => Some(Yaku::Daisushi),
+        50 => Some(Yaku::Shosushi),
+        51 => Some(Yaku::Sukantsu),
+        _ => None,
+    }
+}
+
+// Decodes a Tenhou meld bitfield, called by player. Returns the meld along with the ids of its tiles; the called
+// tile's id comes last, except for ankan. For shouminkan the last id is the added tile instead.
+//
+// # Errors
+//
+// Returns Unimplemented for kita (sanma), and BadMeld if the bitfield doesn't describe a valid meld.
+#[allow(clippy::similar_names)]
+pub fn decode_meld(m: u16, player: usize, dealer: usize, red: bool) -> Result<(Meld, Vec<u8>), ParsingError> {
+    let from = usize::from(m & 3); // relative to the caller; 1 is the next player, 3 the previous one
+    let called_from = Some(WINDS[(player + from + 4 - dealer) % 4]);
+    let tiles = |ids: &[u8]| ids.iter().map(|id| tile_from_id(*id, red)).collect::<Result<Vec<_>, _>>();
+
+    #[allow(clippy::cast_possible_truncation)]
+    let (mut meld, ids, kind, called_index) = if m & 0x4 != 0 {
+        let base = (m & 0xFC00) >> 10;
+        let called = usize::from(base % 3);
+        let start = (base / 3) / 7 * 9 + (base / 3) % 7;
+        let ids: Vec<u8> = (0..3).map(|i| ((start + i) * 4 + ((m >> (3 + 2 * i)) & 3)) as u8).collect();
+        let meld = tiles(&ids)?.make_meld(true).ok_or(ParsingError::BadMeld)?;
+        let mut ordered: Vec<u8> = ids.iter().enumerate().filter(|(i, _)| *i != called).map(|(_, id)| *id).collect();
+        ordered.push(ids[called]);
+        (meld, ordered, MeldKind::Chi, Some(called))
+    } else if m & 0x18 != 0 {
+        let base = (m & 0xFE00) >> 9;
+        let called = usize::from(base % 3);
+        let kind = (base / 3) as u8;
+        let unused = ((m & 0x60) >> 5) as u8;
+        let ids: Vec<u8> = (0..4).filter(|c| *c != unused).map(|c| kind * 4 + c).collect();
+        if m & 0x8 != 0 {
+            let meld = tiles(&ids)?.make_meld(true).ok_or(ParsingError::BadMeld)?;
+            let mut ordered: Vec<u8> = ids.iter().enumerate().filter(|(i, _)| *i != called).map(|(_, id)| *id).collect();
+            ordered.push(ids[called]);
+            (meld, ordered, MeldKind::Pon, Some(called))
+        } else {
+            let mut all = ids.clone();
+            all.push(kind * 4 + unused);
+            let meld = tiles(&all)?.make_meld(true).ok_or(ParsingError::BadMeld)?;
+            (meld, all, MeldKind::Shouminkan, Some(called))
+        }
+    } else if m & 0x20 != 0 {
+        return Err(ParsingError::Unimplemented)
+    } else {
+        let called = (m >> 8) as u8;
+        let kind = called / 4;
+        if from == 0 {
+            let ids: Vec<u8> = (0..4).map(|c| kind * 4 + c).collect();
+            let meld = tiles(&ids)?.make_meld(false).ok_or(ParsingError::BadMeld)?;
+            (meld, ids, MeldKind::Ankan, None)
+        } else {
+            let mut ids: Vec<u8> = (0..4).map(|c| kind * 4 + c).filter(|id| *id != called).collect();
+            ids.push(called);
+            let meld = tiles(&(0..4).map(|c| kind * 4 + c).collect::<Vec<_>>())?.make_meld(true).ok_or(ParsingError::BadMeld)?;
+            (meld, ids, MeldKind::Daiminkan, Some(usize::from(called % 4)))
+        }
+    };
+
+    meld.kind = Some(kind);
+    meld.called_index = called_index;
+    if kind != MeldKind::Ankan { meld.called_from = called_from }
+    Ok((meld, ids))
+}
+
+impl Table {
+    fn discard(&mut self, player: usize, id: u8, red: bool) -> Result<(), ParsingError> {
+        let hand = &mut self.hands[player];
+        let index = hand.iter().position(|t| *t == id).ok_or(ParsingError::NothingFound)?;
+        hand.remove(index);
+        self.discards[player].push(Discard { tile: tile_from_id(id, red)?, called: false });
+        self.last_draw[player] = None;
+        self.last_discard = Some(player);
+        Ok(())
+    }
+
+    fn call(&mut self, player: usize, meld: Meld, ids: &[u8]) -> Result<(), ParsingError> {
+        let from_hand = match meld.kind {
+            Some(MeldKind::Ankan) => ids,
+            Some(MeldKind::Shouminkan) => &ids[3..],
+            _ => {
+                let discarder = self.last_discard.ok_or(ParsingError::BadMeld)?;
+                if let Some(discard) = self.discards[discarder].last_mut() { discard.called = true }
+                &ids[..ids.len() - 1]
+            },
+        };
+        self.last_draw[player] = None;
+        for id in from_hand {
+            let index = self.hands[player].iter().position(|t| t == id).ok_or(ParsingError::NothingFound)?;
+            self.hands[player].remove(index);
+        }
+
+        if meld.kind == Some(MeldKind::Shouminkan) {
+            // the pon is upgraded in place, so the melds stay in the order they were called
+            let pon = self.melds[player].iter_mut().find(|m| m.is_trip() && m.tiles[0] == meld.tiles[0]).ok_or(ParsingError::BadMeld)?;
+            *pon = meld;
+        } else { self.melds[player].push(meld) }
+        Ok(())
+    }
+
+    fn seat(&self, player: usize, red: bool) -> Result<Seat, ParsingError> {
+        let mut closed = self.hands[player].iter().map(|id| tile_from_id(*id, red)).collect::<Result<Vec<_>, _>>()?;
+        closed.sort();
+        let mut seat = Seat::new(closed, if self.melds[player].is_empty() { None } else { Some(self.melds[player].clone()) },
+            WINDS[(player + 4 - self.dealer) % 4], None, None, if self.riichi[player] { Some(vec![Yaku::Riichi]) } else { None });
+        seat.discards = Some(self.discards[player].clone());
+        Ok(seat)
+    }
+
+    fn agari<'a>(&self, attr: &impl Fn(&str) -> Option<&'a str>, red: bool) -> Result<MjlogAgari, ParsingError> {
+        let winner: usize = parse_number(attr("who").ok_or(ParsingError::Empty)?)?;
+        let from: usize = parse_number(attr("fromWho").ok_or(ParsingError::Empty)?)?;
+        if winner > 3 || from > 3 { return Err(ParsingError::BadInteger) }
+        let discarder = if from == winner { None } else { Some(from) };
+
+        let machi: u8 = parse_number(attr("machi").ok_or(ParsingError::Empty)?)?;
+        let mut hai: Vec<u8> = parse_list(attr("hai").ok_or(ParsingError::Empty)?)?;
+        let index = hai.iter().position(|id| *id == machi).ok_or(ParsingError::NothingFound)?;
+        hai.remove(index);
+
+        let ten: Vec<u32> = parse_list(attr("ten").ok_or(ParsingError::Empty)?)?;
+        let [fu, points, ..] = ten[..] else { return Err(ParsingError::BadString) };
+        let yaku: Vec<u8> = parse_list(attr("yaku").unwrap_or(""))?;
+        let yaku: Vec<(u8, u8)> = yaku.chunks(2).filter_map(|pair| if let [id, han] = pair { Some((*id, *han)) } else { None }).collect();
+        let yakuman: Vec<u8> = parse_list(attr("yakuman").unwrap_or(""))?;
+
+        let ba: Vec<u8> = parse_list(attr("ba").unwrap_or("0,0"))?;
+        let tiles = |key: &str| -> Result<Option<Vec<Tile>>, ParsingError> {
+            match attr(key) {
+                Some(ids) => Ok(Some(parse_list::<u8>(ids)?.into_iter().map(|id| tile_from_id(id, red)).collect::<Result<Vec<_>, _>>()?)),
+                None => Ok(None),
+            }
+        };
+        let mut game = self.game.clone().ok_or(ParsingError::BadString)?;
+        game.repeats = ba.first().copied().unwrap_or(game.repeats);
+        game.dora_markers = tiles("doraHai")?.or(game.dora_markers);
+        game.ura_dora_markers = tiles("doraHaiUra")?;
+
+        // situational yaku can't be derived from the tiles, so they're taken from what Tenhou awarded
+        let special: Vec<Yaku> = yaku.iter().map(|(id, _)| *id).chain(yakuman.iter().copied())
+            .filter(|id| matches!(id, 1..=6 | 21 | 37 | 38)).filter_map(yaku_from_id).collect();
+        let latest_type = if discarder.is_some() { TileType::Call }
+            else if special.contains(&Yaku::AfterKan) { TileType::Kan }
+            else { TileType::Draw };
+
+        let mut closed = hai.iter().map(|id| tile_from_id(*id, red)).collect::<Result<Vec<_>, _>>()?;
+        closed.sort();
+        let mut seat = Seat::new(closed, if self.melds[winner].is_empty() { None } else { Some(self.melds[winner].clone()) },
+            WINDS[(winner + 4 - self.dealer) % 4], Some(tile_from_id(machi, red)?), Some(latest_type), if special.is_empty() { None } else { Some(special) });
+        seat.discards = Some(self.discards[winner].clone());
+
+        Ok(MjlogAgari { game, seat, winner, discarder, fu: u8::try_from(fu).map_err(|_| ParsingError::BadInteger)?, points, yaku, yakuman })
+    }
+}
+
+// Splits a log into tag names and their attributes. Doesn't handle anything beyond what mjlogs use.
+fn read_tags(log: &str) -> impl Iterator<Item = (&str, Vec<(&str, &str)>)> {
+    log.split('<').skip(1).filter_map(|s| s.split_once('>')).map(|(tag, _)| {
+        let tag = tag.trim_end_matches('/').trim();
+        let (name, mut rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let mut attrs: Vec<(&str, &str)> = Vec::new();
+        while let Some((key, after)) = rest.split_once("=\"") {
+            let Some((value, remaining)) = after.split_once('"') else { break };
+            attrs.push((key.trim(), value));
+            rest = remaining;
+        }
+        (name, attrs)
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, ParsingError> {
+    s.trim().parse().map_err(|_| ParsingError::BadInteger)
+}
+
+fn parse_list<T: std::str::FromStr>(s: &str) -> Result<Vec<T>, ParsingError> {
+    if s.is_empty() { return Ok(Vec::new()) }
+    s.split(',').map(parse_number).collect()
+}
+
+///////////
+// tests //
+///////////
+
+mod tests {
+    use super::*;
+    use crate::hand::{Hand, HandTrait};
+    use crate::scoring::{Payment, HandScore};
+    use crate::conversions::ConvertStrings;
+
+    // player 1 pons red dragon off player 0, then rons on player 0's s8. 1 han 30 fu.
+    static LOG: &str = r#"<mjloggm ver="2.3"><GO type="169" lobby="0"/>
+        <INIT seed="0,0,0,2,3,32" ten="250,250,250,250" oya="0" hai0="134,101,0,1,2,20,24,28,60,64,68,108,112"
+            hai1="133,132,4,8,12,40,44,48,92,96,76,77,120" hai2="3,5,6,7,9,10,11,13,14,15,17,18,19"
+            hai3="21,22,23,25,26,27,29,30,31,33,34,35,36"/>
+        <T50/><D134/><N who="1" m="51819" /><E120/><V37/><F37/><W38/><G38/><T39/><D101/>
+        <AGARI ba="0,0" hai="4,8,12,40,44,48,76,77,92,96,101" m="51819" machi="101" ten="30,1000,0" yaku="20,1"
+            doraHai="32" who="1" fromWho="0" sc="250,-10,250,10,250,0,250,0" />
+        </mjloggm>"#;
+
+    #[test]
+    fn test_tile_ids(){
+        assert_eq!(tile_from_id(0, true).map(|t| t.to_string()), Ok("m1".to_string()));
+        assert_eq!(tile_from_id(16, true).map(|t| t.to_string()), Ok("m5r".to_string()));
+        assert_eq!(tile_from_id(16, false).map(|t| t.to_string()), Ok("m5".to_string()));
+        assert_eq!(tile_from_id(53, true).map(|t| t.to_string()), Ok("p5".to_string()));
+        assert_eq!(tile_from_id(88, true).map(|t| t.to_string()), Ok("s5r".to_string()));
+        assert_eq!(tile_from_id(111, true).map(|t| t.to_string()), Ok("we".to_string()));
+        assert_eq!(tile_from_id(124, true).map(|t| t.to_string()), Ok("dw".to_string()));
+        assert_eq!(tile_from_id(135, true).map(|t| t.to_string()), Ok("dr".to_string()));
+        assert_eq!(tile_from_id(136, true), Err(ParsingError::BadInteger));
+    }
+
+    #[test]
+    fn test_meld_bitfields(){
+        // chi of m2,m3,m4 calling m3
+        let (meld, ids) = decode_meld(4103, 1, 0, true).unwrap();
+        assert_eq!((meld.to_string(), ids), ("m2,m3*,m4@e".to_string(), vec![4, 12, 8]));
+        // pon of red dragons, calling the third copy
+        assert_eq!(decode_meld(51819, 1, 0, true).map(|(m, _)| m.to_string()), Ok("dr,dr,dr*@e".to_string()));
+        // closed kan
+        assert_eq!(decode_meld(33792, 1, 0, true).map(|(m, _)| m.to_string()), Ok("!dr,dr,dr,dr".to_string()));
+        // the same pon, with the unused copy added
+        assert_eq!(decode_meld(51819 ^ 0x18, 1, 0, true).map(|(m, ids)| (m.to_string(), ids)), Ok(("+dr,dr,dr*,dr@e".to_string(), vec![132, 133, 134, 135])));
+    }
+
+    #[test]
+    fn test_reading_mjlog(){
+        let events = read_mjlog(LOG, RiichiRuleset::Default).unwrap();
+        assert_eq!(events.len(), 12);
+        assert!(matches!(events[0], MjlogEvent::Init { dealer: 0, deposits: 0, scores: [25000, 25000, 25000, 25000], .. }));
+        assert_eq!(events[2], MjlogEvent::Discard { player: 0, tile: "dr".to_tile().unwrap(), tsumogiri: false });
+        assert!(matches!(events[6], MjlogEvent::Discard { player: 2, tsumogiri: true, .. }));
+
+        let Some(MjlogEvent::Agari(agari)) = events.last() else { panic!("should end with a win") };
+        assert_eq!((agari.winner, agari.discarder, agari.fu, agari.points), (1, Some(0), 30, 1000));
+        assert_eq!(agari.seat.seat_wind, Wind::South);
+        assert_eq!(agari.seat.called_melds.as_ref().map(|m| m[0].to_string()), Some("dr,dr,dr*@e".to_string()));
+
+        let hand = Hand::new(agari.game.clone(), agari.seat.clone());
+        assert_eq!((hand.han(), hand.fu()), (1, agari.fu));
+        assert_eq!(hand.payment_split(agari.game.ruleset, 0), Ok(Payment::Ron(agari.points)));
+
+        assert_eq!(read_mjlog("<N who=\"1\" m=\"51819\" />", RiichiRuleset::Default), Err(ParsingError::BadMeld));
+        assert_eq!(read_mjlog("<N who=\"4\" m=\"51819\" />", RiichiRuleset::Default), Err(ParsingError::BadInteger));
+        assert_eq!(read_mjlog("<REACH who=\"4\" step=\"1\" />", RiichiRuleset::Default), Err(ParsingError::BadInteger));
+    }
+}
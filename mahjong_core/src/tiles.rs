@@ -32,6 +32,20 @@ pub enum Dragon {White, Green, Red,}
 #[derive(Debug, PartialEq, Clone, Copy, Eq, PartialOrd, Ord, Hash)]
 pub enum Wind {East, South, West, North,}
 
+// A tile kind as an index from 0 to 33, in the layout used by most bot tooling and lookup tables:
+// 1-9m, 1-9p, 1-9s, then east south west north, then white green red. Red fives share an index with other fives.
+// Note this isn't the same order as Tile's Ord, which sorts sou before pin and dragons before winds.
+#[derive(Debug, PartialEq, Clone, Copy, Eq, PartialOrd, Ord, Hash)]
+pub struct TileId(u8);
+
+// Number of copies of each tile kind, indexed by TileId.
+pub type Histogram = [u8; 34];
+
+// One of the 136 physical tiles in a set, numbered as Tenhou does: id / 4 is the TileId, and the first copy of
+// each five (16, 52, 88) is the red one. Unlike Tile, two copies of the same kind are never equal.
+#[derive(Debug, PartialEq, Clone, Copy, Eq, PartialOrd, Ord, Hash)]
+pub struct PhysicalTile(u8);
+
 ///////////////
 // functions //
 ///////////////
@@ -42,6 +56,22 @@ pub fn make_tiles_from_string(str: &str) -> Result<Vec<Tile>, ParsingError> {
     Ok(tiles) 
 }
 
+// returns one of every tile, in sorted order. red fives are not included.
+pub fn all_tile_kinds() -> Vec<Tile> {
+    let mut tiles: Vec<Tile> = Vec::with_capacity(34);
+    for suit in [Suit::Man, Suit::Sou, Suit::Pin] {
+        for number in 1..=9 { tiles.push(Tile::Number{suit, number, red: false}) }
+    }
+    for dragon in [Dragon::White, Dragon::Green, Dragon::Red] { tiles.push(Tile::Dragon(dragon)) }
+    for wind in [Wind::East, Wind::South, Wind::West, Wind::North] { tiles.push(Tile::Wind(wind)) }
+    tiles
+}
+
+// returns all 136 tiles of a set, ie to build a wall from.
+pub fn all_physical_tiles() -> Vec<PhysicalTile> {
+    (0..136).map(PhysicalTile).collect()
+}
+
 ////////////
 // traits //
 ////////////
@@ -64,7 +94,7 @@ pub trait TileRelations {
     fn adjacent_all(&self) -> Vec<[Tile; 2]>;       // returns all possible adjacent tiles
     fn adjacent_up(&self) -> Option<[Tile; 2]>;     // for numbered tiles, returns n+1 and n+2 (if possible)
     fn adjacent_down(&self) -> Option<[Tile; 2]>;   // for numbered tiles, returns n-1 and n-2 (if possible)
-    fn adjacent_around(&self) -> Option<[Tile; 2]>; // 
+    fn adjacent_around(&self) -> Option<[Tile; 2]>; // for numbered tiles, returns n-1 and n+1 (if possible)
     fn adjacent_aside(&self) -> Option<[Tile; 2]>;  // returns itself, twice.
     fn adjacent(&self, one: i8, two: i8) -> Option<[Tile; 2]>; // helper for the others
 }
@@ -73,6 +103,15 @@ pub trait DoraTrait {
     fn dora(&self) -> Self where Self: Sized;
 }
 
+pub trait ToHistogram {
+    fn to_histogram(&self) -> Histogram;
+}
+
+pub trait HistogramTrait {
+    fn tile_count(&self) -> usize;
+    fn to_tile_vec(&self) -> Vec<Tile>;     // sorted, without red fives
+}
+
 pub trait TileVecTrait {
     fn count_occurrences(&self, tile: &Tile) -> usize;
     fn remove_occurences(&mut self, tile: &Tile, count: usize);
@@ -83,6 +122,11 @@ pub trait TileVecTrait {
     fn count_dora(&self, dora_markers: &Option<Vec<Tile>>) -> u8;
 }
 
+// Only for tiles which keep their red fives; histograms only count kinds.
+pub trait RedFiveTrait {
+    fn count_red(&self) -> u8;
+}
+
 /////////////////////
 // implementations //
 /////////////////////
@@ -139,17 +183,13 @@ impl TileIs for Tile {
 impl TileRelations for Tile {
     fn adjacent_all(&self) -> Vec<[Tile; 2]> { panic!() }
     fn adjacent_up(&self) -> Option<[Tile; 2]> { self.adjacent(1,2) }
-    fn adjacent_down(&self) -> Option<[Tile; 2]> { self.adjacent(-2,1) }
+    fn adjacent_down(&self) -> Option<[Tile; 2]> { self.adjacent(-1,-2) }
     fn adjacent_around(&self) -> Option<[Tile; 2]> { self.adjacent(-1,1) }
     fn adjacent_aside(&self) -> Option<[Tile; 2]> { Some([*self, *self]) }
     fn adjacent(&self, one: i8, two: i8) -> Option<[Tile; 2]> {
-        if let Tile::Number {suit, number, ..} = self {
-            match number {
-                8 | 9 => None,
-                _ => Some([
-                    Tile::Number{suit: *suit, number: number + one, red: false},
-                    Tile::Number{suit: *suit, number: number + two, red: false} ])
-        } } else { None }
+        let Tile::Number {suit, number, ..} = self else { return None };
+        let shift = |offset: i8| (1..=9).contains(&(number + offset)).then_some(Tile::Number{suit: *suit, number: number + offset, red: false});
+        Some([shift(one)?, shift(two)?])
     }
 }
 
@@ -220,14 +260,8 @@ macro_rules! impl_TileVecTrait {
                 self.iter().any(|t| t.is_terminal())
             }
             fn count_dora(&self, dora_markers: &Option<Vec<Tile>>) -> u8 {
-                if let Some(markers) = dora_markers {
-                    let mut dora_tiles: Vec<_> = markers.iter().map(|t| t.dora()).collect();
-                    dora_tiles.sort();
-                    self.iter().fold(0, |acc, t| {
-                        if let Ok(_) = dora_tiles.binary_search(&t) { acc + 1 }
-                        else { acc }
-                    })
-                } else { 0 }
+                // every marker counts, so a tile pointed to by two markers is worth two dora
+                dora_markers.iter().flatten().map(|m| self.iter().filter(|t| **t == m.dora()).count() as u8).sum()
             }
         })*
     }
@@ -235,8 +269,131 @@ macro_rules! impl_TileVecTrait {
 
 impl_TileVecTrait!(for Vec<Tile>, [Tile]);
 
+impl RedFiveTrait for [Tile] {
+    fn count_red(&self) -> u8 {
+        self.iter().fold(0, |acc, t| if matches!(t, Tile::Number { red: true, .. }) { acc + 1 } else { acc })
+    }
+}
+
+impl TileId {
+    pub const COUNT: usize = 34;
+
+    pub fn new(id: u8) -> Option<TileId> {
+        if usize::from(id) < TileId::COUNT { Some(TileId(id)) } else { None }
+    }
+    pub fn index(self) -> usize { usize::from(self.0) }
+}
+
+impl From<Tile> for TileId {
+    fn from(tile: Tile) -> TileId {
+        TileId(match tile {
+            Tile::Number { suit: Suit::Man, number, .. } => number as u8 - 1,
+            Tile::Number { suit: Suit::Pin, number, .. } => number as u8 + 8,
+            Tile::Number { suit: Suit::Sou, number, .. } => number as u8 + 17,
+            Tile::Wind(wind) => 27 + wind as u8,
+            Tile::Dragon(dragon) => 31 + dragon as u8,
+        })
+    }
+}
+
+impl From<TileId> for Tile {
+    fn from(id: TileId) -> Tile {
+        let number = (id.0 % 9) as i8 + 1;
+        match id.0 {
+            0..=8 => Tile::Number { suit: Suit::Man, number, red: false },
+            9..=17 => Tile::Number { suit: Suit::Pin, number, red: false },
+            18..=26 => Tile::Number { suit: Suit::Sou, number, red: false },
+            27..=30 => Tile::Wind([Wind::East, Wind::South, Wind::West, Wind::North][usize::from(id.0 - 27)]),
+            _ => Tile::Dragon([Dragon::White, Dragon::Green, Dragon::Red][usize::from(id.0 - 31)]),
+        }
+    }
+}
+
+impl ToHistogram for [Tile] {
+    fn to_histogram(&self) -> Histogram {
+        let mut histogram: Histogram = [0; 34];
+        for tile in self { histogram[TileId::from(*tile).index()] += 1 }
+        histogram
+    }
+}
+
+impl ToHistogram for [PhysicalTile] {
+    fn to_histogram(&self) -> Histogram {
+        let mut histogram: Histogram = [0; 34];
+        for tile in self { histogram[tile.kind().index()] += 1 }
+        histogram
+    }
+}
+
+impl HistogramTrait for Histogram {
+    fn tile_count(&self) -> usize {
+        self.iter().map(|c| usize::from(*c)).sum()
+    }
+    fn to_tile_vec(&self) -> Vec<Tile> {
+        let mut tiles: Vec<Tile> = Vec::with_capacity(self.tile_count());
+        for (id, count) in self.iter().enumerate() {
+            for _ in 0..*count { tiles.push(Tile::from(TileId(id as u8))) }
+        }
+        tiles.sort();
+        tiles
+    }
+}
+
+impl TileVecTrait for Histogram {
+    fn count_occurrences(&self, tile: &Tile) -> usize {
+        usize::from(self[TileId::from(*tile).index()])
+    }
+    fn remove_occurences(&mut self, tile: &Tile, count: usize) {
+        let index = TileId::from(*tile).index();
+        self[index] = self[index].saturating_sub(u8::try_from(count).unwrap_or(u8::MAX));
+    }
+    fn count_suits(&self) -> usize {
+        self[..27].chunks(9).filter(|suit| suit.iter().any(|c| *c > 0)).count()
+    }
+    fn has_any_simple(&self) -> bool {
+        self[..27].chunks(9).any(|suit| suit[1..8].iter().any(|c| *c > 0))
+    }
+    fn has_any_honor(&self) -> bool {
+        self[27..].iter().any(|c| *c > 0)
+    }
+    fn has_any_terminal(&self) -> bool {
+        self[..27].chunks(9).any(|suit| suit[0] > 0 || suit[8] > 0)
+    }
+    fn count_dora(&self, dora_markers: &Option<Vec<Tile>>) -> u8 {
+        dora_markers.iter().flatten().map(|m| self[TileId::from(m.dora()).index()]).sum()
+    }
+}
+
+impl PhysicalTile {
+    pub const COUNT: usize = 136;
+
+    pub fn new(id: u8) -> Option<PhysicalTile> {
+        if usize::from(id) < PhysicalTile::COUNT { Some(PhysicalTile(id)) } else { None }
+    }
+    // copy is 0-3; copy 0 of a five is the red one.
+    pub fn from_kind(kind: TileId, copy: u8) -> Option<PhysicalTile> {
+        if copy < 4 { Some(PhysicalTile(kind.0 * 4 + copy)) } else { None }
+    }
+    pub fn id(self) -> u8 { self.0 }
+    pub fn kind(self) -> TileId { TileId(self.0 / 4) }
+    pub fn is_red(self) -> bool { matches!(self.0, 16 | 52 | 88) }
+}
+
+impl From<PhysicalTile> for Tile {
+    fn from(tile: PhysicalTile) -> Tile {
+        match Tile::from(tile.kind()) {
+            Tile::Number { suit, number, .. } => Tile::Number { suit, number, red: tile.is_red() },
+            honor => honor,
+        }
+    }
+}
+
+impl From<PhysicalTile> for TileId {
+    fn from(tile: PhysicalTile) -> TileId { tile.kind() }
+}
+
 /////////////////////////////
-/// equality & ordinality ///
+// equality & ordinality //
 /////////////////////////////
 
 impl PartialEq for Tile {
@@ -307,7 +464,7 @@ impl Hash for Tile {
 }
 
 //////////////////
-/// formatting ///
+// formatting //
 //////////////////
 
 impl fmt::Display for Tile {
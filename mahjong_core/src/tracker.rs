@@ -0,0 +1,191 @@
+/*
+Tracks which tiles one seat can see, so analyses can ask how many copies of a tile are still unseen from that seat:
+its own hand, every discard, called melds, dora markers, and north tiles set aside as kita.
+
+A tracker is either built from a snapshot of the table, or kept up to date as events happen. Players are numbered 0-3,
+as in tenhou::MjlogEvent, which a tracker can follow directly. Red fives count as regular fives.
+*/
+
+use crate::tiles::{Tile, TileId, Wind, Histogram};
+use crate::hand::{Meld, MeldKind, MeldHas};
+use crate::state::Discard;
+use crate::tenhou::MjlogEvent;
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TileTracker {
+    player: usize,      // the seat whose point of view is tracked
+    seen: Histogram,
+}
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl TileTracker {
+    // A tracker at the start of a hand.
+    pub fn new(player: usize, hand: &[Tile], dora_markers: &[Tile]) -> TileTracker {
+        let mut tracker = TileTracker { player, seen: [0; 34] };
+        hand.iter().chain(dora_markers).for_each(|t| tracker.reveal(t));
+        tracker
+    }
+
+    // A tracker for a hand already in progress. `hand` is the player's closed tiles; melds and ponds are everyone's,
+    // including the player's own. Called discards are skipped, since they're counted in the caller's meld.
+    pub fn from_table(player: usize, hand: &[Tile], ponds: &[&[Discard]], melds: &[&[Meld]], dora_markers: &[Tile], kita: usize) -> TileTracker {
+        let mut tracker = TileTracker::new(player, hand, dora_markers);
+        ponds.iter().flat_map(|pond| pond.iter()).filter(|d| !d.called).for_each(|d| tracker.reveal(&d.tile));
+        melds.iter().flat_map(|melds| melds.iter()).flat_map(MeldHas::as_tiles).for_each(|t| tracker.reveal(&t));
+        for _ in 0..kita { tracker.reveal(&Tile::Wind(Wind::North)) }
+        tracker
+    }
+
+    pub fn player(&self) -> usize { self.player }
+
+    // Copies of each tile kind the player has seen, indexed by TileId.
+    pub fn seen(&self) -> &Histogram { &self.seen }
+
+    pub fn unseen(&self, tile: &Tile) -> u8 {
+        4_u8.saturating_sub(self.seen[TileId::from(*tile).index()])
+    }
+
+    // Copies of each tile kind the player hasn't seen, indexed by TileId.
+    pub fn remaining(&self) -> Histogram {
+        self.seen.map(|count| 4_u8.saturating_sub(count))
+    }
+
+    // The player draws a tile. Other players' draws can't be seen.
+    pub fn draw(&mut self, tile: &Tile) { self.reveal(tile) }
+
+    pub fn discard(&mut self, player: usize, tile: &Tile) {
+        if player != self.player { self.reveal(tile) }
+    }
+
+    // Called tiles were already counted as discards, and added kans already had their pon counted.
+    pub fn call(&mut self, player: usize, meld: &Meld) {
+        if player == self.player { return }
+        let tiles = meld.as_tiles();
+
+        match meld.kind {
+            Some(MeldKind::Shouminkan) => if let Some(tile) = tiles.first() { self.reveal(tile) },
+            Some(MeldKind::Ankan) => tiles.iter().for_each(|t| self.reveal(t)),
+            _ if !meld.is_open => tiles.iter().for_each(|t| self.reveal(t)),
+            _ => {
+                let called = meld.called_index.unwrap_or(0);
+                tiles.iter().enumerate().filter(|(i, _)| *i != called).for_each(|(_, t)| self.reveal(t));
+            },
+        }
+    }
+
+    // A new dora marker, after a kan.
+    pub fn dora(&mut self, marker: &Tile) { self.reveal(marker) }
+
+    // A player sets a north aside (sanma).
+    pub fn kita(&mut self, player: usize) {
+        if player != self.player { self.reveal(&Tile::Wind(Wind::North)) }
+    }
+
+    // Follows a Tenhou log. A new hand resets the tracker to the player's starting hand.
+    pub fn apply(&mut self, event: &MjlogEvent) {
+        match event {
+            MjlogEvent::Init { game, hands, .. } => {
+                let hand = hands.get(self.player).map_or(&[][..], Vec::as_slice);
+                *self = TileTracker::new(self.player, hand, game.dora_markers.as_deref().unwrap_or_default());
+            },
+            MjlogEvent::Draw { player, tile } if *player == self.player => self.draw(tile),
+            MjlogEvent::Discard { player, tile, .. } => self.discard(*player, tile),
+            MjlogEvent::Call { player, meld } => self.call(*player, meld),
+            MjlogEvent::Dora(tile) => self.dora(tile),
+            _ => (),
+        }
+    }
+
+    fn reveal(&mut self, tile: &Tile) {
+        let count = &mut self.seen[TileId::from(*tile).index()];
+        *count = (*count + 1).min(4);
+    }
+}
+
+///////////
+// tests //
+///////////
+
+mod tests {
+    use super::*;
+    use crate::conversions::{ConvertStrings, ConvertTiles};
+    use crate::state::{Game, GameHelper};
+    use crate::rulesets::RiichiRuleset;
+
+    #[test]
+    fn test_tracking_tiles(){
+        let hand = "m1,m2,m3,p5r,p5,s7,s8,s9,dr,dr,we,we,wn".to_tiles().unwrap();
+        let dora = "p4".to_tiles().unwrap();
+        let mut tracker = TileTracker::new(0, &hand, &dora);
+        let tile = |t: &str| t.to_tile().unwrap();
+
+        assert_eq!(tracker.unseen(&tile("p5")), 2);
+        assert_eq!(tracker.unseen(&tile("p4")), 3);
+        assert_eq!(tracker.remaining().iter().map(|c| u32::from(*c)).sum::<u32>(), 136 - 14);
+
+        // the player's own discard was already in hand; others' are new
+        tracker.draw(&tile("dr"));
+        tracker.discard(0, &tile("dr"));
+        assert_eq!(tracker.unseen(&tile("dr")), 1);
+        tracker.discard(1, &tile("dr"));
+        assert_eq!(tracker.unseen(&tile("dr")), 0);
+
+        // player 2 pons a discarded west: only the two tiles from their hand are new
+        tracker.discard(1, &tile("ww"));
+        let mut pon = "ww,ww,ww".to_tiles().unwrap().make_meld(true).unwrap();
+        pon.kind = Some(MeldKind::Pon);
+        pon.called_index = Some(0);
+        tracker.call(2, &pon);
+        assert_eq!(tracker.unseen(&tile("ww")), 1);
+
+        // ... then adds the last one, and a closed kan shows all four at once
+        let mut added = "ww,ww,ww,ww".to_tiles().unwrap().make_meld(true).unwrap();
+        added.kind = Some(MeldKind::Shouminkan);
+        tracker.call(2, &added);
+        assert_eq!(tracker.unseen(&tile("ww")), 0);
+        tracker.call(3, &"!s2,s2,s2,s2".to_calls().unwrap()[0]);
+        assert_eq!(tracker.unseen(&tile("s2")), 0);
+
+        // chi only adds the tiles that weren't called
+        tracker.discard(3, &tile("m7"));
+        let mut chi = "m6,m7,m8".to_tiles().unwrap().make_meld(true).unwrap();
+        chi.kind = Some(MeldKind::Chi);
+        chi.called_index = Some(1);
+        tracker.call(1, &chi);
+        assert_eq!((tracker.unseen(&tile("m6")), tracker.unseen(&tile("m7")), tracker.unseen(&tile("m8"))), (3, 3, 3));
+
+        tracker.kita(0);
+        tracker.kita(2);
+        tracker.dora(&tile("wn"));
+        assert_eq!(tracker.unseen(&tile("wn")), 1);
+
+        // the same table, from a snapshot
+        let ponds: [Vec<Discard>; 4] = [
+            vec![Discard { tile: tile("dr"), called: false }],
+            vec![Discard { tile: tile("dr"), called: false }, Discard { tile: tile("ww"), called: true }],
+            Vec::new(),
+            vec![Discard { tile: tile("m7"), called: true }],
+        ];
+        let melds: [Vec<Meld>; 4] = [Vec::new(), vec![chi], vec![added], "!s2,s2,s2,s2".to_calls().unwrap()];
+        let mut closed = hand.clone();
+        closed.retain(|t| *t != tile("wn"));
+        let snapshot = TileTracker::from_table(0, &closed, &ponds.each_ref().map(Vec::as_slice), &melds.each_ref().map(Vec::as_slice),
+            &"p4,wn".to_tiles().unwrap(), 2);
+        assert_eq!(snapshot, tracker);
+
+        // a new hand in a log starts over
+        let game = Game::new(RiichiRuleset::Default, Wind::East, 0, Some(dora.clone()), None);
+        tracker.apply(&MjlogEvent::Init { game, dealer: 0, deposits: 0, scores: [25000; 4], hands: [hand.clone(), Vec::new(), Vec::new(), Vec::new()] });
+        assert_eq!(tracker, TileTracker::new(0, &hand, &dora));
+        tracker.apply(&MjlogEvent::Draw { player: 1, tile: tile("s1") });
+        tracker.apply(&MjlogEvent::Discard { player: 1, tile: tile("s2"), tsumogiri: false });
+        assert_eq!((tracker.unseen(&tile("s1")), tracker.unseen(&tile("s2"))), (4, 3));
+    }
+}
@@ -0,0 +1,150 @@
+/*
+The dead wall: the 14 tiles at the end of the wall which hold the dora indicators and the replacement draws for kans.
+Tiles are given in the order they're dealt from the wall: the four replacement draws, then the five dora indicators,
+then the five ura dora indicators under them. They're kept as physical tiles, so a red five indicator or replacement
+draw stays red.
+
+Each kan reveals another dora indicator. A closed kan's is revealed straight away; an open kan's (daiminkan or
+shouminkan) waits until the kan's replacement draw has been discarded, unless the ruleset flips them immediately. So a
+rinshan win on an open kan is scored without its own kandora. Whether it scores tsumo fu is up to
+RuleVariations::is_rinshan_tsumo(), which is given the kind of the last kan through LastAction::KanDraw.
+
+How many kans a hand allows is up to RuleVariations::max_kans(), though the dead wall only has four replacement draws.
+Each kan moves a tile from the end of the live wall into the dead wall, so the last draw comes one tile earlier per kan.
+*/
+
+use crate::tiles::{Tile, Wind, PhysicalTile};
+use crate::state::Game;
+use crate::hand::MeldKind;
+use crate::rulesets::RuleVariations;
+use crate::errors::mahjong_errors::HandError;
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeadWall {
+    tiles: [PhysicalTile; 14],
+    kans: Vec<(Wind, MeldKind)>,    // the seat which declared each kan and its kind, in order
+    revealed: usize,    // dora indicators face up
+    pending: bool,      // an open kan's indicator is waiting for the discard
+}
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl DeadWall {
+    // A dead wall at the start of a hand, with only the first dora indicator revealed.
+    //
+    // # Errors
+    //
+    // Returns TileCount unless given exactly 14 tiles.
+    pub fn new(tiles: &[PhysicalTile]) -> Result<DeadWall, HandError> {
+        Ok(DeadWall { tiles: tiles.try_into().map_err(|_| HandError::TileCount)?, kans: Vec::new(), revealed: 1, pending: false })
+    }
+
+    pub fn dora_markers(&self) -> Vec<Tile> { self.tiles[4..4 + self.revealed].iter().map(|t| Tile::from(*t)).collect() }
+
+    // Only looked at when a riichi hand wins, but there's one under each revealed indicator.
+    pub fn ura_dora_markers(&self) -> Vec<Tile> { self.tiles[9..9 + self.revealed].iter().map(|t| Tile::from(*t)).collect() }
+
+    // The seat which declared each kan so far and its kind, in order.
+    pub fn kans(&self) -> &[(Wind, MeldKind)] { &self.kans }
+
+    // The latest kan, which a replacement draw is for.
+    pub fn last_kan(&self) -> Option<(Wind, MeldKind)> { self.kans.last().copied() }
+
+    pub fn can_declare_kan<R: RuleVariations>(&self, ruleset: &R) -> bool { self.kans.len() < ruleset.max_kans().min(4) }
+
+    // Whether the kans so far abort the hand, ie four of them by more than one seat. Leaves the timing to the caller;
+    // see RuleVariations::suukaikan_on_discard().
+    pub fn is_suukaikan<R: RuleVariations>(&self, ruleset: &R) -> bool {
+        ruleset.has_suukaikan() && self.kans.len() >= ruleset.max_kans().min(4) && self.kans.iter().any(|(w, _)| *w != self.kans[0].0)
+    }
+
+    // Declares a kan, and returns the replacement draw. An open kan's indicator still waiting for a discard is
+    // revealed first, since a kan declared on a replacement draw comes before that discard.
+    //
+    // # Errors
+    //
+    // Returns IllegalCall if the ruleset's kans have all been declared, or ValueError if kind isn't a kan.
+    pub fn declare_kan<R: RuleVariations>(&mut self, ruleset: &R, kind: MeldKind, seat: Wind) -> Result<PhysicalTile, HandError> {
+        if !matches!(kind, MeldKind::Daiminkan | MeldKind::Shouminkan | MeldKind::Ankan) { return Err(HandError::ValueError) }
+        if !self.can_declare_kan(ruleset) { return Err(HandError::IllegalCall) }
+
+        self.discarded();
+        let replacement = self.tiles[self.kans.len()];
+        self.kans.push((seat, kind));
+        if kind == MeldKind::Ankan || ruleset.flips_open_kandora_immediately() { self.revealed += 1 }
+        else { self.pending = true }
+        Ok(replacement)
+    }
+
+    // A tile was discarded, revealing any open kan's indicator.
+    pub fn discarded(&mut self) {
+        if self.pending {
+            self.revealed += 1;
+            self.pending = false;
+        }
+    }
+
+    // Copies the revealed indicators into the game state.
+    pub fn update(&self, game_state: &mut Game) {
+        game_state.dora_markers = Some(self.dora_markers());
+        game_state.ura_dora_markers = Some(self.ura_dora_markers());
+    }
+}
+
+///////////
+// tests //
+///////////
+
+mod tests {
+    use super::*;
+    use crate::conversions::ConvertStrings;
+    use crate::tiles::TileId;
+    use crate::rulesets::RiichiRuleset;
+    use crate::state::GameHelper;
+
+    #[test]
+    fn test_kandora_timing(){
+        // the first copy of each kind, so p5 is the red one
+        let tiles: Vec<PhysicalTile> = "m1,m2,m3,m4,p1,p2,p3,p4,p5,s1,s2,s3,s4,s5".to_tiles().unwrap().into_iter()
+            .map(|t| PhysicalTile::from_kind(TileId::from(t), 0).unwrap()).collect();
+        let markers = |tiles: &str| tiles.to_tiles().unwrap();
+        assert_eq!(DeadWall::new(&tiles[..13]), Err(HandError::TileCount));
+        let mut wall = DeadWall::new(&tiles).unwrap();
+        assert_eq!((wall.dora_markers(), wall.ura_dora_markers()), (markers("p1"), markers("s1")));
+
+        // a closed kan flips at once, an open one after the discard
+        assert_eq!(wall.declare_kan(&RiichiRuleset::MajSoul, MeldKind::Ankan, Wind::East), Ok(tiles[0]));
+        assert_eq!(wall.dora_markers(), markers("p1,p2"));
+        assert_eq!(wall.declare_kan(&RiichiRuleset::MajSoul, MeldKind::Daiminkan, Wind::South), Ok(tiles[1]));
+        assert_eq!(wall.dora_markers(), markers("p1,p2"));
+        wall.discarded();
+        assert_eq!(wall.dora_markers(), markers("p1,p2,p3"));
+
+        // a kan on a replacement draw reveals the one before it
+        wall.declare_kan(&RiichiRuleset::MajSoul, MeldKind::Shouminkan, Wind::South).unwrap();
+        wall.declare_kan(&RiichiRuleset::MajSoul, MeldKind::Ankan, Wind::South).unwrap();
+        assert_eq!(wall.dora_markers(), markers("p1,p2,p3,p4,p5"));
+        assert_eq!(wall.dora_markers()[4].to_string(), "p5r");
+        assert_eq!(wall.kans(), &[(Wind::East, MeldKind::Ankan), (Wind::South, MeldKind::Daiminkan),
+            (Wind::South, MeldKind::Shouminkan), (Wind::South, MeldKind::Ankan)]);
+        assert_eq!(wall.last_kan(), Some((Wind::South, MeldKind::Ankan)));
+        assert!(wall.is_suukaikan(&RiichiRuleset::MajSoul) && !wall.is_suukaikan(&RiichiRuleset::WRC2022));
+        assert_eq!(wall.declare_kan(&RiichiRuleset::MajSoul, MeldKind::Ankan, Wind::South), Err(HandError::IllegalCall));
+
+        // some rulesets flip open kans straight away too
+        let mut wall = DeadWall::new(&tiles).unwrap();
+        wall.declare_kan(&RiichiRuleset::EMA2016, MeldKind::Daiminkan, Wind::West).unwrap();
+        assert_eq!(wall.dora_markers(), markers("p1,p2"));
+        assert_eq!(wall.declare_kan(&RiichiRuleset::EMA2016, MeldKind::Pon, Wind::West), Err(HandError::ValueError));
+
+        let mut game = Game::new(RiichiRuleset::EMA2016, Wind::East, 0, None, None);
+        wall.update(&mut game);
+        assert_eq!(game.ura_dora_markers, Some(markers("s1,s2")));
+    }
+}
@@ -1,8 +1,8 @@
 use crate::tiles::{Tile, Dragon, Wind, Suit, TileIs, TileVecTrait};
 use crate::errors::mahjong_errors::{HandError, ParsingError};
 use crate::rulesets::{RiichiRuleset, RuleVariations};
-use crate::state::{Game, Seat, TileType, WinType, InferWin, SeatAccess};
-use crate::hand::{HandShape, Pair, Meld, MeldIs, MeldHas, MeldVecHas, PairTrait};
+use crate::state::{Game, Seat, TileType, WinType, LastAction, InferWin, SeatAccess};
+use crate::hand::{HandShape, Pair, Meld, MeldIs, MeldHas, MeldVecHas, PairTrait, WaitShape, winning_tile_shapes, read_win};
 use std::fmt;
 use std::collections::HashSet;
 use itertools::Itertools;
@@ -11,86 +11,104 @@ use itertools::Itertools;
 // enums //
 ///////////
 
+// Han, names and descriptions for each yaku are kept together in YakuMetadata::info().
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Yaku {
-    Chiitoi,        // unique shape, fully closed hand          2 han closed
-    ClosedTsumo,    // tsumo, fully closed hand                 1 han closed
+    Chiitoi,
+    ClosedTsumo,
 
     // based on sequence
-    Pinfu,          // no fu awarded                            1 han closed
-    Ipeiko,         // two identical sequences, closed hand     1 han closed
-    SanshokuDoujun, // same sequence in each suit               2 han closed / 1 han open
-    Ittsuu,         // straight (1-9 in a suit)                 2 han closed / 1 han open
-    Ryanpeiko,      // ipeiko twice. replaces ipeiko            3 han closed
+    Pinfu,
+    Ipeiko,
+    SanshokuDoujun,
+    Ittsuu,
+    Ryanpeiko,      // replaces ipeiko
 
     // based on triplets/quads
-    Toitoi,         // all triplets                             2 han
-    Sananko,        // three concealed triplets                 2 han
-    SanshokuDouko,  // same triplet in each suit                2 han
-    Sankantsu,      // three quads                              2 han
+    Toitoi,
+    Sananko,
+    SanshokuDouko,
+    Sankantsu,
 
     // based on terminal/honor
-    Tanyao,         // no honor or terminal                     1 han
-    Yakuhai(u8),     // triplets or quads of dragons,           1 han per triplet
-                    // seat winds, or round winds. Round+Seat wind counts for double.
-    Chanta,         // each sequence/meld contains a terminal   2 han closed / 1 han open
-                    // or honor tile
-    Junchan,        // each sequence/meld contains a terminal   3 han closed / 2 han open
-                    // tile; no honor tiles present in hand
-    Honro,          // hand contains only terminal and honor    2 han
-                    // tiles; always paired with one of
-                    // chitoi or toitoi
-    Shosangen,      // two triplets/quads of dragons, and a     2 han
-                    // pair of the third dragon
-    
+    Tanyao,
+    Yakuhai(u8),    // counted once per triplet; round+seat wind counts twice
+    Chanta,
+    Junchan,
+    Honro,
+    Shosangen,
+
     // based on suits
-    Honitsu,        // half flush. simple tiles from only one   3 han closed / 2 han open
-                    // suit, plus honor tiles.
-                    // can apply to chitoi hands
-    Chinitsu,       // full flush. hand only contains simple    6 han closed / 5 han open
-                    // tiles from one suit; no honors.
-                    // can apply to chitoi hands
+    Honitsu,
+    Chinitsu,
 
     // yakuman hands
-    Kokushi,        // thirteen orphans, single-tile wait       limit           closed
-    Suuankou,       // four concealed triplets, double wait     limit           closed
-    SuuankouTanki,  // four concealed triplets, single wait     double limit    open or closed
-    Daisangen,      // big three dragons                        limit           open or closed
-    Shosushi,       // little four winds (3 triplets + pair)    limit           open or closed
-    Daisushi,       // big four winds (4 triplets)              double limit    open or closed
-    Tsuiso,         // all honors                               limit           open or closed
-    Daichiishin,    // all honors (chiitoi variant)             limit           closed
-    Chinroto,       // all terminals                            limit           open or closed
-    Ryuiso,         // all green (sou 2,3,4,6,8 + green dragon) limit           open or closed
-    ChurenPoto,     // nine gates                               limit           closed
-    Sukantsu,       // four kans                                limit           open or closed
-    
-    SpecialWait,    // the yakuman's wait adds additional value limit
+    Kokushi,
+    Suuankou,
+    SuuankouTanki,
+    Daisangen,
+    Shosushi,
+    Daisushi,
+    Tsuiso,
+    Daichiishin,
+    Chinroto,
+    Ryuiso,
+    ChurenPoto,
+    Sukantsu,
+    SpecialWait,    // the yakuman's wait adds additional value
                     // ie: four concealed triplets, single wait
                     //     thirteen orphans, thirteen-way wait
                     //     nine gates, nine-way wait
                     // I think that breaking this out into a unique criteria will simplify code somewhat
 
     // special yaku
-    Riichi,         // declared Riichi, fully closed hand       1 han closed
-    DoubleRiichi,   // declared Riichi on first turn            2 han closed
-    Ippatsu,        // win within one go-around after Riichi    1 han closed
-    UnderSea,       // very last tile drawn, tsumo only         1 han
-    UnderRiver,     // very last tile discarded, ron only       1 han
-    AfterKan,       // win on dead wall draw, tsumo only        1 han
-    RobbedKan,      // ron only                                 1 han
-    NagashiMangan,  // counts as tsumo, ignores other yaku      automatic mangan
+    Riichi,
+    DoubleRiichi,
+    Ippatsu,
+    UnderSea,
+    UnderRiver,
+    AfterKan,
+    RobbedKan,
+    NagashiMangan,  // ignores other yaku
 
     // special yakuman hands
-    Tenho,          // blessing of heaven. tsumo.               limit   closed, dealer only
-    Chiho,          // blessing of earth. tsumo.                limit   closed, non-dealer only
+    Tenho,
+    Chiho,
+    Renho,          // local yaku, see RuleVariations::renho_han()
+}
+
+// How a yaku is scored and what it's called. Rulesets can still change how much some yaku are worth; see CountHan.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct YakuInfo {
+    pub han_closed: u8,
+    pub han_open: Option<u8>,           // None for yaku which need a closed hand
+    pub yakuman: u8,                    // limit multiplier: 0 for regular yaku, 2 for double yakuman
+    pub name: &'static str,             // romanized Japanese
+    pub kanji: &'static str,
+    pub english: &'static str,
+    pub aliases: &'static [&'static str],   // other names accepted by ConvertStrings::to_yaku()
+    pub description: &'static str,
 }
 
 pub static YAKUMAN: [Yaku; 15] = [Yaku::Kokushi, Yaku::Suuankou, Yaku::SuuankouTanki, Yaku::Daisangen, Yaku::Shosushi,
                                 Yaku::Daisushi, Yaku::Tsuiso, Yaku::Chinroto, Yaku::Ryuiso, Yaku::ChurenPoto, Yaku::Sukantsu,
                                 Yaku::Daichiishin, Yaku::SpecialWait, Yaku::Tenho, Yaku::Chiho];
-pub static YAKU_SPECIAL: [Yaku; 10] = [Yaku::Riichi, Yaku::DoubleRiichi, Yaku::Ippatsu, Yaku::UnderSea, Yaku::UnderRiver,
-                                Yaku::AfterKan, Yaku::RobbedKan, Yaku::NagashiMangan, Yaku::Tenho, Yaku::Chiho];
+pub static YAKU_SPECIAL: [Yaku; 11] = [Yaku::Riichi, Yaku::DoubleRiichi, Yaku::Ippatsu, Yaku::UnderSea, Yaku::UnderRiver,
+                                Yaku::AfterKan, Yaku::RobbedKan, Yaku::NagashiMangan, Yaku::Tenho, Yaku::Chiho, Yaku::Renho];
+pub static ALL_YAKU: [Yaku; 43] = [Yaku::Chiitoi, Yaku::ClosedTsumo, Yaku::Pinfu, Yaku::Ipeiko, Yaku::SanshokuDoujun,
+                                Yaku::Ittsuu, Yaku::Ryanpeiko, Yaku::Toitoi, Yaku::Sananko, Yaku::SanshokuDouko, Yaku::Sankantsu,
+                                Yaku::Tanyao, Yaku::Yakuhai(1), Yaku::Chanta, Yaku::Junchan, Yaku::Honro, Yaku::Shosangen,
+                                Yaku::Honitsu, Yaku::Chinitsu, Yaku::Kokushi, Yaku::Suuankou, Yaku::SuuankouTanki, Yaku::Daisangen,
+                                Yaku::Shosushi, Yaku::Daisushi, Yaku::Tsuiso, Yaku::Daichiishin, Yaku::Chinroto, Yaku::Ryuiso,
+                                Yaku::ChurenPoto, Yaku::Sukantsu, Yaku::SpecialWait, Yaku::Riichi, Yaku::DoubleRiichi, Yaku::Ippatsu,
+                                Yaku::UnderSea, Yaku::UnderRiver, Yaku::AfterKan, Yaku::RobbedKan, Yaku::NagashiMangan,
+                                Yaku::Tenho, Yaku::Chiho, Yaku::Renho];
+// special yaku which depend on a riichi declaration; see find_riichi_yaku()
+pub static YAKU_RIICHI: [Yaku; 3] = [Yaku::Riichi, Yaku::DoubleRiichi, Yaku::Ippatsu];
+// special yaku which depend on how the winning tile was reached; see find_situational_yaku()
+pub static YAKU_SITUATIONAL: [Yaku; 4] = [Yaku::UnderSea, Yaku::UnderRiver, Yaku::AfterKan, Yaku::RobbedKan];
+// special yaku which depend on winning before the first go-around is over; see find_first_turn_yaku()
+pub static YAKU_FIRST_TURN: [Yaku; 3] = [Yaku::Tenho, Yaku::Chiho, Yaku::Renho];
 
 ////////////
 // traits //
@@ -107,6 +125,10 @@ pub trait YakuHelpers {
     fn contains_any(&self, yaku: &Self) -> bool;
 }
 
+pub trait YakuMetadata {
+    fn info(&self) -> YakuInfo;
+}
+
 pub trait FindYaku {
     fn yaku(&self, game_state: &Game, seat_state: &Seat) -> Vec<Yaku>;
 }
@@ -133,6 +155,8 @@ impl YakuHelpers for Vec<Yaku> {
                     if self.contains(&Yaku::Riichi) {
                         self.retain(|x| *x != Yaku::Riichi);
                     } self.push(yaku); },
+                // a hand can only be blessed once
+                Yaku::Tenho | Yaku::Chiho | Yaku::Renho if self.contains_any(&YAKU_FIRST_TURN.to_vec()) => (),
                 // yakuhai(0) isn't real and can't hurt you, but pretending that it is makes code cleaner elsewhere.
                 Yaku::Yakuhai(count) => if count > 0 { self.push(yaku) },
                 // nagashi mangan is incompatible with all other yaku
@@ -154,10 +178,113 @@ impl YakuHelpers for Vec<Yaku> {
         list.iter().any(|y| self.contains(y)) }
 }
 
+impl YakuMetadata for Yaku {
+    fn info(&self) -> YakuInfo {
+        match self {
+            Yaku::Chiitoi => regular(2, None, "chiitoitsu", "七対子", "Seven pairs", &["chiitoi"],
+                "Seven different pairs."),
+            Yaku::ClosedTsumo => regular(1, None, "menzenchin tsumohou", "門前清自摸和", "Fully concealed hand", &["menzen tsumo", "closed tsumo"],
+                "Winning on a self-draw with a closed hand."),
+            Yaku::Pinfu => regular(1, None, "pinfu", "平和", "All sequences", &[],
+                "Four sequences and a non-yakuhai pair, won on a two-sided wait: a hand worth no fu."),
+            Yaku::Ipeiko => regular(1, None, "iipeikou", "一盃口", "Pure double sequence", &["ipeiko"],
+                "Two identical sequences."),
+            Yaku::SanshokuDoujun => regular(2, Some(1), "sanshoku doujun", "三色同順", "Mixed triple sequence", &["sanshoku"],
+                "The same sequence in each suit."),
+            Yaku::Ittsuu => regular(2, Some(1), "ikkitsuukan", "一気通貫", "Pure straight", &["ittsuu", "ittsu"],
+                "1-2-3, 4-5-6 and 7-8-9 in one suit."),
+            Yaku::Ryanpeiko => regular(3, None, "ryanpeikou", "二盃口", "Twice pure double sequence", &["ryanpeiko"],
+                "Two pairs of identical sequences. Replaces iipeikou."),
+            Yaku::Toitoi => regular(2, Some(2), "toitoihou", "対々和", "All triplets", &["toitoi"],
+                "Four triplets or quads."),
+            Yaku::Sananko => regular(2, Some(2), "sanankou", "三暗刻", "Three concealed triplets", &["sananko"],
+                "Three triplets or quads formed without calls."),
+            Yaku::SanshokuDouko => regular(2, Some(2), "sanshoku doukou", "三色同刻", "Triple triplets", &["sanshoku douko"],
+                "The same triplet in each suit."),
+            Yaku::Sankantsu => regular(2, Some(2), "sankantsu", "三槓子", "Three quads", &[],
+                "Three quads, open or closed."),
+            Yaku::Tanyao => regular(1, Some(1), "tanyao", "断幺九", "All simples", &["tanyaochuu"],
+                "No terminals or honors. Some rulesets only allow it closed."),
+            Yaku::Yakuhai(count) => regular(*count, Some(*count), "yakuhai", "役牌", "Value tiles", &["fanpai"],
+                "A triplet or quad of dragons, the seat wind, or the round wind. A double wind counts twice."),
+            Yaku::Chanta => regular(2, Some(1), "honchantaiyaochuu", "混全帯幺九", "Half outside hand", &["chanta"],
+                "Every meld and the pair include a terminal or an honor."),
+            Yaku::Junchan => regular(3, Some(2), "junchantaiyaochuu", "純全帯幺九", "Fully outside hand", &["junchan"],
+                "Every meld and the pair include a terminal, with no honors."),
+            Yaku::Honro => regular(2, Some(2), "honroutou", "混老頭", "All terminals and honors", &["honro"],
+                "Only terminals and honors; always comes with toitoi or chiitoitsu."),
+            Yaku::Shosangen => regular(2, Some(2), "shousangen", "小三元", "Little three dragons", &["shosangen"],
+                "Triplets or quads of two dragons, and a pair of the third."),
+            Yaku::Honitsu => regular(3, Some(2), "honiisou", "混一色", "Half flush", &["honitsu"],
+                "Tiles from one suit, plus honors."),
+            Yaku::Chinitsu => regular(6, Some(5), "chiniisou", "清一色", "Full flush", &["chinitsu"],
+                "Tiles from one suit, with no honors."),
+
+            Yaku::Kokushi => yakuman(1, true, "kokushi musou", "国士無双", "Thirteen orphans", &["kokushi"],
+                "One of each terminal and honor, and a pair of one of them."),
+            Yaku::Suuankou => yakuman(1, true, "suuankou", "四暗刻", "Four concealed triplets", &["suuanko"],
+                "Four triplets or quads formed without calls."),
+            Yaku::SuuankouTanki => yakuman(2, true, "suuankou tanki", "四暗刻単騎", "Four concealed triplets, single wait", &["suuanko tanki"],
+                "Four concealed triplets, won on the pair."),
+            Yaku::Daisangen => yakuman(1, false, "daisangen", "大三元", "Big three dragons", &[],
+                "Triplets or quads of all three dragons."),
+            Yaku::Shosushi => yakuman(1, false, "shousuushii", "小四喜", "Little four winds", &["shosushi"],
+                "Triplets or quads of three winds, and a pair of the fourth."),
+            Yaku::Daisushi => yakuman(2, false, "daisuushii", "大四喜", "Big four winds", &["daisushi"],
+                "Triplets or quads of all four winds."),
+            Yaku::Tsuiso => yakuman(1, false, "tsuuiisou", "字一色", "All honors", &["tsuiso"],
+                "Only honors."),
+            Yaku::Daichiishin => yakuman(2, true, "daichiishin", "大七星", "Big seven stars", &[],
+                "Seven pairs of honors."),
+            Yaku::Chinroto => yakuman(1, false, "chinroutou", "清老頭", "All terminals", &["chinroto"],
+                "Only terminals."),
+            Yaku::Ryuiso => yakuman(1, false, "ryuuiisou", "緑一色", "All green", &["ryuiso"],
+                "Only 2, 3, 4, 6 and 8 sou, and green dragons where the ruleset allows them."),
+            Yaku::ChurenPoto => yakuman(1, true, "chuuren poutou", "九蓮宝燈", "Nine gates", &["churen poto"],
+                "1-1-1-2-3-4-5-6-7-8-9-9-9 in one suit, plus any tile of that suit."),
+            Yaku::Sukantsu => yakuman(1, false, "suukantsu", "四槓子", "Four quads", &["sukantsu"],
+                "Four quads, open or closed."),
+            Yaku::SpecialWait => yakuman(1, false, "special wait", "", "Special wait", &[],
+                "The extra limit for a yakuman won on its widest or narrowest wait."),
+
+            Yaku::Riichi => regular(1, None, "riichi", "立直", "Riichi", &["reach"],
+                "Declared tenpai with a closed hand."),
+            Yaku::DoubleRiichi => regular(2, None, "daburu riichi", "両立直", "Double riichi", &["double reach"],
+                "Riichi declared on the first discard, before any call."),
+            Yaku::Ippatsu => regular(1, None, "ippatsu", "一発", "One shot", &[],
+                "Winning within one go-around of declaring riichi, before any call."),
+            Yaku::UnderSea => regular(1, Some(1), "haitei raoyue", "海底摸月", "Under the sea", &["haitei", "undersea"],
+                "Winning on the last draw from the live wall."),
+            Yaku::UnderRiver => regular(1, Some(1), "houtei raoyui", "河底撈魚", "Under the river", &["houtei", "underriver"],
+                "Winning on the last discard."),
+            Yaku::AfterKan => regular(1, Some(1), "rinshan kaihou", "嶺上開花", "After a kan", &["rinshan", "afterkan", "rinshan kaiho"],
+                "Winning on a kan's replacement draw."),
+            Yaku::RobbedKan => regular(1, Some(1), "chankan", "搶槓", "Robbing a kan", &["robbedkan"],
+                "Winning on a tile added to another seat's pon."),
+            Yaku::NagashiMangan => regular(0, Some(0), "nagashi mangan", "流し満貫", "Mangan at draw", &[],
+                "Only terminals and honors discarded, none of them called, when the wall runs out. Paid as a mangan."),
+
+            Yaku::Tenho => yakuman(1, true, "tenhou", "天和", "Blessing of heaven", &["tenho"],
+                "The dealer wins on their first draw."),
+            Yaku::Chiho => yakuman(1, true, "chiihou", "地和", "Blessing of earth", &["chiho"],
+                "A non-dealer wins on their first draw, before any call."),
+            Yaku::Renho => regular(0, None, "renhou", "人和", "Blessing of man", &["renho"],
+                "A non-dealer wins on a discard before their first draw. Worth what the ruleset says, if anything."),
+        }
+    }
+}
+
 impl FindYaku for HandShape {
     fn yaku(&self, game_state: &Game, seat_state: &Seat) -> Vec<Yaku> {
         let win_type: WinType = seat_state.latest_type.unwrap().as_win();
         let mut yaku: Vec<Yaku> = { if let Some(y) = &seat_state.special_yaku { y.clone() } else { Vec::new() }};
+        // contradictions are reported when the hand is settled; see scoring::settle_win()
+        if seat_state.riichi.is_some() { yaku.retain(|y| !YAKU_RIICHI.contains(y)) }
+        yaku.append_checked(&riichi_yaku(game_state, seat_state).0);
+        // manual flags the derivations reject are dropped here, and reported by settle_win()
+        yaku.retain(|y| !YAKU_SITUATIONAL.contains(y) && !YAKU_FIRST_TURN.contains(y));
+        yaku.append_checked(&situational_yaku(game_state, seat_state).0);
+        yaku.append_checked(&first_turn_yaku(game_state, seat_state).0);
 
         match self {
             HandShape::Standard {melds, pair} => {yaku.append_checked(&find_yaku_standard(
@@ -175,6 +302,139 @@ impl FindYaku for HandShape {
 // functions //
 ///////////////
 
+const fn regular(han_closed: u8, han_open: Option<u8>, name: &'static str, kanji: &'static str, english: &'static str,
+    aliases: &'static [&'static str], description: &'static str) -> YakuInfo {
+    YakuInfo { han_closed, han_open, yakuman: 0, name, kanji, english, aliases, description }
+}
+
+const fn yakuman(multiplier: u8, closed_only: bool, name: &'static str, kanji: &'static str, english: &'static str,
+    aliases: &'static [&'static str], description: &'static str) -> YakuInfo {
+    let han = 13 * multiplier;
+    YakuInfo { han_closed: han, han_open: if closed_only { None } else { Some(han) }, yakuman: multiplier, name, kanji, english, aliases, description }
+}
+
+// Derives riichi, double riichi and ippatsu from seat_state.riichi, as far as the ruleset allows them.
+// Without a declaration, the manually supplied special yaku are used as they are.
+//
+// # Errors
+//
+// Returns ImpossibleYaku if the special yaku include a riichi yaku which the declaration rules out, ie ippatsu after
+// the window closed, or double riichi for a later declaration.
+pub fn find_riichi_yaku(game_state: &Game, seat_state: &Seat) -> Result<Vec<Yaku>, HandError> {
+    match riichi_yaku(game_state, seat_state) {
+        (_, Some(error)) => Err(error),
+        (derived, None) => Ok(derived),
+    }
+}
+
+// The riichi yaku the hand can have, along with any error in the manual flags.
+fn riichi_yaku(game_state: &Game, seat_state: &Seat) -> (Vec<Yaku>, Option<HandError>) {
+    let manual: Vec<Yaku> = seat_state.special_yaku.iter().flatten().filter(|y| YAKU_RIICHI.contains(y)).copied().collect();
+    let Some(riichi) = seat_state.riichi else { return (manual, None) };
+
+    let error = manual.iter().any(|y| match y {
+        Yaku::DoubleRiichi => !riichi.double,
+        Yaku::Ippatsu => !riichi.ippatsu,
+        _ => false,
+    }).then_some(HandError::ImpossibleYaku);
+
+    let ruleset = game_state.ruleset;
+    let mut derived = vec![if riichi.double && ruleset.allows_double_riichi() { Yaku::DoubleRiichi } else { Yaku::Riichi }];
+    if riichi.ippatsu && ruleset.allows_ippatsu() { derived.push(Yaku::Ippatsu) }
+    (derived, error)
+}
+
+// Derives tenho, chiho and renho from game_state.turn. Renho is only awarded if the ruleset plays it.
+// Without a turn, the manually supplied special yaku are used, as long as the seat and the win allow them.
+//
+// # Errors
+//
+// Returns ImpossibleYaku if the special yaku include a yaku the seat or the win could never have, ie tenho for a
+// non-dealer or chiho on a ron, or one which the turn rules out.
+pub fn find_first_turn_yaku(game_state: &Game, seat_state: &Seat) -> Result<Vec<Yaku>, HandError> {
+    match first_turn_yaku(game_state, seat_state) {
+        (_, Some(error)) => Err(error),
+        (derived, None) => Ok(derived),
+    }
+}
+
+// The first-turn yaku the hand can have, along with any error in the manual flags.
+fn first_turn_yaku(game_state: &Game, seat_state: &Seat) -> (Vec<Yaku>, Option<HandError>) {
+    let manual: Vec<Yaku> = seat_state.special_yaku.iter().flatten().filter(|y| YAKU_FIRST_TURN.contains(y)).copied().collect();
+    if manual.is_empty() && game_state.turn.is_none() { return (manual, None) }
+    let Some(latest_type) = seat_state.latest_type else { return (Vec::new(), Some(HandError::ValueError)) };
+    let dealer = seat_state.seat_wind == Wind::East;
+    // any meld, closed kans included, ends the seat's first go-around
+    let first_turn = seat_state.called_melds.as_ref().is_none_or(Vec::is_empty);
+
+    let possible = |yaku: &Yaku| first_turn && match yaku {
+        Yaku::Tenho => dealer && latest_type == TileType::Draw,
+        Yaku::Chiho => !dealer && latest_type == TileType::Draw,
+        Yaku::Renho => !dealer && latest_type == TileType::Call,
+        _ => false,
+    };
+    let mut derived: Vec<Yaku> = match game_state.turn {
+        None => manual.iter().filter(|y| possible(y)).copied().collect(),
+        // a seat's first draw comes after one discard per seat ahead of it
+        Some(turn) if first_turn && !turn.called => match latest_type {
+            TileType::Draw if turn.discards == seat_state.seat_wind as u8 => vec![if dealer { Yaku::Tenho } else { Yaku::Chiho }],
+            TileType::Call if !dealer && turn.discards <= seat_state.seat_wind as u8 => vec![Yaku::Renho],
+            _ => Vec::new(),
+        },
+        Some(_) => Vec::new(),
+    };
+    let error = manual.iter().any(|y| !derived.contains(y)).then_some(HandError::ImpossibleYaku);
+    if game_state.ruleset.renho_han() == 0 { derived.retain(|y| *y != Yaku::Renho) }
+    (derived, error)
+}
+
+// Derives haitei, houtei, rinshan and chankan from game_state.last_action and game_state.tiles_left.
+// Without a last action, the manually supplied special yaku are used, as long as the winning tile's type allows them.
+//
+// # Errors
+//
+// Returns ValueError if the win couldn't have followed the last action (ie a ron after a draw, or robbing a closed
+// kan without kokushi), or ImpossibleYaku if the special yaku include a situational yaku which the last action or
+// the winning tile's type rules out.
+pub fn find_situational_yaku(game_state: &Game, seat_state: &Seat) -> Result<Vec<Yaku>, HandError> {
+    match situational_yaku(game_state, seat_state) {
+        (_, Some(error)) => Err(error),
+        (derived, None) => Ok(derived),
+    }
+}
+
+// The situational yaku the hand can have, along with any error in the manual flags or the last action.
+fn situational_yaku(game_state: &Game, seat_state: &Seat) -> (Vec<Yaku>, Option<HandError>) {
+    let manual: Vec<Yaku> = seat_state.special_yaku.iter().flatten().filter(|y| YAKU_SITUATIONAL.contains(y)).copied().collect();
+    if manual.is_empty() && game_state.last_action.is_none() { return (manual, None) }
+    let Some(latest_type) = seat_state.latest_type else { return (Vec::new(), Some(HandError::ValueError)) };
+    let winner = seat_state.seat_wind;
+    // when the wall count isn't known, the last tile can still be claimed by hand
+    let last_tile = |yaku: Yaku| match game_state.tiles_left {
+        Some(left) => left == 0,
+        None => manual.contains(&yaku),
+    };
+
+    let derived: Vec<Yaku> = match (game_state.last_action, latest_type) {
+        (None, _) => manual.iter().filter(|y| match y {
+            Yaku::UnderSea => latest_type == TileType::Draw,
+            Yaku::AfterKan => latest_type != TileType::Call,
+            _ => latest_type == TileType::Call,
+        }).copied().collect(),
+        (Some(LastAction::Draw(wind)), TileType::Draw) if wind == winner => if last_tile(Yaku::UnderSea) { vec![Yaku::UnderSea] } else { Vec::new() },
+        (Some(LastAction::KanDraw(wind, _)), TileType::Kan) if wind == winner => vec![Yaku::AfterKan],
+        (Some(LastAction::Discard(wind)), TileType::Call) if wind != winner => if last_tile(Yaku::UnderRiver) { vec![Yaku::UnderRiver] } else { Vec::new() },
+        (Some(LastAction::Shouminkan(wind)), TileType::Call) if wind != winner => vec![Yaku::RobbedKan],
+        (Some(LastAction::Ankan(wind)), TileType::Call) if wind != winner && game_state.ruleset.allows_ankan_chankan()
+            && read_win(&seat_state.closed_tiles, &seat_state.called_melds, &seat_state.latest_tile)
+                .is_some_and(|hands| hands.iter().any(|h| matches!(h, HandShape::Kokushi(_)))) => Vec::new(),
+        _ => return (Vec::new(), Some(HandError::ValueError)),
+    };
+
+    let error = manual.iter().any(|y| !derived.contains(y)).then_some(HandError::ImpossibleYaku);
+    (derived, error)
+}
+
 // there are a lot of yaku to check for.
 pub fn find_yaku_standard(melds: &[Meld; 4], pair: Pair, win_type: WinType, game_state: &Game, seat_state: &Seat) -> Vec<Yaku> {
     let mut yaku: Vec<Yaku> = Vec::with_capacity(7);
@@ -216,14 +476,13 @@ pub fn find_yaku_standard(melds: &[Meld; 4], pair: Pair, win_type: WinType, game
             }
         },
         1 if check_sananko(&hand_trips, &hand_seqs, pair, win_type, win_tile) => yaku.push_checked(Yaku::Sananko),
-        4 if !pair.is_dragon() => { // check for pinfu
-            // for the pinfu wait to be valid, there must be one closed sequence where the winning tile wasn't in the center.
-            // ... but it can't be a one-sided edge wait.
-            if melds.iter().any(|m| !m.is_open && m.contains(&win_tile) && m.tiles[1].is_some_and(|t| t != win_tile) )
-                // and the pair can't be the seat or round wind, because those both give fu.
-                && pair.tile().wind() != Some(game_state.round_wind) && pair.tile().wind() != Some(seat_state.seat_wind)
-                { yaku.push_checked(Yaku::Pinfu) }
-        },
+        // check for pinfu
+        4 if !pair.is_dragon()
+            // the winning tile has to be readable as a ryanmen wait
+            && winning_tile_shapes(melds, &pair, &win_tile).contains(&WaitShape::Ryanmen)
+            // and the pair can't be the seat or round wind, because those both give fu.
+            && pair.tile().wind() != Some(game_state.round_wind) && pair.tile().wind() != Some(seat_state.seat_wind)
+            => yaku.push_checked(Yaku::Pinfu),
         _ => (),
     }
 
@@ -361,7 +620,7 @@ fn check_ittsuu(melds: &[&Meld], suits: usize) -> bool {
 // doesn't filter input.
 fn count_ipeiko(melds: &[&Meld]) -> u8 {
     let mut count: u8 = 0;
-    let mut iter = melds.into_iter().circular_tuple_windows::<(_,_)>();
+    let mut iter = melds.iter().circular_tuple_windows::<(_,_)>();
     while let Some((a, b)) = iter.next() {
         if a == b {
             count += 1;
@@ -375,7 +634,7 @@ fn count_ipeiko(melds: &[&Meld]) -> u8 {
 // doesn't filter input.
 fn check_sanshoku_doujun(melds: &[&Meld]) -> bool {
     if melds.len() >= 3 {
-        return melds.into_iter()
+        return melds.iter()
         .map(|m| m.tiles[0].unwrap())
         .circular_tuple_windows::<(_,_,_)>()
         .any(|(a, b, c)| {
@@ -389,7 +648,7 @@ fn check_sanshoku_doujun(melds: &[&Meld]) -> bool {
 // doesn't check whether they have different suits.
 // filters for numbered trips.
 fn check_sanshoku_douko(melds: &[&Meld]) -> bool {
-    let filtered = melds.into_iter().filter(|m| m.is_numbered()).collect::<Vec<_>>();
+    let filtered = melds.iter().filter(|m| m.is_numbered()).collect::<Vec<_>>();
     if filtered.len() >= 3 {
         return filtered.into_iter()
         .map(|m| m.number().unwrap())
@@ -402,12 +661,12 @@ fn check_sanshoku_douko(melds: &[&Meld]) -> bool {
 // assumes that only one suit is present; will return false positives otherwise.
 // will fail if given honor tiles.
 fn check_churenpoto(tiles: &[Tile]) -> bool {
-    if tiles.into_iter().collect::<HashSet<&Tile>>().len() == 9 {
+    if tiles.iter().collect::<HashSet<&Tile>>().len() == 9 {
         // naive approach
         // TODO: refactor, test
         let mut arr = [0; 9];
 
-        tiles.into_iter().for_each(|t| arr[(t.number().unwrap() - 1) as usize] += 1 );
+        tiles.iter().for_each(|t| arr[(t.number().unwrap() - 1) as usize] += 1 );
 
         if [0,8].into_iter().all(|n| matches!(arr[n as usize], 3|4))
         && arr[1..=7].iter().all(|n| matches!(arr[*n as usize], 1|2)) { return true }
@@ -416,12 +675,12 @@ fn check_churenpoto(tiles: &[Tile]) -> bool {
 
 // expects tiles to be a sorted and deduped Vec<Tile>; misbehaves otherwise.
 fn check_shosushi(tiles: &Vec<&Tile>) -> bool {
-    tiles.into_iter().fold(0, |acc, t| if t.is_wind() { acc + 1 } else { acc } ) == 4
+    tiles.iter().fold(0, |acc, t| if t.is_wind() { acc + 1 } else { acc } ) == 4
 }
 
 // expects tiles to be a sorted and deduped Vec<Tile>; misbehaves otherwise.
 fn check_shosangen(tiles: &Vec<&Tile>) -> bool {
-    tiles.into_iter().fold(0, |acc, t| if t.is_dragon() { acc + 1 } else { acc } ) == 3
+    tiles.iter().fold(0, |acc, t| if t.is_dragon() { acc + 1 } else { acc } ) == 3
 }
 
 fn three_in_common<T: std::cmp::PartialEq>(a: &T, b: &T, c: &T, d: &T) -> bool {
@@ -461,32 +720,33 @@ mod tests {
     fn basic_yaku_tests(){
         let mut game = Game{
             ruleset: RiichiRuleset::Default, round_wind: Wind::East,
-            dora_markers: None, ura_dora_markers: None, repeats: 0 };
+            dora_markers: None, ura_dora_markers: None, repeats: 0,
+            last_action: None, tiles_left: None, turn: None };
         let mut seat = Seat{
             closed_tiles: "m2,m3,p5,p6,p7,p4,p5,p6,s3,s4,s5,m7,m7".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("m4".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("m4".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::ClosedTsumo, Yaku::Tanyao, Yaku::Pinfu]);
 
         seat = Seat{
             closed_tiles: "m2,m2,m3,m3,m4,s2,s3,s4,p2,p3,p4,p9,p9".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("m4".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("m4".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Pinfu, Yaku::Ipeiko, Yaku::SanshokuDoujun]);
 
         seat = Seat{
             closed_tiles: "m2,m2,m3,m3,m4,s2,s3,s4,p2,p2,p2,p8,p8".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("m4".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("m4".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::Tanyao, Yaku::Ipeiko]);
 
         seat = Seat{
             closed_tiles: "p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("p9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("p9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         let mut hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::ClosedTsumo, Yaku::Pinfu, Yaku::Ittsuu]);
@@ -496,7 +756,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "m2,m2,m3,m3,p3,p3,p5,p5,s6,s6,s7,s8,s8".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("s7".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("s7".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Chiitoi, Yaku::ClosedTsumo, Yaku::Tanyao]);
@@ -506,7 +766,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "m5,m6,m7,m8,m8,m8,m3".to_tiles().unwrap(),
             called_melds: "p8,p8,p8|m2,m2,m2".to_calls().ok(), seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("m3".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("m3".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Tanyao]);
@@ -516,7 +776,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "p2,p2,we,we".to_tiles().unwrap(),
             called_melds: "m8,m8,m8|p3,p3,p3|s8,s8,s8".to_calls().ok(), seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("p2".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("p2".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Toitoi]);
@@ -526,7 +786,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "p2,p3,p3,p4,p4,p5,p5".to_tiles().unwrap(),
             called_melds: "s8,s8,s8|!s7,s7,s7,s7".to_calls().ok(), seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("p2".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("p2".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Tanyao]);
@@ -536,7 +796,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "p2,p3,p3,p4,p4,p5,p5,s8,s8,s8".to_tiles().unwrap(),
             called_melds: "!s7,s7,s7,s7".to_calls().ok(), seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("p2".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("p2".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::ClosedTsumo, Yaku::Tanyao, Yaku::Ipeiko]);
@@ -546,7 +806,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "m1,m2,m4,m4,m5,m6,m7,s8,s8,s8".to_tiles().unwrap(),
             called_melds: "we,we,we,we".to_calls().ok(), seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("m3".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("m3".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Yakuhai(2)]);
@@ -554,7 +814,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "m7,m9,m9,m9,s9,s9,s9".to_tiles().unwrap(),
             called_melds: "ws,ws,ws,ws|s9,s9,s9".to_calls().ok(), seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("m8".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("m8".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Chanta]);
@@ -562,7 +822,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "s2,s3,s1,s3,s2,p7,p8,p9,p1,p1".to_tiles().unwrap(),
             called_melds: "m1,m2,m3".to_calls().ok(), seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("s1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("s1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Junchan, Yaku::Pinfu]);
@@ -570,18 +830,19 @@ mod tests {
         seat = Seat{
             closed_tiles: "s1,s1,p1,p1,p3,p3,p3".to_tiles().unwrap(),
             called_melds: "we,we,we,we|wn,wn,wn,wn".to_calls().ok(), seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("s1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None
+            latest_tile: Some("s1".to_tile().unwrap()), latest_type: Some(TileType::Call), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Toitoi, Yaku::Yakuhai(1)]);
 
         game = Game{
             ruleset: RiichiRuleset::JPML2022, round_wind: Wind::East,
-            dora_markers: None, ura_dora_markers: None, repeats: 0 };
+            dora_markers: None, ura_dora_markers: None, repeats: 0,
+            last_action: None, tiles_left: None, turn: None };
         seat = Seat{
             closed_tiles: "p1,p2,p3,p4,p4,p4,p5,p6,p7,p8,s2,s3,s4".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("p9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("p9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::ClosedTsumo, Yaku::Pinfu, Yaku::Ittsuu]);
@@ -591,7 +852,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "we,we,ws,ws,ws,p8,p8".to_tiles().unwrap(),
             called_melds: "wn,wn,wn|ww,ww,ww".to_calls().ok(), seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("p8".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("p8".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Shosushi]);
@@ -599,7 +860,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "we,we,ws,ws,ws,p8,p8".to_tiles().unwrap(),
             called_melds: "wn,wn,wn|ww,ww,ww".to_calls().ok(), seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("we".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("we".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Daisushi]);
@@ -607,7 +868,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "dr,dr,dg,dg,dg,p9,p9".to_tiles().unwrap(),
             called_melds: "dw,dw,dw|p8,p8,p8".to_calls().ok(), seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("p9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("p9".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert!(hand.yaku().contains(&Yaku::Shosangen));
@@ -615,7 +876,7 @@ mod tests {
         seat = Seat{
             closed_tiles: "dr,dr,dg,dg,dg,p9,p9".to_tiles().unwrap(),
             called_melds: "dw,dw,dw|p8,p8,p8".to_calls().ok(), seat_wind: Wind::East, special_yaku: None,
-            latest_tile: Some("dr".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("dr".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         hand = Hand::new(game.clone(), seat);
         assert_eq!(hand.yaku(), &vec![Yaku::Daisangen]);
@@ -625,26 +886,187 @@ mod tests {
     fn test_churenpoto() {
         let game = Game{
             ruleset: RiichiRuleset::Default, round_wind: Wind::East,
-            dora_markers: None, ura_dora_markers: None, repeats: 0 };
+            dora_markers: None, ura_dora_markers: None, repeats: 0,
+            last_action: None, tiles_left: None, turn: None };
         let mut seat = Seat{
             closed_tiles: "p1,p1,p1,p2,p4,p5,p6,p7,p8,p9,p9,p9,p9".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("p3".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("p3".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::ChurenPoto]);
 
         seat = Seat{
             closed_tiles: "p1,p1,p1,p2,p3,p4,p5,p6,p7,p8,p9,p9,p9".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("p2".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("p2".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::ChurenPoto, Yaku::SpecialWait]);
 
         seat = Seat{
             closed_tiles: "p1,p1,p1,p2,p4,p5,p5,p5,p7,p8,p9,p9,p9".to_tiles().unwrap(),
             called_melds: None, seat_wind: Wind::South, special_yaku: None,
-            latest_tile: Some("p3".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None
+            latest_tile: Some("p3".to_tile().unwrap()), latest_type: Some(TileType::Draw), all_tiles: None, discards: None, riichi: None
         };
         assert_eq!(Hand::new(game.clone(), seat).yaku(), &vec![Yaku::ClosedTsumo, Yaku::Chinitsu]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_yaku_metadata() {
+        use crate::conversions::ConvertStrings;
+        use crate::scoring::CountHan;
+
+        assert_eq!(Yaku::Chinitsu.info().han_open, Some(5));
+        assert_eq!(Yaku::Pinfu.info().han_open, None);
+        assert_eq!(Yaku::Yakuhai(2).info().han_closed, 2);
+        assert_eq!((Yaku::Daisushi.info().yakuman, Yaku::Tenho.info().english), (2, "Blessing of heaven"));
+        // the table is what's counted, apart from the ruleset's exceptions
+        assert_eq!(vec![Yaku::Chinitsu, Yaku::Pinfu].han(true, RiichiRuleset::Default), 5);
+        assert_eq!(vec![Yaku::Daisushi, Yaku::Tsuiso].han(true, RiichiRuleset::MajSoul), 39);
+        assert_eq!(vec![Yaku::Tanyao].han(true, RiichiRuleset::Default), 1);
+
+        // every name belongs to one yaku, and parses back to it
+        let mut names: HashSet<String> = HashSet::new();
+        for yaku in ALL_YAKU {
+            let info = yaku.info();
+            assert_eq!(YAKUMAN.contains(&yaku), info.yakuman > 0);
+            let own: HashSet<String> = [info.name, info.english].iter().chain(info.aliases).map(|name| {
+                assert_eq!(name.to_yaku(), Ok(yaku));
+                name.to_lowercase().replace(' ', "")
+            }).collect();
+            assert!(names.is_disjoint(&own), "{yaku:?} shares a name");
+            names.extend(own);
+        }
+    }
+
+    #[test]
+    fn test_situational_yaku() {
+        use crate::state::{SeatHelper, GameHelper};
+        use crate::hand::MeldKind;
+        use crate::scoring::settle_win;
+
+        let game = |action: LastAction, tiles_left: Option<u8>| Game { last_action: Some(action), tiles_left,
+            ..Game::new(RiichiRuleset::Default, Wind::East, 0, None, None) };
+        let seat = |latest: &str, latest_type: TileType, special: &str| Seat::new("m2,m3,m4,p5,p6,p7,s3,s4,s5,dr,dr,m7,m8".to_tiles().unwrap(),
+            None, Wind::South, latest.to_tile().ok(), Some(latest_type), special.to_yaku_vec().ok());
+        let situational = |game: &Game, seat: &Seat| find_situational_yaku(game, seat);
+
+        // derived from the last action alone
+        assert_eq!(situational(&game(LastAction::Draw(Wind::South), Some(0)), &seat("m9", TileType::Draw, "")), Ok(vec![Yaku::UnderSea]));
+        assert_eq!(situational(&game(LastAction::Draw(Wind::South), Some(3)), &seat("m9", TileType::Draw, "")), Ok(Vec::new()));
+        assert_eq!(situational(&game(LastAction::Discard(Wind::West), Some(0)), &seat("m9", TileType::Call, "")), Ok(vec![Yaku::UnderRiver]));
+        assert_eq!(situational(&game(LastAction::KanDraw(Wind::South, MeldKind::Ankan), Some(0)), &seat("m9", TileType::Kan, "")), Ok(vec![Yaku::AfterKan]));
+        assert_eq!(situational(&game(LastAction::Shouminkan(Wind::West), Some(0)), &seat("m9", TileType::Call, "")), Ok(vec![Yaku::RobbedKan]));
+        assert!(Hand::new(game(LastAction::Shouminkan(Wind::West), None), seat("m9", TileType::Call, "")).yaku().contains(&Yaku::RobbedKan));
+        // without a wall count, the last tile is taken on trust
+        assert_eq!(situational(&game(LastAction::Draw(Wind::South), None), &seat("m9", TileType::Draw, "haitei")), Ok(vec![Yaku::UnderSea]));
+        // and without a last action, everything is
+        assert_eq!(situational(&Game::new(RiichiRuleset::Default, Wind::East, 0, None, None), &seat("m9", TileType::Call, "chankan")), Ok(vec![Yaku::RobbedKan]));
+
+        // flags or tile types which contradict the last action
+        assert_eq!(situational(&game(LastAction::Draw(Wind::South), Some(3)), &seat("m9", TileType::Draw, "haitei")), Err(HandError::ImpossibleYaku));
+        assert_eq!(situational(&game(LastAction::Discard(Wind::West), None), &seat("m9", TileType::Call, "chankan")), Err(HandError::ImpossibleYaku));
+        assert_eq!(situational(&game(LastAction::Draw(Wind::South), None), &seat("m9", TileType::Kan, "rinshan")), Err(HandError::ValueError));
+        assert_eq!(situational(&Game::new(RiichiRuleset::Default, Wind::East, 0, None, None), &seat("m9", TileType::Call, "rinshan")), Err(HandError::ImpossibleYaku));
+        // and are never awarded, even where nothing checks for errors
+        assert_eq!(Hand::new(game(LastAction::Draw(Wind::South), Some(3)), seat("m9", TileType::Draw, "haitei")).yaku(), &vec![Yaku::ClosedTsumo]);
+        assert_eq!(Hand::new(game(LastAction::Discard(Wind::West), None), seat("m9", TileType::Call, "chankan,riichi")).yaku(), &vec![Yaku::Riichi]);
+        assert_eq!(Hand::new(game(LastAction::Draw(Wind::South), Some(3)), seat("m9", TileType::Draw, "rinshan")).yaku(), &vec![Yaku::ClosedTsumo]);
+        assert_eq!(situational(&game(LastAction::Discard(Wind::South), None), &seat("m9", TileType::Call, "")), Err(HandError::ValueError));
+        assert_eq!(settle_win(&game(LastAction::Draw(Wind::West), None), &seat("m9", TileType::Draw, ""), None, 0), Err(HandError::ValueError));
+
+        // a closed kan can only be robbed for kokushi
+        let kokushi = Seat::new("m1,m9,p1,p9,s1,s9,dw,dr,dg,we,ws,wn,wn".to_tiles().unwrap(), None, Wind::South, "ww".to_tile().ok(), Some(TileType::Call), None);
+        assert_eq!(situational(&game(LastAction::Ankan(Wind::West), None), &kokushi), Ok(Vec::new()));
+        assert_eq!(situational(&Game { ruleset: RiichiRuleset::EMA2016, ..game(LastAction::Ankan(Wind::West), None) }, &kokushi), Err(HandError::ValueError));
+        assert_eq!(situational(&game(LastAction::Ankan(Wind::West), None), &seat("m9", TileType::Call, "")), Err(HandError::ValueError));
+    }
+
+    #[test]
+    fn test_riichi_yaku() {
+        use crate::state::{SeatHelper, GameHelper, RiichiState};
+
+        let game = Game::new(RiichiRuleset::Default, Wind::East, 0, None, None);
+        let seat = |special: &str| Seat::new("m2,m3,m4,p5,p6,p7,s3,s4,s5,dr,dr,m7,m8".to_tiles().unwrap(),
+            None, Wind::South, "m9".to_tile().ok(), Some(TileType::Call), special.to_yaku_vec().ok());
+        let discard = "we".to_tile().unwrap();
+
+        // declaring on the first discard, before any call, is a double riichi
+        let mut declared = seat("");
+        assert_eq!(declared.declare_riichi(0, discard, true), Ok(()));
+        assert_eq!(find_riichi_yaku(&game, &declared), Ok(vec![Yaku::DoubleRiichi, Yaku::Ippatsu]));
+        assert_eq!(declared.declare_riichi(1, discard, true), Err(HandError::ValueError));
+        declared.end_ippatsu();
+        assert_eq!(find_riichi_yaku(&game, &declared), Ok(vec![Yaku::DoubleRiichi]));
+        let mut late = seat("");
+        late.declare_riichi(0, discard, false).unwrap();
+        assert_eq!(find_riichi_yaku(&game, &late), Ok(vec![Yaku::Riichi, Yaku::Ippatsu]));
+        assert_eq!(find_riichi_yaku(&Game { ruleset: RiichiRuleset::JPML2022, ..game.clone() }, &late), Ok(vec![Yaku::Riichi]));
+        assert!(Hand::new(game.clone(), late).yaku().contains(&Yaku::Ippatsu));
+
+        // manual flags are only used without a declaration, and mustn't contradict one
+        assert_eq!(find_riichi_yaku(&game, &seat("riichi,ippatsu")), Ok(vec![Yaku::Riichi, Yaku::Ippatsu]));
+        let closed = Seat { riichi: declared.riichi, ..seat("ippatsu") };
+        assert_eq!(find_riichi_yaku(&game, &closed), Err(HandError::ImpossibleYaku));
+        let mut riichi = seat("doubleriichi");
+        riichi.declare_riichi(3, discard, true).unwrap();
+        assert_eq!(find_riichi_yaku(&game, &riichi), Err(HandError::ImpossibleYaku));
+        // the declaration still scores
+        assert_eq!(Hand::new(game.clone(), closed).yaku(), &vec![Yaku::DoubleRiichi]);
+        assert_eq!(Hand::new(game.clone(), riichi).yaku(), &vec![Yaku::Riichi, Yaku::Ippatsu]);
+
+        // open or not tenpai hands can't declare
+        let mut open = Seat::new("m2,m3,m4,p5,p6,p7,dr,dr,m7,m8".to_tiles().unwrap(), "s3,s4,s5".to_calls().ok(), Wind::South, None, None, None);
+        assert_eq!(open.declare_riichi(2, discard, true), Err(HandError::ValueError));
+        let mut noten = Seat::new("m2,m3,m4,p5,p6,p7,s3,s4,s5,dr,dg,m7,m8".to_tiles().unwrap(), None, Wind::South, None, None, None);
+        assert_eq!(noten.declare_riichi(2, discard, true), Err(HandError::ValueError));
+    }
+
+    #[test]
+    fn test_first_turn_yaku() {
+        use crate::state::{SeatHelper, GameHelper, Turn};
+        use crate::scoring::settle_win;
+
+        let game = |discards: u8, called: bool| Game { turn: Some(Turn { discards, called }),
+            ..Game::new(RiichiRuleset::Default, Wind::East, 0, None, None) };
+        let seat = |wind: Wind, latest_type: TileType, special: &str| Seat::new("m2,m3,m4,p5,p6,p7,s3,s4,s5,dr,dr,m7,m8".to_tiles().unwrap(),
+            None, wind, "m9".to_tile().ok(), Some(latest_type), special.to_yaku_vec().ok());
+        let first_turn = |game: &Game, seat: &Seat| find_first_turn_yaku(game, seat);
+
+        // each seat's first draw, with nobody having called
+        assert_eq!(first_turn(&game(0, false), &seat(Wind::East, TileType::Draw, "")), Ok(vec![Yaku::Tenho]));
+        assert_eq!(first_turn(&game(2, false), &seat(Wind::West, TileType::Draw, "")), Ok(vec![Yaku::Chiho]));
+        assert_eq!(first_turn(&game(2, true), &seat(Wind::West, TileType::Draw, "")), Ok(Vec::new()));
+        assert_eq!(first_turn(&game(6, false), &seat(Wind::West, TileType::Draw, "")), Ok(Vec::new()));
+        assert_eq!(Hand::new(game(3, false), seat(Wind::North, TileType::Draw, "")).yaku(), &vec![Yaku::Chiho]);
+        // a closed kan ends the seat's first go-around too
+        let kan = Seat { called_melds: "!s1,s1,s1,s1".to_calls().ok(), closed_tiles: "m2,m3,m4,p5,p6,p7,dr,dr,m7,m8".to_tiles().unwrap(),
+            ..seat(Wind::South, TileType::Draw, "") };
+        assert_eq!(first_turn(&game(1, false), &kan), Ok(Vec::new()));
+
+        // renho is only awarded by rulesets which play it, but is still checked against the turn
+        let ema = |discards: u8| Game { ruleset: RiichiRuleset::EMA2016, ..game(discards, false) };
+        assert_eq!(first_turn(&game(2, false), &seat(Wind::West, TileType::Call, "")), Ok(vec![Yaku::Renho]));
+        assert_eq!(first_turn(&game(1, false), &seat(Wind::West, TileType::Call, "renho")), Ok(vec![Yaku::Renho]));
+        assert_eq!(first_turn(&ema(2), &seat(Wind::West, TileType::Call, "")), Ok(Vec::new()));
+        assert_eq!(first_turn(&ema(2), &seat(Wind::West, TileType::Call, "renho")), Ok(Vec::new()));
+        assert_eq!(first_turn(&ema(3), &seat(Wind::West, TileType::Call, "renho")), Err(HandError::ImpossibleYaku));
+        // and scored as a mangan where it is
+        assert_eq!(settle_win(&game(2, false), &seat(Wind::West, TileType::Call, ""), Some(Wind::South), 0), Ok([0, -8000, 8000, 0]));
+        assert_eq!(settle_win(&ema(2), &seat(Wind::West, TileType::Call, ""), Some(Wind::South), 0), Err(HandError::NoYaku));
+
+        // mistaken flags, with or without a turn
+        let unknown = Game::new(RiichiRuleset::Default, Wind::East, 0, None, None);
+        assert_eq!(first_turn(&unknown, &seat(Wind::East, TileType::Draw, "tenho")), Ok(vec![Yaku::Tenho]));
+        assert_eq!(first_turn(&unknown, &seat(Wind::South, TileType::Draw, "tenho")), Err(HandError::ImpossibleYaku));
+        // and are never awarded, even where nothing checks for errors
+        assert_eq!(Hand::new(unknown.clone(), seat(Wind::South, TileType::Draw, "tenho")).yaku(), &vec![Yaku::ClosedTsumo]);
+        assert_eq!(Hand::new(game(5, false), seat(Wind::South, TileType::Draw, "chiho")).yaku(), &vec![Yaku::ClosedTsumo]);
+        assert_eq!(first_turn(&unknown, &seat(Wind::East, TileType::Draw, "chiho")), Err(HandError::ImpossibleYaku));
+        assert_eq!(first_turn(&unknown, &seat(Wind::South, TileType::Call, "chiho")), Err(HandError::ImpossibleYaku));
+        assert_eq!(first_turn(&game(5, false), &seat(Wind::South, TileType::Draw, "chiho")), Err(HandError::ImpossibleYaku));
+        assert_eq!(settle_win(&unknown, &seat(Wind::South, TileType::Draw, "tenho"), None, 0), Err(HandError::ImpossibleYaku));
+        let mut blessed = vec![Yaku::Tenho];
+        blessed.push_checked(Yaku::Chiho);
+        assert_eq!(blessed, vec![Yaku::Tenho]);
+    }
+}
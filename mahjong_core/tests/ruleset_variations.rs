@@ -1,3 +1,5 @@
+extern crate mahjong_core as fivesou;
+
 use fivesou::scoring::Payment;
 
 #[test]
@@ -0,0 +1,120 @@
+/*
+Agents play seats in the self-play simulation. The simulation asks an agent for its discard each turn, and whether it
+wants to riichi, pon or kan when it's allowed to; wins are always taken when the hand has a yaku.
+*/
+
+use mahjong_core::tiles::{Tile, Wind, TileId, PhysicalTile, TileIs, ToHistogram, Histogram};
+use mahjong_core::hand::Meld;
+use mahjong_core::state::Discard;
+use mahjong_core::agari::is_agari;
+use crate::simulation::Rng;
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+// What a seat can see when it's asked for a decision. Per-seat arrays are indexed by seat wind (east, south, west, north).
+// The built-in agents don't look at all of it.
+#[allow(dead_code)]
+pub struct View<'a> {
+    pub hand: &'a [PhysicalTile],       // closed tiles, including a tile just drawn
+    pub melds: &'a [Meld],
+    pub seat_wind: Wind,
+    pub round_wind: Wind,
+    pub dora_markers: &'a [Tile],
+    pub discards: [&'a [Discard]; 4],
+    pub riichi: [bool; 4],
+    pub scores: [i32; 4],
+    pub tiles_left: usize,              // draws left in the live wall
+}
+
+// Keeps tenpai with the most waits when it can, and otherwise throws the tile with the fewest neighbors.
+// Declares riichi whenever allowed, pons yakuhai, and kans honors.
+pub struct Efficient;
+
+// Throws a random tile. Never declares riichi or calls.
+pub struct Random(Rng);
+
+////////////
+// traits //
+////////////
+
+pub trait Agent: Send {
+    // Chooses a tile from view.hand to discard.
+    fn discard(&mut self, view: &View) -> PhysicalTile;
+    // Asked after a discard which leaves the hand tenpai, when the seat is allowed to riichi.
+    fn declare_riichi(&mut self, view: &View) -> bool;
+    // Asked when another seat discards a tile that the agent holds a pair of.
+    fn call_pon(&mut self, view: &View, tile: Tile) -> bool;
+    // Asked when the agent draws a fourth copy of a tile, or another seat discards a tile that the agent holds three of.
+    fn call_kan(&mut self, view: &View, tile: Tile) -> bool;
+}
+
+///////////////
+// functions //
+///////////////
+
+pub fn make_agent(name: &str, seed: u64) -> Option<Box<dyn Agent>> {
+    match name {
+        "efficient" => Some(Box::new(Efficient)),
+        "random" => Some(Box::new(Random(Rng::new(seed)))),
+        _ => None,
+    }
+}
+
+// Returns how many kinds of tile would complete a hand of 3n+1 tiles.
+pub fn count_waits(hand: &Histogram) -> usize {
+    (0..34).filter(|id| hand[*id] < 4).filter(|id| {
+        let mut complete = *hand;
+        complete[*id] += 1;
+        is_agari(&complete)
+    }).count()
+}
+
+fn is_yakuhai(tile: &Tile, view: &View) -> bool {
+    tile.is_dragon() || tile.wind().is_some_and(|w| w == view.seat_wind || w == view.round_wind)
+}
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl Agent for Efficient {
+    fn discard(&mut self, view: &View) -> PhysicalTile {
+        let hand = view.hand.to_histogram();
+        let kinds: Vec<usize> = (0..34).filter(|id| hand[*id] > 0).collect();
+
+        let waits = |id: usize| { let mut rest = hand; rest[id] -= 1; count_waits(&rest) };
+        let chosen = match kinds.iter().map(|id| (waits(*id), *id)).filter(|(w, _)| *w > 0).max_by_key(|(w, id)| (*w, usize::MAX - id)) {
+            Some((_, id)) => id,
+            None => *kinds.iter().min_by_key(|id| {
+                let id = **id;
+                let tile = Tile::from(TileId::new(id as u8).expect("ids are under 34"));
+                let copies = i32::from(hand[id]) - 1;
+                let neighbors = if id < 27 {
+                    let near = |offset: i32| {
+                        let number = (id % 9) as i32 + offset;
+                        i32::from((0..9).contains(&number) && hand[id - id % 9 + number as usize] > 0)
+                    };
+                    2 * (near(-1) + near(1)) + near(-2) + near(2)
+                } else if is_yakuhai(&tile, view) && copies > 0 { 2 } else { 0 };
+                // honors go first, then terminals, then everything else
+                (copies * 3 + neighbors, if tile.is_honor() { 0 } else if tile.is_terminal() { 1 } else { 2 }, id)
+            }).expect("a hand always has a tile to discard"),
+        };
+
+        // throw a regular five before a red one
+        let matching = |red: bool| view.hand.iter().find(|t| t.kind().index() == chosen && t.is_red() == red);
+        *matching(false).or_else(|| matching(true)).expect("the chosen tile is in hand")
+    }
+    fn declare_riichi(&mut self, _view: &View) -> bool { true }
+    fn call_pon(&mut self, view: &View, tile: Tile) -> bool { is_yakuhai(&tile, view) }
+    fn call_kan(&mut self, _view: &View, tile: Tile) -> bool { tile.is_honor() }
+}
+
+impl Agent for Random {
+    fn discard(&mut self, view: &View) -> PhysicalTile { view.hand[self.0.below(view.hand.len())] }
+    fn declare_riichi(&mut self, _view: &View) -> bool { false }
+    fn call_pon(&mut self, _view: &View, _tile: Tile) -> bool { false }
+    fn call_kan(&mut self, _view: &View, _tile: Tile) -> bool { false }
+}
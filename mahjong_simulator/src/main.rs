@@ -1,3 +1,96 @@
+mod agents;
+mod majsoul;
+mod mjai;
+mod simulation;
+mod tenhou_json;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+use std::thread;
+use mahjong_core::tenhou::read_mjlog;
+use mahjong_core::rulesets::RiichiRuleset;
+use mahjong_core::conversions::ConvertStrings;
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        // converts a Tenhou mjlog into the JSON format used by Tenhou's replay viewer
+        Some("tenhou-json") => {
+            let Some(path) = args.get(2) else { return eprintln!("usage: simulator tenhou-json <mjlog>") };
+            let log = match fs::read_to_string(path) {
+                Ok(log) => log,
+                Err(e) => { eprintln!("{path}: couldn't read mjlog ({e})"); process::exit(1) },
+            };
+            let events = match read_mjlog(&log, RiichiRuleset::Default) {
+                Ok(events) => events,
+                Err(e) => { eprintln!("{path}: couldn't parse mjlog ({e:?})"); process::exit(1) },
+            };
+            match tenhou_json::write_tenhou_json(&events, ["", "", "", ""]) {
+                Ok(json) => println!("{json}"),
+                Err(e) => { eprintln!("{path}: couldn't score mjlog ({e:?})"); process::exit(1) },
+            }
+        },
+        // acts as the rules and scoring backend for mjai bots, over stdin/stdout
+        Some("mjai") => {
+            let ruleset = args.get(2).map_or(RiichiRuleset::Default, |r| r.to_ruleset().unwrap_or(RiichiRuleset::Default));
+            mjai::run(io::stdin().lock(), io::stdout().lock(), ruleset).expect("stdin and stdout should be available");
+        },
+        // rescores every round of Mahjong Soul paipu files under the MajSoul ruleset, reporting any that disagree
+        Some("majsoul-check") => {
+            let mut mismatches = 0;
+            for path in &args[2..] {
+                let json = fs::read_to_string(path).expect("paipu should be readable");
+                let rounds = match majsoul::read_paipu(&json) {
+                    Ok(rounds) => rounds,
+                    Err(e) => { mismatches += 1; println!("{path}: couldn't read paipu ({e:?})"); continue },
+                };
+                for (index, round) in rounds.iter().enumerate() {
+                    match majsoul::rescore(round) {
+                        Ok(points) if points == majsoul::recorded(round) => (),
+                        Ok(points) => { mismatches += 1; println!("{path} round {index}: recorded {:?}, scored {points:?}", majsoul::recorded(round)) },
+                        Err(e) => { mismatches += 1; println!("{path} round {index}: couldn't score ({e:?})") },
+                    }
+                }
+            }
+            if mismatches > 0 { process::exit(1) }
+        },
+        // plays seeded self-play games across threads and prints each agent's statistics as JSON,
+        // optionally writing each game to <dir>/game-<index>.json in the format used by Tenhou's replay viewer
+        Some("simulate") => {
+            let usage = "usage: simulator simulate <games> <seed> [threads] [agent,agent,agent,agent] [ruleset] [--log <dir>]";
+            let mut args = args;
+            let log_dir = match args.iter().position(|a| a == "--log") {
+                Some(index) if index + 1 < args.len() => Some(args.drain(index..=index + 1).nth(1).expect("the flag has a value")),
+                Some(_) => return eprintln!("{usage}"),
+                None => None,
+            };
+            let (Some(games), Some(seed)) = (args.get(2).and_then(|g| g.parse().ok()), args.get(3).and_then(|s| s.parse().ok())) else { return eprintln!("{usage}") };
+            let threads = args.get(4).and_then(|t| t.parse().ok())
+                .unwrap_or_else(|| thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get));
+            let names: Vec<String> = args.get(5).map_or("efficient,efficient,efficient,efficient", String::as_str).split(',').map(String::from).collect();
+            let Ok(agents) = <[String; 4]>::try_from(names) else { return eprintln!("{usage}") };
+            let ruleset = args.get(6).map_or(RiichiRuleset::Default, |r| r.to_ruleset().unwrap_or(RiichiRuleset::Default));
+
+            let config = simulation::Simulation { games, seed, threads, ruleset, agents };
+            match simulation::simulate(&config) {
+                Ok(report) => println!("{}", serde_json::to_string(&report).expect("reports should serialize")),
+                Err(e) => { eprintln!("couldn't simulate ({e:?})"); process::exit(1) },
+            }
+            if let Some(dir) = log_dir {
+                fs::create_dir_all(&dir).expect("log directory should be writable");
+                for index in 0..games {
+                    let json = simulation::log_game(&config, index)
+                        .and_then(|log| tenhou_json::write_tenhou_json(&log.events, log.names.each_ref().map(String::as_str)));
+                    match json {
+                        Ok(json) => fs::write(format!("{dir}/game-{index}.json"), json).expect("log directory should be writable"),
+                        Err(e) => { eprintln!("couldn't log game {index} ({e:?})"); process::exit(1) },
+                    }
+                }
+            }
+        },
+        _ => println!("Hello, world!"),
+    }
 }
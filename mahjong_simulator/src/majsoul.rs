@@ -0,0 +1,403 @@
+/*
+Reads Mahjong Soul game records (paipu), as exported to JSON, so recorded hands can be rescored under
+RiichiRuleset::MajSoul and checked against the recorded point changes.
+
+A record is a list of {"name": ".lq.RecordNewRound", "data": {...}} entries, found under "records", "data.records",
+or "data.actions". Tiles are written as "1m", "0p" (a red five), or "1z"-"7z" (east south west north white green red).
+Seats are indexed 0-3 for the whole game; seat winds are derived from the dealer ("ju") of each round.
+*/
+
+use serde_json::Value;
+use mahjong_core::tiles::{Tile, Suit, Dragon, Wind};
+use mahjong_core::hand::{Meld, MeldKind, MeldIs};
+use mahjong_core::state::{Game, Seat, Discard, TileType, SeatHelper};
+use mahjong_core::yaku::Yaku;
+use mahjong_core::scoring::{RonSettlement, settle_win, settle_ron};
+use mahjong_core::ryuukyoku::{Ryuukyoku, AbortiveDraw, exhaustive_draw};
+use mahjong_core::conversions::ConvertTiles;
+use mahjong_core::rulesets::RiichiRuleset;
+use mahjong_core::errors::mahjong_errors::{HandError, ParsingError};
+
+static WINDS: [Wind; 4] = [Wind::East, Wind::South, Wind::West, Wind::North];
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PaipuRound {
+    pub dealer: usize,
+    pub result: PaipuResult,
+}
+
+// How a round ended. Recorded point changes are indexed by seat, and don't include riichi bets.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PaipuResult {
+    Hule {
+        game: Game,
+        discarder: Option<usize>,   // None for tsumo
+        winners: Vec<Seat>,
+        deposits: u8,
+        recorded: [i32; 4],
+    },
+    NoTile {
+        game: Game,
+        seats: Vec<Seat>,
+        recorded: [i32; 4],
+    },
+    LiuJu(AbortiveDraw),
+}
+
+// Tracks each seat's tiles while the record is read.
+#[derive(Debug, Default)]
+struct Table {
+    game: Option<Game>,
+    dealer: usize,
+    deposits: u8,
+    hands: [Vec<Tile>; 4],
+    melds: [Vec<Meld>; 4],
+    discards: [Vec<Discard>; 4],
+    last_discard: Option<usize>,
+    riichi: bool,           // the last discard declared riichi, and its stick isn't down yet
+    last_kan: Option<usize>,    // the seat whose kan can still be robbed
+}
+
+/////////////////////
+// implementations //
+/////////////////////
+
+// Reads every round of a record.
+//
+// # Errors
+//
+// Returns BadString for malformed JSON or records, BadChar for bad tiles, and Unimplemented for three player games.
+pub fn read_paipu(json: &str) -> Result<Vec<PaipuRound>, ParsingError> {
+    let root: Value = serde_json::from_str(json).map_err(|_| ParsingError::BadString)?;
+    let records = ["/records", "/data/records", "/data/actions"].iter().find_map(|path| root.pointer(path))
+        .and_then(Value::as_array).ok_or(ParsingError::NothingFound)?;
+
+    let mut rounds: Vec<PaipuRound> = Vec::new();
+    let mut table = Table::default();
+
+    for record in records {
+        let name = record.get("name").and_then(Value::as_str).ok_or(ParsingError::BadString)?;
+        let data = record.get("data").ok_or(ParsingError::BadString)?;
+        let seat = || number(data, "seat").and_then(|s| if s < 4 { Ok(s) } else { Err(ParsingError::BadInteger) });
+        let name = name.trim_start_matches(".lq.");
+
+        // a riichi only puts its stick down once its discard passes without a ron
+        if table.riichi && name != "RecordHule" {
+            table.riichi = false;
+            table.deposits += 1;
+        }
+
+        match name {
+            "RecordNewRound" => {
+                let dealer = number(data, "ju")?;
+                if dealer > 3 { return Err(ParsingError::BadInteger) }
+                let mut dora = tiles(data, "doras")?;
+                if dora.is_empty() { dora = data.get("dora").and_then(Value::as_str).map(tile_from_majsoul).transpose()?.into_iter().collect() }
+
+                let game = Game {
+                    ruleset: RiichiRuleset::MajSoul,
+                    round_wind: WINDS[number(data, "chang")? % 4],
+                    repeats: u8::try_from(number(data, "ben")?).map_err(|_| ParsingError::BadInteger)?,
+                    dora_markers: Some(dora),
+                    ura_dora_markers: None,
+                    last_action: None,
+                    tiles_left: None,
+                    turn: None,
+                };
+                table = Table { game: Some(game), dealer, deposits: u8::try_from(number(data, "liqibang")?).map_err(|_| ParsingError::BadInteger)?, ..Table::default() };
+                for (index, hand) in table.hands.iter_mut().enumerate() { *hand = tiles(data, &format!("tiles{index}"))? }
+            },
+            "RecordDealTile" => {
+                table.last_kan = None;
+                table.hands[seat()?].push(tile(data, "tile")?);
+                if let Some(game) = table.game.as_mut() {
+                    let dora = tiles(data, "doras")?;
+                    if !dora.is_empty() { game.dora_markers = Some(dora) }
+                }
+            },
+            "RecordDiscardTile" => {
+                let seat = seat()?;
+                let discarded = tile(data, "tile")?;
+                remove_tile(&mut table.hands[seat], discarded)?;
+                table.discards[seat].push(Discard { tile: discarded, called: false });
+                table.last_discard = Some(seat);
+                table.riichi = flag(data, "is_liqi") || flag(data, "is_wliqi");
+            },
+            "RecordChiPengGang" => {
+                let seat = seat()?;
+                let called_seat = table.last_discard.ok_or(ParsingError::BadMeld)?;
+                let meld_tiles = tiles(data, "tiles")?;
+                let froms: Vec<usize> = data.get("froms").and_then(Value::as_array).ok_or(ParsingError::BadString)?
+                    .iter().map(|f| f.as_u64().and_then(|f| usize::try_from(f).ok()).ok_or(ParsingError::BadInteger)).collect::<Result<_, _>>()?;
+                let called_at = froms.iter().position(|f| *f != seat).ok_or(ParsingError::BadMeld)?;
+
+                let mut meld = meld_tiles.make_meld(true).ok_or(ParsingError::BadMeld)?;
+                meld.kind = Some(match number(data, "type")? { 0 => MeldKind::Chi, 1 => MeldKind::Pon, _ => MeldKind::Daiminkan });
+                meld.called_from = Some(WINDS[(called_seat + 4 - table.dealer) % 4]);
+                meld.called_index = if meld.is_seq() { meld.tiles.iter().position(|t| *t == Some(meld_tiles[called_at])) } else { Some(called_at) };
+
+                if let Some(discard) = table.discards[called_seat].last_mut() { discard.called = true }
+                for (index, t) in meld_tiles.iter().enumerate() {
+                    if index != called_at { remove_tile(&mut table.hands[seat], *t)? }
+                }
+                table.melds[seat].push(meld);
+            },
+            "RecordAnGangAddGang" => {
+                let seat = seat()?;
+                let kan_tile = tile(data, "tiles")?;
+                table.last_kan = Some(seat);
+                if number(data, "type")? == 3 {
+                    let mut removed: Vec<Tile> = Vec::with_capacity(4);
+                    for _ in 0..4 {
+                        let index = table.hands[seat].iter().position(|t| *t == kan_tile).ok_or(ParsingError::NothingFound)?;
+                        removed.push(table.hands[seat].remove(index));
+                    }
+                    let mut meld = removed.make_meld(false).ok_or(ParsingError::BadMeld)?;
+                    meld.kind = Some(MeldKind::Ankan);
+                    table.melds[seat].push(meld);
+                } else {
+                    remove_tile(&mut table.hands[seat], kan_tile)?;
+                    let pon = table.melds[seat].iter_mut().find(|m| m.kind == Some(MeldKind::Pon) && m.tiles[0] == Some(kan_tile)).ok_or(ParsingError::BadMeld)?;
+                    pon.tiles[3] = Some(kan_tile);
+                    pon.kind = Some(MeldKind::Shouminkan);
+                }
+            },
+            "RecordHule" => {
+                let mut game = table.game.clone().ok_or(ParsingError::BadString)?;
+                let hules = data.get("hules").and_then(Value::as_array).ok_or(ParsingError::BadString)?;
+                let mut discarder: Option<usize> = None;
+                let mut winners: Vec<Seat> = Vec::with_capacity(hules.len());
+
+                for hule in hules {
+                    let seat = number(hule, "seat")?;
+                    if seat > 3 { return Err(ParsingError::BadInteger) }
+                    let zimo = flag(hule, "zimo");
+                    // a ron straight after a kan robs it, so the kan's caller pays
+                    if !zimo { discarder = table.last_kan.or(table.last_discard) }
+                    let ura = tiles(hule, "li_doras")?;
+                    if !ura.is_empty() { game.ura_dora_markers = Some(ura) }
+                    winners.push(table.winner(hule, seat, zimo)?);
+                }
+                rounds.push(PaipuRound { dealer: table.dealer, result: PaipuResult::Hule {
+                    game, discarder, winners, deposits: table.deposits, recorded: deltas(data.get("delta_scores"))? } });
+            },
+            "RecordNoTile" => {
+                let game = table.game.clone().ok_or(ParsingError::BadString)?;
+                let seats: Vec<Seat> = (0..4).map(|seat| {
+                    let mut closed = table.hands[seat].clone();
+                    closed.sort();
+                    let mut state = Seat::new(closed, if table.melds[seat].is_empty() { None } else { Some(table.melds[seat].clone()) },
+                        WINDS[(seat + 4 - table.dealer) % 4], None, None, None);
+                    state.discards = Some(table.discards[seat].clone());
+                    state
+                }).collect();
+                let recorded = deltas(data.pointer("/scores/0/delta_scores"))?;
+                rounds.push(PaipuRound { dealer: table.dealer, result: PaipuResult::NoTile { game, seats, recorded } });
+            },
+            "RecordLiuJu" => {
+                let draw = match number(data, "type")? {
+                    1 => AbortiveDraw::KyuushuKyuuhai,
+                    2 => AbortiveDraw::SuufonRenda,
+                    3 => AbortiveDraw::SuuchaRiichi,
+                    4 => AbortiveDraw::Suukaikan,
+                    5 => AbortiveDraw::Sanchahou,
+                    _ => return Err(ParsingError::BadInteger),
+                };
+                rounds.push(PaipuRound { dealer: table.dealer, result: PaipuResult::LiuJu(draw) });
+            },
+            "RecordBaBei" => return Err(ParsingError::Unimplemented),
+            _ => (),
+        }
+    }
+    Ok(rounds)
+}
+
+// Scores a round with FiveSou, returning the point changes for each seat. Abortive draws don't move any points.
+//
+// # Errors
+//
+// Errors from scoring are passed along.
+pub fn rescore(round: &PaipuRound) -> Result<[i32; 4], HandError> {
+    let points = match &round.result {
+        PaipuResult::Hule { game, discarder, winners, deposits, .. } => match (discarder, winners.as_slice()) {
+            (_, [winner]) => settle_win(game, winner, discarder.map(|d| WINDS[(d + 4 - round.dealer) % 4]), *deposits)?,
+            (Some(discarder), _) => match settle_ron(game, WINDS[(discarder + 4 - round.dealer) % 4], winners, *deposits)? {
+                RonSettlement::Wins { points, .. } => points,
+                RonSettlement::Abortive(_) => [0; 4],
+            },
+            (None, _) => return Err(HandError::ValueError),
+        },
+        PaipuResult::NoTile { game, seats, .. } => match exhaustive_draw(game, seats)? {
+            Ryuukyoku::Exhaustive { points, .. } => points,
+            Ryuukyoku::Abortive(_) => [0; 4],
+        },
+        PaipuResult::LiuJu(_) => [0; 4],
+    };
+
+    let mut by_seat = [0; 4];
+    for (wind, change) in points.iter().enumerate() { by_seat[(wind + round.dealer) % 4] = *change }
+    Ok(by_seat)
+}
+
+pub fn recorded(round: &PaipuRound) -> [i32; 4] {
+    match &round.result {
+        PaipuResult::Hule { recorded, .. } | PaipuResult::NoTile { recorded, .. } => *recorded,
+        PaipuResult::LiuJu(_) => [0; 4],
+    }
+}
+
+// Converts a Mahjong Soul tile into a tile, ie "5m", "0p" or "7z".
+//
+// # Errors
+//
+// Returns BadChar for anything else.
+pub fn tile_from_majsoul(name: &str) -> Result<Tile, ParsingError> {
+    let mut chars = name.chars();
+    let (Some(number), Some(suit), None) = (chars.next().and_then(|c| c.to_digit(10)), chars.next(), chars.next()) else { return Err(ParsingError::BadString) };
+    let number = i8::try_from(number).map_err(|_| ParsingError::BadInteger)?;
+
+    match (suit, number) {
+        ('m' | 'p' | 's', 0..=9) => Ok(Tile::Number {
+            suit: match suit { 'm' => Suit::Man, 'p' => Suit::Pin, _ => Suit::Sou },
+            number: if number == 0 { 5 } else { number },
+            red: number == 0,
+        }),
+        ('z', 1..=4) => Ok(Tile::Wind(WINDS[usize::try_from(number - 1).map_err(|_| ParsingError::BadInteger)?])),
+        ('z', 5) => Ok(Tile::Dragon(Dragon::White)),
+        ('z', 6) => Ok(Tile::Dragon(Dragon::Green)),
+        ('z', 7) => Ok(Tile::Dragon(Dragon::Red)),
+        _ => Err(ParsingError::BadChar),
+    }
+}
+
+impl Table {
+    fn winner(&self, hule: &Value, seat: usize, zimo: bool) -> Result<Seat, ParsingError> {
+        let hu_tile = tile(hule, "hu_tile")?;
+        let mut closed = tiles(hule, "hand")?;
+        if closed.len() % 3 == 2 { remove_tile(&mut closed, hu_tile)? }
+        closed.sort();
+
+        // situational yaku can't be derived from the tiles, so they're taken from the recorded fans
+        let fans: Vec<u64> = hule.get("fans").and_then(Value::as_array).map(|f| f.iter().filter_map(|f| f.get("id").and_then(Value::as_u64)).collect()).unwrap_or_default();
+        let special: Vec<Yaku> = fans.iter().filter_map(|id| match id {
+            2 => Some(Yaku::Riichi),
+            3 => Some(Yaku::RobbedKan),
+            4 => Some(Yaku::AfterKan),
+            5 => Some(Yaku::UnderSea),
+            6 => Some(Yaku::UnderRiver),
+            18 => Some(Yaku::DoubleRiichi),
+            30 => Some(Yaku::Ippatsu),
+            35 => Some(Yaku::Tenho),
+            36 => Some(Yaku::Chiho),
+            _ => None,
+        }).collect();
+        let latest_type = if !zimo { TileType::Call } else if special.contains(&Yaku::AfterKan) { TileType::Kan } else { TileType::Draw };
+
+        let mut state = Seat::new(closed, if self.melds[seat].is_empty() { None } else { Some(self.melds[seat].clone()) },
+            WINDS[(seat + 4 - self.dealer) % 4], Some(hu_tile), Some(latest_type), if special.is_empty() { None } else { Some(special) });
+        state.discards = Some(self.discards[seat].clone());
+        Ok(state)
+    }
+}
+
+fn number(data: &Value, key: &str) -> Result<usize, ParsingError> {
+    data.get(key).map_or(Ok(0), |v| v.as_u64().and_then(|n| usize::try_from(n).ok()).ok_or(ParsingError::BadInteger))
+}
+
+fn flag(data: &Value, key: &str) -> bool {
+    data.get(key).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn tile(data: &Value, key: &str) -> Result<Tile, ParsingError> {
+    tile_from_majsoul(data.get(key).and_then(Value::as_str).ok_or(ParsingError::Empty)?)
+}
+
+fn tiles(data: &Value, key: &str) -> Result<Vec<Tile>, ParsingError> {
+    data.get(key).and_then(Value::as_array).map_or(Ok(Vec::new()), |tiles| {
+        tiles.iter().map(|t| t.as_str().ok_or(ParsingError::BadString).and_then(tile_from_majsoul)).collect()
+    })
+}
+
+fn deltas(value: Option<&Value>) -> Result<[i32; 4], ParsingError> {
+    let values: Vec<i32> = value.and_then(Value::as_array).ok_or(ParsingError::Empty)?
+        .iter().map(|v| v.as_i64().and_then(|v| i32::try_from(v).ok()).ok_or(ParsingError::BadInteger)).collect::<Result<_, _>>()?;
+    values.try_into().map_err(|_| ParsingError::BadString)
+}
+
+// Removes the exact tile, so that a red five isn't swapped for a regular one.
+fn remove_tile(tiles: &mut Vec<Tile>, tile: Tile) -> Result<(), ParsingError> {
+    let is_red = |t: &Tile| matches!(t, Tile::Number { red: true, .. });
+    let index = tiles.iter().position(|t| *t == tile && is_red(t) == is_red(&tile))
+        .or_else(|| tiles.iter().position(|t| *t == tile)).ok_or(ParsingError::NothingFound)?;
+    tiles.remove(index);
+    Ok(())
+}
+
+///////////
+// tests //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_majsoul_tiles(){
+        assert_eq!(tile_from_majsoul("0p").map(|t| t.to_string()), Ok("p5r".to_string()));
+        assert_eq!(tile_from_majsoul("5p").map(|t| t.to_string()), Ok("p5".to_string()));
+        assert_eq!(tile_from_majsoul("1z"), Ok(Tile::Wind(Wind::East)));
+        assert_eq!(tile_from_majsoul("7z"), Ok(Tile::Dragon(Dragon::Red)));
+        assert_eq!(tile_from_majsoul("8z"), Err(ParsingError::BadChar));
+        assert_eq!(tile_from_majsoul("15m"), Err(ParsingError::BadString));
+    }
+
+    #[test]
+    fn test_majsoul_riichi_sticks(){
+        let paipu = |after_riichi: &str| format!(r#"{{"records": [
+            {{"name": ".lq.RecordNewRound", "data": {{"chang": 0, "ju": 0, "ben": 0, "liqibang": 1, "doras": ["9m"],
+                "tiles0": ["1m", "1m", "1m", "2m", "3m", "4m", "5m", "6m", "7m", "2p", "3p", "4p", "9s", "9s"],
+                "tiles1": ["2m", "3m", "4m", "2p", "3p", "4p", "6s", "7s", "8s", "5p", "5p", "4s", "5s"],
+                "tiles2": [], "tiles3": []}}}},
+            {{"name": ".lq.RecordDiscardTile", "data": {{"seat": 0, "tile": "1m", "is_liqi": true}}}},
+            {after_riichi}
+            {{"name": ".lq.RecordHule", "data": {{"hules": [{{"seat": 1, "zimo": false, "hu_tile": "3s",
+                "hand": ["2m", "3m", "4m", "2p", "3p", "4p", "6s", "7s", "8s", "5p", "5p", "4s", "5s"]}}],
+                "delta_scores": [-1000, 1000, 0, 0]}}}}]}}"#);
+        let deposits = |json: &str| match read_paipu(json).unwrap().remove(0).result {
+            PaipuResult::Hule { deposits, .. } => deposits,
+            _ => panic!("should be a win"),
+        };
+
+        // dealing in with the riichi discard means the stick never goes down
+        assert_eq!(deposits(&paipu("")), 1);
+        assert_eq!(deposits(&paipu(r#"{"name": ".lq.RecordDealTile", "data": {"seat": 2, "tile": "3s"}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 2, "tile": "3s"}},"#)), 2);
+    }
+
+    #[test]
+    fn test_majsoul_chankan(){
+        let json = r#"{"records": [
+            {"name": ".lq.RecordNewRound", "data": {"chang": 0, "ju": 0, "ben": 0, "liqibang": 0, "doras": ["9m"],
+                "tiles0": ["1m", "1m", "1m", "2m", "3m", "4m", "5m", "6m", "7m", "2p", "3p", "4p", "1p", "9s"],
+                "tiles1": ["2m", "3m", "4m", "2p", "3p", "4p", "6s", "7s", "8s", "5p", "5p", "7s", "8s"],
+                "tiles2": [], "tiles3": ["9s", "9s", "1z"]}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 0, "tile": "9s"}},
+            {"name": ".lq.RecordChiPengGang", "data": {"seat": 3, "type": 1, "tiles": ["9s", "9s", "9s"], "froms": [3, 3, 0]}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 3, "tile": "1z"}},
+            {"name": ".lq.RecordDealTile", "data": {"seat": 2, "tile": "9m"}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 2, "tile": "9m"}},
+            {"name": ".lq.RecordDealTile", "data": {"seat": 3, "tile": "9s"}},
+            {"name": ".lq.RecordAnGangAddGang", "data": {"seat": 3, "type": 2, "tiles": "9s"}},
+            {"name": ".lq.RecordHule", "data": {"hules": [{"seat": 1, "zimo": false, "hu_tile": "9s",
+                "hand": ["2m", "3m", "4m", "2p", "3p", "4p", "6s", "7s", "8s", "5p", "5p", "7s", "8s"], "fans": [{"id": 3, "val": 1}]}],
+                "delta_scores": [0, 1000, 0, -1000]}}]}"#;
+
+        // the seat that added to its pon pays, not the last seat to discard
+        let result = read_paipu(json).map(|mut rounds| rounds.remove(0).result);
+        assert!(matches!(result, Ok(PaipuResult::Hule { discarder: Some(3), .. })), "{result:?}");
+    }
+}
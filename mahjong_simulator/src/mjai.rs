@@ -0,0 +1,455 @@
+/*
+An adapter for the mjai protocol, so FiveSou can act as the rules and scoring backend next to mjai bots.
+Events arrive as JSON lines on stdin. Every event is answered with one line on stdout: hora events are answered with
+FiveSou's scoring of the win, anything else with {"type":"none"}, and bad input with an error event.
+
+Players are referred to by their mjai id (0-3), which stays fixed for the whole game; seat winds are derived from oya.
+*/
+
+use std::io::{self, BufRead, Write};
+use serde::{Deserialize, Serialize};
+use mahjong_core::tiles::{Tile, Suit, Dragon, Wind};
+use mahjong_core::hand::{Hand, HandTrait, MeldKind, MeldIs, read_waits};
+use mahjong_core::state::{Game, Seat, Discard, TileType, LastAction, Turn, Riichi, SeatHelper, RiichiState};
+use mahjong_core::yaku::{Yaku, YAKUMAN};
+use mahjong_core::scoring::{CountHan, HandScore, Payment, settle_win};
+use mahjong_core::conversions::ConvertTiles;
+use mahjong_core::rulesets::RiichiRuleset;
+use mahjong_core::errors::mahjong_errors::{HandError, ParsingError};
+
+static WINDS: [Wind; 4] = [Wind::East, Wind::South, Wind::West, Wind::North];
+static LIVE_DRAWS: u8 = 70; // draws in a hand before the wall runs out, including rinshan draws
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MjaiEvent {
+    StartGame {
+        #[serde(default)]
+        names: Vec<String>,
+    },
+    StartKyoku {
+        bakaze: String,
+        kyoku: u8,
+        honba: u8,
+        kyotaku: u8,
+        oya: usize,
+        dora_marker: String,
+        #[serde(default)]
+        scores: Option<[i32; 4]>,
+        tehais: Vec<Vec<String>>,
+    },
+    Tsumo { actor: usize, pai: String },
+    Dahai { actor: usize, pai: String, #[serde(default)] tsumogiri: bool },
+    Chi { actor: usize, target: usize, pai: String, consumed: Vec<String> },
+    Pon { actor: usize, target: usize, pai: String, consumed: Vec<String> },
+    Daiminkan { actor: usize, target: usize, pai: String, consumed: Vec<String> },
+    Kakan { actor: usize, pai: String, consumed: Vec<String> },
+    Ankan { actor: usize, consumed: Vec<String> },
+    Dora { dora_marker: String },
+    Reach { actor: usize },
+    ReachAccepted { actor: usize },
+    Hora {
+        actor: usize,
+        target: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pai: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uradora_markers: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        yakus: Option<Vec<(String, u8)>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fu: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fan: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hora_points: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        deltas: Option<[i32; 4]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scores: Option<[i32; 4]>,
+    },
+    Ryukyoku,
+    EndKyoku,
+    EndGame,
+    None,
+    Error { message: String },
+    #[serde(other)]
+    Unknown,
+}
+
+// Everything known about the current hand. The game keeps the last action, the tiles left and the turn, and each seat
+// its tiles, discards and riichi, which the special yaku are derived from when a win is scored.
+#[derive(Debug)]
+pub struct MjaiState {
+    ruleset: RiichiRuleset,
+    game: Option<Game>,
+    oya: usize,
+    scores: [i32; 4],
+    deposits: u8,
+    last_tile: Option<Tile>,    // the latest discard or added kan tile, which a ron can be on
+    reach: Option<usize>,       // declared riichi, but hasn't made the riichi discard yet
+    kan: Option<MeldKind>,      // the next draw comes from the dead wall, after a kan of this kind
+    seats: Vec<Seat>,           // indexed by mjai id
+}
+
+/////////////////////
+// implementations //
+/////////////////////
+
+// Answers each line of input with one line of output until input runs out.
+//
+// # Errors
+//
+// Only fails when reading or writing does.
+pub fn run(input: impl BufRead, mut output: impl Write, ruleset: RiichiRuleset) -> io::Result<()> {
+    let mut state = MjaiState::new(ruleset);
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() { continue }
+
+        let response = match serde_json::from_str::<MjaiEvent>(&line) {
+            Ok(event) => state.handle(&event).unwrap_or_else(|e| MjaiEvent::Error { message: format!("{e:?}") }),
+            Err(e) => MjaiEvent::Error { message: e.to_string() },
+        };
+        writeln!(output, "{}", serde_json::to_string(&response).map_err(io::Error::other)?)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+impl MjaiState {
+    pub fn new(ruleset: RiichiRuleset) -> Self {
+        MjaiState { ruleset, game: None, oya: 0, scores: [25000; 4], deposits: 0, last_tile: None, reach: None, kan: None, seats: Vec::new() }
+    }
+
+    // Applies an event, returning the response to it.
+    //
+    // # Errors
+    //
+    // Returns ParseError for bad tiles or events that don't fit the current state, and scoring errors for bad wins.
+    pub fn handle(&mut self, event: &MjaiEvent) -> Result<MjaiEvent, HandError> {
+        match event {
+            MjaiEvent::StartKyoku { bakaze, honba, kyotaku, oya, dora_marker, scores, tehais, .. } => {
+                let round_wind = match bakaze.as_str() { "E" => Wind::East, "S" => Wind::South, "W" => Wind::West, "N" => Wind::North,
+                    _ => return Err(HandError::ParseError(ParsingError::BadString)) };
+                self.game = Some(Game { ruleset: self.ruleset, round_wind, repeats: *honba,
+                    dora_markers: Some(vec![tile(dora_marker)?]), ura_dora_markers: None, last_action: None, tiles_left: Some(LIVE_DRAWS),
+                    turn: Some(Turn { discards: 0, called: false }) });
+                self.oya = *oya % 4;
+                if let Some(scores) = scores { self.scores = *scores }
+                self.deposits = *kyotaku;
+                self.last_tile = None;
+                self.reach = None;
+                self.kan = None;
+                // other players' tiles may be hidden as "?"
+                self.seats = (0..4).map(|actor| {
+                    let closed = tehais.get(actor).into_iter().flatten().filter_map(|t| tile_from_mjai(t).ok()).collect();
+                    let mut seat = Seat::new(closed, None, self.wind(actor), None, None, None);
+                    seat.discards = Some(Vec::new());
+                    seat
+                }).collect();
+            },
+            MjaiEvent::Tsumo { actor, pai } => {
+                let wind = self.wind(*actor);
+                let action = match self.kan.take() { Some(kind) => LastAction::KanDraw(wind, kind), None => LastAction::Draw(wind) };
+                if let Ok(tile) = tile_from_mjai(pai) { self.seat(*actor)?.closed_tiles.push(tile) }
+                let game = self.game()?;
+                game.last_action = Some(action);
+                game.tiles_left = game.tiles_left.map(|left| left.saturating_sub(1));
+            },
+            MjaiEvent::Dahai { actor, pai, .. } => {
+                let tile = tile(pai)?;
+                let wind = self.wind(*actor);
+                let game = self.game()?;
+                let called = game.turn.is_some_and(|t| t.called);
+                game.last_action = Some(LastAction::Discard(wind));
+                if let Some(turn) = game.turn.as_mut() { turn.discards = turn.discards.saturating_add(1) }
+
+                let reach = self.reach.take() == Some(*actor);
+                let seat = self.seat(*actor)?;
+                remove_tile(&mut seat.closed_tiles, tile);
+                let turn = u8::try_from(seat.discards.as_ref().map_or(0, Vec::len)).unwrap_or(u8::MAX);
+                // other players' hands are hidden, so the declaration can't be checked with declare_riichi()
+                if reach { seat.riichi = Some(Riichi::new(turn, tile, !called)) }
+                else { seat.end_ippatsu() }
+                seat.discards.get_or_insert_with(Vec::new).push(Discard { tile, called: false });
+                self.last_tile = Some(tile);
+            },
+            MjaiEvent::Chi { actor, target, pai, consumed } | MjaiEvent::Pon { actor, target, pai, consumed } | MjaiEvent::Daiminkan { actor, target, pai, consumed } => {
+                let kind = match event { MjaiEvent::Chi { .. } => MeldKind::Chi, MjaiEvent::Pon { .. } => MeldKind::Pon, _ => MeldKind::Daiminkan };
+                let called = tile(pai)?;
+                let consumed = consumed.iter().map(|t| tile(t)).collect::<Result<Vec<_>, _>>()?;
+
+                let mut meld = [consumed.clone(), vec![called]].concat().make_meld(true).ok_or(HandError::ParseError(ParsingError::BadMeld))?;
+                meld.kind = Some(kind);
+                meld.called_from = Some(self.wind(*target));
+                meld.called_index = if meld.is_seq() { meld.tiles.iter().position(|t| *t == Some(called)) } else { Some(consumed.len()) };
+
+                if let Some(discard) = self.seat(*target)?.discards.iter_mut().flatten().last() { discard.called = true }
+                let seat = self.seat(*actor)?;
+                for tile in consumed { remove_tile(&mut seat.closed_tiles, tile) }
+                seat.called_melds.get_or_insert_with(Vec::new).push(meld);
+                self.kan = (kind == MeldKind::Daiminkan).then_some(kind);
+                self.interrupt()?;
+            },
+            MjaiEvent::Kakan { actor, pai, .. } => {
+                let tile = tile(pai)?;
+                let seat = self.seat(*actor)?;
+                remove_tile(&mut seat.closed_tiles, tile);
+                let pon = seat.called_melds.iter_mut().flatten().find(|m| m.kind == Some(MeldKind::Pon) && m.tiles[0] == Some(tile))
+                    .ok_or(HandError::ParseError(ParsingError::BadMeld))?;
+                pon.tiles[3] = Some(tile);
+                pon.kind = Some(MeldKind::Shouminkan);
+                self.kan = Some(MeldKind::Shouminkan);
+                self.interrupt()?;
+                self.game()?.last_action = Some(LastAction::Shouminkan(self.wind(*actor)));
+                self.last_tile = Some(tile);
+            },
+            MjaiEvent::Ankan { actor, consumed } => {
+                let consumed = consumed.iter().map(|t| tile(t)).collect::<Result<Vec<_>, _>>()?;
+                let mut meld = consumed.make_meld(false).ok_or(HandError::ParseError(ParsingError::BadMeld))?;
+                meld.kind = Some(MeldKind::Ankan);
+                let seat = self.seat(*actor)?;
+                for tile in consumed { remove_tile(&mut seat.closed_tiles, tile) }
+                seat.called_melds.get_or_insert_with(Vec::new).push(meld);
+                self.kan = Some(MeldKind::Ankan);
+                self.interrupt()?;
+                self.game()?.last_action = Some(LastAction::Ankan(self.wind(*actor)));
+            },
+            MjaiEvent::Dora { dora_marker } => {
+                let tile = tile(dora_marker)?;
+                if let Some(game) = self.game.as_mut() { game.dora_markers.get_or_insert_with(Vec::new).push(tile) }
+            },
+            MjaiEvent::Reach { actor } => {
+                self.seat(*actor)?;    // only to check the actor
+                self.reach = Some(*actor);
+            },
+            MjaiEvent::ReachAccepted { actor } => {
+                self.seat(*actor)?;
+                self.scores[*actor] -= 1000;
+                self.deposits += 1;
+            },
+            MjaiEvent::Hora { actor, target, pai, uradora_markers, .. } => return self.hora(*actor, *target, pai.as_deref(), uradora_markers.as_deref()),
+            _ => (),
+        }
+        Ok(MjaiEvent::None)
+    }
+
+    // Scores a win. Deposits go to the first winner of the hand.
+    fn hora(&mut self, actor: usize, target: usize, pai: Option<&str>, uradora_markers: Option<&[String]>) -> Result<MjaiEvent, HandError> {
+        let mut game = self.game.clone().ok_or(HandError::ParseError(ParsingError::Empty))?;
+        if let Some(markers) = uradora_markers {
+            game.ura_dora_markers = Some(markers.iter().map(|t| tile(t)).collect::<Result<Vec<_>, _>>()?);
+        }
+        let mut seat = self.seats.get(actor).ok_or(HandError::ValueError)?.clone();
+
+        let (tile, tile_type) = if actor == target {
+            let tile = match pai { Some(pai) => tile(pai)?, None => *seat.closed_tiles.last().ok_or(HandError::TileCount)? };
+            remove_tile(&mut seat.closed_tiles, tile);
+            (tile, if matches!(game.last_action, Some(LastAction::KanDraw(..))) { TileType::Kan } else { TileType::Draw })
+        } else {
+            (match pai { Some(pai) => tile(pai)?, None => self.last_tile.ok_or(HandError::ValueError)? }, TileType::Call)
+        };
+        seat.closed_tiles.sort();
+        seat.latest_tile = Some(tile);
+        seat.latest_type = Some(tile_type);
+
+        // Hand::new() expects a winning hand
+        if !read_waits(&seat.closed_tiles, &seat.called_melds).is_some_and(|w| w.tiles.contains(&tile)) { return Err(HandError::NotAgari) }
+
+        let hand = Hand::new(game.clone(), seat.clone());
+        let points = settle_win(&game, &seat, if actor == target { None } else { Some(self.wind(target)) }, self.deposits)?;
+        self.deposits = 0;
+
+        let mut deltas = [0; 4];
+        for (wind, change) in points.iter().enumerate() { deltas[(wind + self.oya) % 4] = *change }
+        for (score, change) in self.scores.iter_mut().zip(deltas) { *score += change }
+
+        let mut yakus: Vec<(String, u8)> = hand.yaku().iter().filter(|y| **y != Yaku::SpecialWait)
+            .map(|y| (yaku_name(*y).to_string(), vec![*y].han(hand.is_open(), game.ruleset))).collect();
+        let is_yakuman = hand.yaku().iter().any(|y| YAKUMAN.contains(y));
+        if hand.dora() > 0 && !is_yakuman { yakus.push(("dora".to_string(), hand.dora())) }
+
+        Ok(MjaiEvent::Hora {
+            actor, target,
+            pai: Some(tile_to_mjai(tile)),
+            uradora_markers: uradora_markers.map(<[String]>::to_vec),
+            yakus: Some(yakus),
+            fu: Some(hand.fu()),
+            fan: Some(hand.han() + if is_yakuman { 0 } else { hand.dora() }),
+            hora_points: Some(match hand.payment_split(game.ruleset, 0)? {
+                Payment::Ron(value) => value,
+                Payment::DealerTsumo(value) => value * 3,
+                Payment::Tsumo { dealer, non_dealer } => dealer + non_dealer * 2,
+            }),
+            deltas: Some(deltas),
+            scores: Some(self.scores),
+        })
+    }
+
+    fn seat(&mut self, actor: usize) -> Result<&mut Seat, HandError> {
+        self.seats.get_mut(actor).ok_or(HandError::ParseError(ParsingError::BadInteger))
+    }
+
+    fn game(&mut self) -> Result<&mut Game, HandError> {
+        self.game.as_mut().ok_or(HandError::ParseError(ParsingError::Empty))
+    }
+
+    fn wind(&self, actor: usize) -> Wind {
+        WINDS[(actor + 4 - self.oya) % 4]
+    }
+
+    // a call breaks every ippatsu and first turn
+    fn interrupt(&mut self) -> Result<(), HandError> {
+        for seat in &mut self.seats { seat.end_ippatsu() }
+        if let Some(turn) = self.game()?.turn.as_mut() { turn.called = true }
+        Ok(())
+    }
+}
+
+// Converts an mjai tile name into a tile, ie "5mr", "E", or "P".
+//
+// # Errors
+//
+// Returns BadString for anything else, including hidden tiles ("?").
+pub fn tile_from_mjai(name: &str) -> Result<Tile, ParsingError> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some(number @ '1'..='9'), Some(suit @ ('m' | 'p' | 's')), red, None) if red.is_none() || (red == Some('r') && number == '5') => {
+            Ok(Tile::Number {
+                suit: match suit { 'm' => Suit::Man, 'p' => Suit::Pin, _ => Suit::Sou },
+                number: number.to_digit(10).and_then(|n| i8::try_from(n).ok()).ok_or(ParsingError::BadInteger)?,
+                red: red.is_some(),
+            })
+        },
+        (Some(honor), None, None, None) => match honor {
+            'E' => Ok(Tile::Wind(Wind::East)),
+            'S' => Ok(Tile::Wind(Wind::South)),
+            'W' => Ok(Tile::Wind(Wind::West)),
+            'N' => Ok(Tile::Wind(Wind::North)),
+            'P' => Ok(Tile::Dragon(Dragon::White)),
+            'F' => Ok(Tile::Dragon(Dragon::Green)),
+            'C' => Ok(Tile::Dragon(Dragon::Red)),
+            _ => Err(ParsingError::BadChar),
+        },
+        _ => Err(ParsingError::BadString),
+    }
+}
+
+pub fn tile_to_mjai(tile: Tile) -> String {
+    match tile {
+        Tile::Number { suit, number, red } => format!("{number}{}{}", match suit { Suit::Man => 'm', Suit::Pin => 'p', Suit::Sou => 's' }, if red { "r" } else { "" }),
+        Tile::Wind(wind) => ["E", "S", "W", "N"][wind as usize].to_string(),
+        Tile::Dragon(Dragon::White) => "P".to_string(),
+        Tile::Dragon(Dragon::Green) => "F".to_string(),
+        Tile::Dragon(Dragon::Red) => "C".to_string(),
+    }
+}
+
+fn tile(name: &str) -> Result<Tile, HandError> {
+    tile_from_mjai(name).map_err(HandError::ParseError)
+}
+
+// Removes the exact tile, so that a red five isn't swapped for a regular one.
+fn remove_tile(tiles: &mut Vec<Tile>, tile: Tile) {
+    let is_red = |t: &Tile| matches!(t, Tile::Number { red: true, .. });
+    if let Some(index) = tiles.iter().position(|t| *t == tile && is_red(t) == is_red(&tile)) { tiles.remove(index); }
+}
+
+// Every yakuhai is reported as "yakuhai", since FiveSou doesn't track which ones were scored.
+fn yaku_name(yaku: Yaku) -> &'static str {
+    match yaku {
+        Yaku::Chiitoi => "chitoitsu",
+        Yaku::ClosedTsumo => "menzenchin_tsumoho",
+        Yaku::Pinfu => "pinfu",
+        Yaku::Ipeiko => "ipeko",
+        Yaku::SanshokuDoujun => "sanshokudojun",
+        Yaku::Ittsuu => "ikkitsukan",
+        Yaku::Ryanpeiko => "ryanpeko",
+        Yaku::Toitoi => "toitoiho",
+        Yaku::Sananko => "sananko",
+        Yaku::SanshokuDouko => "sanshokudoko",
+        Yaku::Sankantsu => "sankantsu",
+        Yaku::Tanyao => "tanyaochu",
+        Yaku::Yakuhai(_) => "yakuhai",
+        Yaku::Chanta => "honchantaiyao",
+        Yaku::Junchan => "junchantaiyao",
+        Yaku::Honro => "honroto",
+        Yaku::Shosangen => "shosangen",
+        Yaku::Honitsu => "honiso",
+        Yaku::Chinitsu => "chiniso",
+        Yaku::Kokushi => "kokushimuso",
+        Yaku::Suuankou | Yaku::SuuankouTanki => "suanko",
+        Yaku::Daisangen => "daisangen",
+        Yaku::Shosushi => "shosushi",
+        Yaku::Daisushi => "daisushi",
+        Yaku::Tsuiso | Yaku::Daichiishin => "tsuiso",
+        Yaku::Chinroto => "chinroto",
+        Yaku::Ryuiso => "ryuiso",
+        Yaku::ChurenPoto => "churenpoton",
+        Yaku::Sukantsu => "sukantsu",
+        Yaku::SpecialWait => "",
+        Yaku::Riichi => "reach",
+        Yaku::DoubleRiichi => "double_reach",
+        Yaku::Ippatsu => "ippatsu",
+        Yaku::UnderSea => "haiteiraoyue",
+        Yaku::UnderRiver => "hoteiraoyui",
+        Yaku::AfterKan => "rinshankaiho",
+        Yaku::RobbedKan => "chankan",
+        Yaku::NagashiMangan => "nagashimangan",
+        Yaku::Tenho => "tenho",
+        Yaku::Chiho => "chiho",
+        Yaku::Renho => "renho",
+    }
+}
+
+///////////
+// tests //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mjai_tiles(){
+        for name in ["1m", "5mr", "9p", "5sr", "E", "N", "P", "F", "C"] {
+            assert_eq!(tile_from_mjai(name).map(tile_to_mjai).as_deref(), Ok(name));
+        }
+        assert_eq!(tile_from_mjai("P"), Ok(Tile::Dragon(Dragon::White)));
+        assert_eq!(tile_from_mjai("?"), Err(ParsingError::BadChar));
+        assert_eq!(tile_from_mjai("4mr"), Err(ParsingError::BadString));
+        assert_eq!(tile_from_mjai("0m"), Err(ParsingError::BadString));
+    }
+
+    #[test]
+    fn test_mjai_riichi(){
+        let events = [
+            r#"{"type":"start_kyoku","bakaze":"E","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"dora_marker":"9m","tehais":[["?","?","?","?","?","?","?","?","?","?","?","?","?"],["2m","3m","4m","2p","3p","4p","6s","7s","8s","5p","5p","4s","5s"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"]]}"#,
+            r#"{"type":"tsumo","actor":0,"pai":"?"}"#,
+            r#"{"type":"dahai","actor":0,"pai":"9m","tsumogiri":true}"#,
+            r#"{"type":"tsumo","actor":1,"pai":"N"}"#,
+            r#"{"type":"reach","actor":1}"#,
+            r#"{"type":"dahai","actor":1,"pai":"N","tsumogiri":true}"#,
+            r#"{"type":"reach_accepted","actor":1}"#,
+            r#"{"type":"tsumo","actor":2,"pai":"?"}"#,
+            r#"{"type":"dahai","actor":2,"pai":"3s","tsumogiri":true}"#,
+        ];
+        let mut state = MjaiState::new(RiichiRuleset::MajSoul);
+        for event in events { assert_eq!(state.handle(&serde_json::from_str(event).unwrap()), Ok(MjaiEvent::None)) }
+
+        // the riichi on the first discard is double, and the ron comes within the ippatsu window
+        let Ok(MjaiEvent::Hora { yakus: Some(mut yakus), hora_points, deltas, .. }) = state.handle(&MjaiEvent::Hora { actor: 1, target: 2, pai: Some("3s".to_string()),
+            uradora_markers: None, yakus: None, fu: None, fan: None, hora_points: None, deltas: None, scores: None }) else { panic!() };
+        yakus.sort();
+        assert_eq!(yakus, [("double_reach".to_string(), 2), ("ippatsu".to_string(), 1), ("pinfu".to_string(), 1), ("tanyaochu".to_string(), 1)]);
+        assert_eq!((hora_points, deltas), (Some(8000), Some([0, 9000, -8000, 0])));
+
+        assert_eq!(state.handle(&MjaiEvent::ReachAccepted { actor: 4 }), Err(HandError::ParseError(ParsingError::BadInteger)));
+    }
+}
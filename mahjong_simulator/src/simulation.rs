@@ -0,0 +1,729 @@
+/*
+Seeded self-play. Plays N east-only games between four agents across threads, and collects statistics for each agent.
+
+Each game gets its own generator, seeded from the simulation seed and the game's index, and agents change seats from
+game to game, so a report only depends on the seed: not on the number of threads, or which thread played which game.
+Statistics are summed as integers for the same reason, and only turned into rates once every game is in.
+
+The table is simplified: pon and kan are the only calls, and a pon can't be added to, so there's no chi or shouminkan
+(and no chi kuikae for legality.rs to catch); riichi hands don't kan; nobody declares kyuushu kyuuhai. Other abortive
+draws come from ryuukyoku.rs once each discard passes, and sanchahou from settle_ron(). Kans use wall.rs, so kandora are
+revealed when the ruleset says they are. Riichi is declared through RiichiState, and deposits left at the end of a game
+go to first place. Furiten covers a seat's own discards, tiles passed since its last discard, and tiles passed after
+its riichi.
+A game can also be replayed with logging on, which records it as mjlog events for tenhou_json.rs to write out.
+The wall, hands and melds hold physical tiles, so each red five stays a tile of its own; they're only turned into tile
+kinds for scoring and the legality checks.
+Pons and the discards after them are checked with legality.rs; an agent's illegal discard is swapped for the first legal one.
+*/
+
+use std::ops::Add;
+use serde::Serialize;
+use mahjong_core::tiles::{Tile, Wind, TileId, PhysicalTile, ToHistogram, all_physical_tiles};
+use mahjong_core::hand::{Hand, HandTrait, Meld, MeldKind};
+use mahjong_core::state::{Game, Seat, Discard, TileType, LastAction, Riichi, RiichiState, Turn, SeatHelper};
+use mahjong_core::scoring::{RonSettlement, settle_win, settle_ron};
+use mahjong_core::ryuukyoku::{Ryuukyoku, AbortiveDraw, exhaustive_draw, abortive_draw};
+use mahjong_core::conversions::ConvertTiles;
+use mahjong_core::rulesets::{RiichiRuleset, RuleVariations};
+use mahjong_core::agari::is_agari;
+use mahjong_core::legality::{check_call, check_discard_after_call, legal_discards_after_call};
+use mahjong_core::wall::DeadWall;
+use mahjong_core::tenhou::{MjlogEvent, MjlogAgari};
+use mahjong_core::errors::mahjong_errors::{HandError, ParsingError};
+use crate::agents::{Agent, View, make_agent, count_waits};
+
+static WINDS: [Wind; 4] = [Wind::East, Wind::South, Wind::West, Wind::North];
+const STARTING_SCORE: i32 = 25000;
+const LIVE_WALL: usize = 122;   // 136 tiles, less the 14 in the dead wall
+const MAX_HANDS: usize = 64;    // a game ends here even if the dealer never loses the seat
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+// splitmix64; small, fast, and the same everywhere
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub games: usize,
+    pub seed: u64,
+    pub threads: usize,
+    pub ruleset: RiichiRuleset,
+    pub agents: [String; 4],    // agent names, as accepted by agents::make_agent
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SimulationReport {
+    pub seed: u64,
+    pub games: usize,
+    pub ruleset: String,
+    pub agents: Vec<AgentReport>,   // in the order they were given
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AgentReport {
+    pub agent: String,
+    pub hands: u64,
+    pub win_rate: f64,
+    pub deal_in_rate: f64,
+    pub average_win_value: f64,     // points from the hand alone, without repeats or deposits
+    pub riichi_rate: f64,
+    pub call_rate: f64,             // share of hands with at least one call
+    pub placements: [f64; 4],       // share of games finished in each place
+    pub average_placement: f64,
+    pub average_score: f64,
+}
+
+// One game's events, for tenhou_json::write_tenhou_json(). Players are numbered by seat, from the first dealer.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GameLog {
+    pub names: [String; 4],     // each player's agent
+    pub events: Vec<MjlogEvent>,
+}
+
+// Per-agent sums.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+struct Tally {
+    hands: u64,
+    wins: u64,
+    deal_ins: u64,
+    riichis: u64,
+    calls: u64,
+    win_value: i64,
+    placements: [u64; 4],
+    score: i64,
+}
+
+struct Player {
+    hand: Vec<PhysicalTile>,
+    melds: Vec<Meld>,
+    meld_tiles: Vec<PhysicalTile>,  // the physical tiles of every meld
+    discards: Vec<Discard>,
+    riichi: Option<Riichi>,
+    passed: Vec<Tile>,      // tiles other seats discarded since this seat's last discard, or since its riichi
+    called: bool,
+}
+
+// A game in progress. Everything is indexed by seat (0-3), and seat 0 deals first.
+struct Table {
+    ruleset: RiichiRuleset,
+    agents: Vec<Box<dyn Agent>>,
+    scores: [i32; 4],
+    dealer: usize,
+    repeats: u8,
+    deposits: u8,
+    tallies: [Tally; 4],
+    log: Option<Vec<MjlogEvent>>,   // every event so far, if the game is being logged
+}
+
+// How a hand ended, for moving on to the next one.
+struct HandResult {
+    won: bool,
+    dealer_stays: bool,
+}
+
+///////////////
+// functions //
+///////////////
+
+pub fn simulate(config: &Simulation) -> Result<SimulationReport, HandError> {
+    if config.agents.iter().any(|name| make_agent(name, 0).is_none()) {
+        return Err(HandError::ParseError(ParsingError::BadString))
+    }
+    let tallies = run_games(config)?;
+    let ratio = |part: f64, whole: f64| if whole == 0.0 { 0.0 } else { part / whole };
+    let games = config.games as f64;
+
+    Ok(SimulationReport {
+        seed: config.seed,
+        games: config.games,
+        ruleset: format!("{:?}", config.ruleset),
+        agents: config.agents.iter().zip(tallies).map(|(name, t)| {
+            let hands = t.hands as f64;
+            AgentReport {
+                agent: name.clone(),
+                hands: t.hands,
+                win_rate: ratio(t.wins as f64, hands),
+                deal_in_rate: ratio(t.deal_ins as f64, hands),
+                average_win_value: ratio(t.win_value as f64, t.wins as f64),
+                riichi_rate: ratio(t.riichis as f64, hands),
+                call_rate: ratio(t.calls as f64, hands),
+                placements: t.placements.map(|p| ratio(p as f64, games)),
+                average_placement: ratio(t.placements.iter().zip(1..).map(|(p, place)| p * place).sum::<u64>() as f64, games),
+                average_score: ratio(t.score as f64, games),
+            }
+        }).collect(),
+    })
+}
+
+#[cfg(not(feature = "rayon"))]
+fn run_games(config: &Simulation) -> Result<[Tally; 4], HandError> {
+    let threads = config.threads.max(1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads).map(|first| scope.spawn(move || {
+            (first..config.games).step_by(threads).try_fold([Tally::default(); 4], |total, index| Ok(add(total, play_game(config, index, false)?.0)))
+        })).collect();
+        handles.into_iter().map(|h| h.join().expect("simulation threads shouldn't panic"))
+            .try_fold([Tally::default(); 4], |total, tallies| Ok(add(total, tallies?)))
+    })
+}
+
+#[cfg(feature = "rayon")]
+fn run_games(config: &Simulation) -> Result<[Tally; 4], HandError> {
+    use rayon::prelude::*;
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(config.threads).build().map_err(|_| HandError::ValueError)?;
+    pool.install(|| (0..config.games).into_par_iter().map(|index| play_game(config, index, false).map(|(tallies, _)| tallies))
+        .try_reduce(|| [Tally::default(); 4], |a, b| Ok(add(a, b))))
+}
+
+fn add(a: [Tally; 4], b: [Tally; 4]) -> [Tally; 4] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]]
+}
+
+// Plays one game of the simulation again with logging on. A game only depends on the seed and its index, so this is
+// the same game that simulate() played.
+//
+// # Errors
+//
+// Returns BadString for an unknown agent, and passes along any scoring error.
+pub fn log_game(config: &Simulation, index: usize) -> Result<GameLog, HandError> {
+    let (_, events) = play_game(config, index, true)?;
+    Ok(GameLog {
+        names: std::array::from_fn(|seat| config.agents[(seat + 4 - index % 4) % 4].clone()),
+        events: events.unwrap_or_default(),
+    })
+}
+
+// Plays one game, returning tallies in the order agents were given, and the game's events if logged.
+// Agent i sits in seat (i + index) % 4.
+fn play_game(config: &Simulation, index: usize, log: bool) -> Result<([Tally; 4], Option<Vec<MjlogEvent>>), HandError> {
+    let mut rng = Rng::new(Rng::new(config.seed.wrapping_add(index as u64)).next_u64());
+    let agent_at = |seat: usize| (seat + 4 - index % 4) % 4;
+
+    let mut table = Table {
+        ruleset: config.ruleset,
+        agents: (0..4).map(|seat| make_agent(&config.agents[agent_at(seat)], rng.next_u64()).ok_or(HandError::ParseError(ParsingError::BadString)))
+            .collect::<Result<_, _>>()?,
+        scores: [STARTING_SCORE; 4],
+        dealer: 0,
+        repeats: 0,
+        deposits: 0,
+        tallies: [Tally::default(); 4],
+        log: log.then(Vec::new),
+    };
+
+    for _ in 0..MAX_HANDS {
+        let result = table.play_hand(&mut rng)?;
+        table.repeats = if result.won && !result.dealer_stays { 0 } else { table.repeats.saturating_add(1) };
+        if !result.dealer_stays { table.dealer += 1 }
+        if table.dealer == 4 || table.scores.iter().any(|s| *s < 0) { break }
+    }
+
+    // ties go to whoever sat closer to the first dealer
+    let mut order: Vec<usize> = (0..4).collect();
+    order.sort_by_key(|seat| (-table.scores[*seat], *seat));
+    table.scores[order[0]] += i32::from(table.deposits) * 1000;
+
+    let mut tallies = [Tally::default(); 4];
+    for (place, seat) in order.iter().enumerate() {
+        let tally = &mut tallies[agent_at(*seat)];
+        *tally = *tally + table.tallies[*seat];
+        tally.placements[place] += 1;
+        tally.score += i64::from(table.scores[*seat]);
+    }
+    Ok((tallies, table.log))
+}
+
+fn is_furiten(player: &Player) -> bool {
+    let hand = player.hand.to_histogram();
+    player.discards.iter().map(|d| d.tile).chain(player.passed.iter().copied()).map(|t| TileId::from(t).index()).any(|id| {
+        let mut complete = hand;
+        complete[id] += 1;
+        complete[id] <= 4 && is_agari(&complete)
+    })
+}
+
+fn kinds(tiles: &[PhysicalTile]) -> Vec<Tile> {
+    let mut tiles: Vec<Tile> = tiles.iter().map(|t| Tile::from(*t)).collect();
+    tiles.sort();
+    tiles
+}
+
+fn seat_for(player: &Player, wind: Wind, closed: &[PhysicalTile], latest: PhysicalTile, latest_type: TileType) -> Seat {
+    let mut seat = Seat::new(kinds(closed), (!player.melds.is_empty()).then(|| player.melds.clone()), wind,
+        Some(Tile::from(latest)), Some(latest_type), None);
+    seat.all_tiles = Some(kinds(&[closed, &player.meld_tiles, &[latest]].concat()));
+    seat.discards = Some(player.discards.clone());
+    seat.riichi = player.riichi;
+    seat
+}
+
+// The hand left once the tile is ponned, along with the new meld and its tiles.
+fn pon(hand: &[PhysicalTile], tile: PhysicalTile, from: Wind) -> Result<(Vec<PhysicalTile>, Meld, Vec<PhysicalTile>), HandError> {
+    let mut hand = hand.to_vec();
+    let mut meld_tiles = vec![tile];
+    for _ in 0..2 {
+        let position = hand.iter().position(|t| t.kind() == tile.kind()).ok_or(HandError::TileCount)?;
+        meld_tiles.push(hand.remove(position));
+    }
+    let mut meld = kinds(&meld_tiles).make_meld(true).ok_or(HandError::ValueError)?;
+    meld.kind = Some(MeldKind::Pon);
+    meld.called_from = Some(from);
+    meld.called_index = Some(0);
+    Ok((hand, meld, meld_tiles))
+}
+
+// The hand left once the kan is made, along with the new meld and its tiles. from is None for a closed kan, which
+// takes all four tiles from the hand.
+fn kan(hand: &[PhysicalTile], tile: PhysicalTile, from: Option<Wind>) -> Result<(Vec<PhysicalTile>, Meld, Vec<PhysicalTile>), HandError> {
+    let mut hand = hand.to_vec();
+    let mut meld_tiles: Vec<PhysicalTile> = from.map(|_| tile).into_iter().collect();
+    while meld_tiles.len() < 4 {
+        let position = hand.iter().position(|t| t.kind() == tile.kind()).ok_or(HandError::TileCount)?;
+        meld_tiles.push(hand.remove(position));
+    }
+    let mut meld = kinds(&meld_tiles).make_meld(from.is_some()).ok_or(HandError::ValueError)?;
+    meld.kind = Some(if from.is_some() { MeldKind::Daiminkan } else { MeldKind::Ankan });
+    meld.called_from = from;
+    meld.called_index = from.map(|_| 0);
+    Ok((hand, meld, meld_tiles))
+}
+
+// A seat for the legality checks between a call and its discard, and for declaring riichi after one.
+fn seat_after_call(hand: &[PhysicalTile], melds: &[Meld], wind: Wind) -> Seat {
+    Seat::new(kinds(hand), Some(melds.to_vec()), wind, None, None, None)
+}
+
+/////////////////////
+// implementations //
+/////////////////////
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng { Rng(seed) }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    // a number in 0..n
+    pub fn below(&mut self, n: usize) -> usize { (self.next_u64() % n as u64) as usize }
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() { items.swap(i, self.below(i + 1)) }
+    }
+}
+
+impl Add for Tally {
+    type Output = Tally;
+    fn add(self, other: Tally) -> Tally {
+        Tally {
+            hands: self.hands + other.hands,
+            wins: self.wins + other.wins,
+            deal_ins: self.deal_ins + other.deal_ins,
+            riichis: self.riichis + other.riichis,
+            calls: self.calls + other.calls,
+            win_value: self.win_value + other.win_value,
+            placements: [0, 1, 2, 3].map(|i| self.placements[i] + other.placements[i]),
+            score: self.score + other.score,
+        }
+    }
+}
+
+impl Table {
+    fn wind_of(&self, seat: usize) -> Wind { WINDS[(seat + 4 - self.dealer) % 4] }
+    fn seat_of(&self, wind: Wind) -> usize { (self.dealer + wind as usize) % 4 }
+
+    // adds point changes indexed by seat wind to the scores
+    fn apply(&mut self, points: [i32; 4]) {
+        for wind in WINDS { self.scores[self.seat_of(wind)] += points[wind as usize] }
+    }
+
+    fn view<'a>(&self, players: &'a [Player], seat: usize, dora: &'a [Tile], tiles_left: usize) -> View<'a> {
+        let by_wind = |wind: Wind| self.seat_of(wind);
+        View {
+            hand: &players[seat].hand,
+            melds: &players[seat].melds,
+            seat_wind: self.wind_of(seat),
+            round_wind: Wind::East,
+            dora_markers: dora,
+            discards: WINDS.map(|w| players[by_wind(w)].discards.as_slice()),
+            riichi: WINDS.map(|w| players[by_wind(w)].riichi.is_some()),
+            scores: WINDS.map(|w| self.scores[by_wind(w)]),
+            tiles_left,
+        }
+    }
+
+    fn play_hand(&mut self, rng: &mut Rng) -> Result<HandResult, HandError> {
+        let mut wall = all_physical_tiles();
+        rng.shuffle(&mut wall);
+
+        let mut players: Vec<Player> = (0..4).map(|seat| {
+            let start = self.wind_of(seat) as usize * 13;
+            Player { hand: wall[start..start + 13].to_vec(), melds: Vec::new(), meld_tiles: Vec::new(), discards: Vec::new(), riichi: None, passed: Vec::new(), called: false }
+        }).collect();
+        for tally in &mut self.tallies { tally.hands += 1 }
+
+        let result = self.play_turns(&wall, &mut players);
+        for (tally, player) in self.tallies.iter_mut().zip(&players) {
+            if player.called { tally.calls += 1 }
+        }
+        result
+    }
+
+    fn play_turns(&mut self, wall: &[PhysicalTile], players: &mut [Player]) -> Result<HandResult, HandError> {
+        let mut dead_wall = DeadWall::new(&wall[LIVE_WALL..])?;
+        let mut game = Game { ruleset: self.ruleset, round_wind: Wind::East, repeats: self.repeats,
+            dora_markers: None, ura_dora_markers: None, last_action: None, tiles_left: None, turn: None };
+        dead_wall.update(&mut game);
+        self.record(MjlogEvent::Init { game: game.clone(), dealer: self.dealer, deposits: self.deposits, scores: self.scores,
+            hands: std::array::from_fn(|seat| players[seat].hand.iter().map(|t| Tile::from(*t)).collect()) });
+        // haitei and houtei are worked out from the last action, and tenho and chiho from the turn
+        let after = |game: &Game, action: LastAction, tiles_left: usize, turn: Turn| Game { last_action: Some(action),
+            tiles_left: u8::try_from(tiles_left).ok(), turn: Some(turn), ..game.clone() };
+        // hand values for the statistics leave out repeats
+        let without_repeats = |game: &Game| Game { repeats: 0, ..game.clone() };
+
+        let mut next_draw = 52;
+        let mut turn = self.dealer;
+        let mut draws = true;
+        let mut replacement: Option<PhysicalTile> = None;   // a kan's replacement draw, which comes next
+        let mut just_called: Option<Meld> = None;
+        let mut uninterrupted = true;   // no calls yet, for double riichi
+        let mut discards: u8 = 0;
+
+        loop {
+            let wind = self.wind_of(turn);
+            // each kan takes a tile from the end of the live wall
+            let live_end = LIVE_WALL - dead_wall.kans().len();
+            if draws {
+                let (tile, kan_draw) = match replacement.take() {
+                    Some(tile) => (tile, true),
+                    None => {
+                        if next_draw == live_end { return self.exhaustive_draw(&game, players) }
+                        next_draw += 1;
+                        (wall[next_draw - 1], false)
+                    },
+                };
+                players[turn].hand.push(tile);
+                self.record(MjlogEvent::Draw { player: turn, tile: Tile::from(tile) });
+
+                let player = &players[turn];
+                if is_agari(&player.hand.to_histogram()) {
+                    let (action, tile_type) = match dead_wall.last_kan().filter(|_| kan_draw) {
+                        Some((_, kind)) => (LastAction::KanDraw(wind, kind), TileType::Kan),
+                        None => (LastAction::Draw(wind), TileType::Draw),
+                    };
+                    let seat = seat_for(player, wind, &player.hand[..player.hand.len() - 1], tile, tile_type);
+                    let drawn = after(&game, action, live_end - next_draw, Turn { discards, called: !uninterrupted });
+                    if let Ok(points) = settle_win(&drawn, &seat, None, self.deposits) {
+                        let value = settle_win(&without_repeats(&drawn), &seat, None, 0)?;
+                        self.record_agari(&drawn, &seat, None, value[wind as usize]);
+                        self.apply(points);
+                        self.deposits = 0;
+                        self.tallies[turn].wins += 1;
+                        self.tallies[turn].win_value += i64::from(value[wind as usize]);
+                        return Ok(HandResult { won: true, dealer_stays: wind == Wind::East })
+                    }
+                }
+                // some rulesets don't wait for the fourth kan's discard
+                if kan_draw && !self.ruleset.suukaikan_on_discard() && dead_wall.is_suukaikan(&self.ruleset) {
+                    return Ok(self.abortive_draw(&game, players, AbortiveDraw::Suukaikan))
+                }
+
+                // a closed kan on the drawn tile, which is followed by another draw
+                let player = &players[turn];
+                if player.riichi.is_none() && dead_wall.can_declare_kan(&self.ruleset) && next_draw < live_end
+                    && player.hand.iter().filter(|t| t.kind() == tile.kind()).count() == 4 {
+                    let view = self.view(players, turn, game.dora_markers.as_deref().unwrap_or_default(), live_end - next_draw);
+                    if self.agents[turn].call_kan(&view, Tile::from(tile)) {
+                        let (hand, meld, meld_tiles) = kan(&players[turn].hand, tile, None)?;
+                        self.record(MjlogEvent::Call { player: turn, meld });
+                        players[turn].hand = hand;
+                        players[turn].melds.push(meld);
+                        players[turn].meld_tiles.extend(meld_tiles);
+                        replacement = Some(dead_wall.declare_kan(&self.ruleset, MeldKind::Ankan, wind)?);
+                        self.reveal_dora(&dead_wall, &mut game);
+                        for riichi in players.iter_mut().filter_map(|p| p.riichi.as_mut()) { riichi.ippatsu = false }
+                        uninterrupted = false;
+                        continue
+                    }
+                }
+            }
+
+            // a riichi hand throws whatever it draws
+            let player = &players[turn];
+            let mut choice = if player.riichi.is_some() { player.hand[player.hand.len() - 1] }
+                else { let view = self.view(players, turn, game.dora_markers.as_deref().unwrap_or_default(), live_end - next_draw); self.agents[turn].discard(&view) };
+            if let Some(meld) = just_called.take() {
+                let seat = seat_after_call(&player.hand, &player.melds, wind);
+                if check_discard_after_call(&self.ruleset, &game, &seat, &meld, &Tile::from(choice)).is_err() {
+                    let legal = legal_discards_after_call(&self.ruleset, &game, &seat, &meld);
+                    choice = *player.hand.iter().find(|t| legal.contains(&Tile::from(**t))).ok_or(HandError::IllegalCall)?;
+                }
+            }
+            let position = player.hand.iter().position(|t| *t == choice).unwrap_or(player.hand.len() - 1);
+            let tsumogiri = draws && position == player.hand.len() - 1;
+
+            let player = &mut players[turn];
+            // temporary furiten lasts until the seat's own discard, but riichi furiten lasts the hand
+            if let Some(riichi) = player.riichi.as_mut() { riichi.ippatsu = false } else { player.passed.clear() }
+            let tile = player.hand.remove(position);
+
+            let can_riichi = player.riichi.is_none() && player.melds.is_empty() && self.scores[turn] >= 1000 && live_end - next_draw >= 4
+                && count_waits(&player.hand.to_histogram()) > 0;
+            let declared = can_riichi && {
+                let view = self.view(players, turn, game.dora_markers.as_deref().unwrap_or_default(), live_end - next_draw);
+                self.agents[turn].declare_riichi(&view)
+            };
+            if declared {
+                let player = &mut players[turn];
+                let mut seat = seat_after_call(&player.hand, &player.melds, wind);
+                seat.declare_riichi(u8::try_from(player.discards.len()).unwrap_or(u8::MAX), Tile::from(tile), uninterrupted)?;
+                player.riichi = seat.riichi;
+                self.scores[turn] -= 1000;
+                self.deposits += 1;
+                self.tallies[turn].riichis += 1;
+                self.record(MjlogEvent::Riichi { player: turn, step: 1 });
+            }
+            players[turn].discards.push(Discard { tile: Tile::from(tile), called: false });
+            self.record(MjlogEvent::Discard { player: turn, tile: Tile::from(tile), tsumogiri });
+            discards = discards.saturating_add(1);
+            // an open kan's dora is revealed by the discard, in time for a ron on it
+            dead_wall.discarded();
+            self.reveal_dora(&dead_wall, &mut game);
+
+            // ron, checked in turn order after the discarder
+            let last_tile = next_draw == live_end;
+            let discarded = after(&game, LastAction::Discard(wind), live_end - next_draw, Turn { discards, called: !uninterrupted });
+            let mut winners: Vec<Seat> = Vec::new();
+            for other in (1..4).map(|offset| (turn + offset) % 4) {
+                let player = &players[other];
+                let mut complete = player.hand.to_histogram();
+                complete[tile.kind().index()] += 1;
+                if !is_agari(&complete) || is_furiten(player) { continue }
+
+                let seat = seat_for(player, self.wind_of(other), &player.hand, tile, TileType::Call);
+                if settle_win(&discarded, &seat, Some(wind), 0).is_ok() { winners.push(seat) }
+            }
+            if !winners.is_empty() {
+                // a riichi is only made once its discard passes
+                if declared {
+                    self.scores[turn] += 1000;
+                    self.deposits -= 1;
+                }
+                return match settle_ron(&discarded, wind, &winners, self.deposits)? {
+                    RonSettlement::Wins { winners: winds, points, .. } => {
+                        for seat in winners.iter().filter(|s| winds.contains(&s.seat_wind)) {
+                            let value = settle_win(&without_repeats(&discarded), seat, Some(wind), 0)?;
+                            self.record_agari(&discarded, seat, Some(turn), value[seat.seat_wind as usize]);
+                            let winner = self.seat_of(seat.seat_wind);
+                            self.tallies[winner].wins += 1;
+                            self.tallies[winner].win_value += i64::from(value[seat.seat_wind as usize]);
+                        }
+                        self.apply(points);
+                        self.deposits = 0;
+                        self.tallies[turn].deal_ins += 1;
+                        Ok(HandResult { won: true, dealer_stays: winds.contains(&Wind::East) })
+                    },
+                    RonSettlement::Abortive(draw) => Ok(self.abortive_draw(&discarded, players, draw)),
+                }
+            }
+            if declared { self.record(MjlogEvent::Riichi { player: turn, step: 2 }) }
+            // a seat that let its winning tile go is furiten on it
+            for other in (1..4).map(|offset| (turn + offset) % 4) { players[other].passed.push(Tile::from(tile)) }
+
+            // four riichi, four winds or four kans, once the discard passes
+            if let Some(Ryuukyoku::Abortive(draw)) = abortive_draw(&game, &self.seats_at_draw(players), 0) {
+                return Ok(self.abortive_draw(&game, players, draw))
+            }
+
+            // kan, by the first seat in turn order that wants it, then the seat draws a replacement
+            let mut kan_caller = None;
+            for other in (1..4).map(|offset| (turn + offset) % 4) {
+                if last_tile || !dead_wall.can_declare_kan(&self.ruleset) || players[other].riichi.is_some()
+                    || players[other].hand.iter().filter(|t| t.kind() == tile.kind()).count() < 3 { continue }
+                let view = self.view(players, other, game.dora_markers.as_deref().unwrap_or_default(), live_end - next_draw);
+                if self.agents[other].call_kan(&view, Tile::from(tile)) { kan_caller = Some(other); break }
+            }
+            if let Some(caller) = kan_caller {
+                let (hand, meld, meld_tiles) = kan(&players[caller].hand, tile, Some(wind))?;
+                self.record(MjlogEvent::Call { player: caller, meld });
+                let player = &mut players[caller];
+                player.hand = hand;
+                player.melds.push(meld);
+                player.meld_tiles.extend(meld_tiles);
+                player.called = true;
+
+                if let Some(discard) = players[turn].discards.last_mut() { discard.called = true }
+                for riichi in players.iter_mut().filter_map(|p| p.riichi.as_mut()) { riichi.ippatsu = false }
+                uninterrupted = false;
+                replacement = Some(dead_wall.declare_kan(&self.ruleset, MeldKind::Daiminkan, self.wind_of(caller))?);
+                self.reveal_dora(&dead_wall, &mut game);
+                turn = caller;
+                draws = true;
+                continue
+            }
+
+            // pon, by the first seat in turn order that wants it and is allowed to
+            let mut caller = None;
+            for other in (1..4).map(|offset| (turn + offset) % 4) {
+                if last_tile || players[other].riichi.is_some() || players[other].hand.iter().filter(|t| t.kind() == tile.kind()).count() < 2 { continue }
+                let (hand, meld, meld_tiles) = pon(&players[other].hand, tile, wind)?;
+                let melds = [players[other].melds.clone(), vec![meld]].concat();
+                if check_call(&self.ruleset, &game, &seat_after_call(&hand, &melds, self.wind_of(other)), &meld).is_err() { continue }
+                let view = self.view(players, other, game.dora_markers.as_deref().unwrap_or_default(), live_end - next_draw);
+                if self.agents[other].call_pon(&view, Tile::from(tile)) { caller = Some((other, hand, meld, meld_tiles)); break }
+            }
+            if let Some((caller, hand, meld, meld_tiles)) = caller {
+                self.record(MjlogEvent::Call { player: caller, meld });
+                let player = &mut players[caller];
+                player.hand = hand;
+                player.melds.push(meld);
+                player.meld_tiles.extend(meld_tiles);
+                player.called = true;
+
+                if let Some(discard) = players[turn].discards.last_mut() { discard.called = true }
+                for riichi in players.iter_mut().filter_map(|p| p.riichi.as_mut()) { riichi.ippatsu = false }
+                uninterrupted = false;
+                turn = caller;
+                draws = false;
+                just_called = Some(meld);
+            } else {
+                turn = (turn + 1) % 4;
+                draws = true;
+            }
+        }
+    }
+
+    // every seat at the end of a hand which nobody won
+    fn seats_at_draw(&self, players: &[Player]) -> Vec<Seat> {
+        players.iter().enumerate().map(|(seat, player)| {
+            let mut state = Seat::new(kinds(&player.hand), (!player.melds.is_empty()).then(|| player.melds.clone()), self.wind_of(seat), None, None, None);
+            state.discards = Some(player.discards.clone());
+            state.riichi = player.riichi;
+            state
+        }).collect()
+    }
+
+    fn exhaustive_draw(&mut self, game: &Game, players: &[Player]) -> Result<HandResult, HandError> {
+        let seats = self.seats_at_draw(players);
+        let result = exhaustive_draw(game, &seats)?;
+        let abortive = if let Ryuukyoku::Abortive(draw) = result { Some(draw) } else { None };
+        self.record(MjlogEvent::Ryuukyoku { game: game.clone(), seats, abortive });
+
+        match result {
+            Ryuukyoku::Exhaustive { tenpai, points, .. } => {
+                self.apply(points);
+                Ok(HandResult { won: false, dealer_stays: tenpai.contains(&Wind::East) })
+            },
+            Ryuukyoku::Abortive(_) => Ok(HandResult { won: false, dealer_stays: true }),
+        }
+    }
+
+    fn abortive_draw(&mut self, game: &Game, players: &[Player], draw: AbortiveDraw) -> HandResult {
+        if self.log.is_some() {
+            let seats = self.seats_at_draw(players);
+            self.record(MjlogEvent::Ryuukyoku { game: game.clone(), seats, abortive: Some(draw) });
+        }
+        HandResult { won: false, dealer_stays: true }
+    }
+
+    fn record(&mut self, event: MjlogEvent) {
+        if let Some(log) = self.log.as_mut() { log.push(event) }
+    }
+
+    // value is the hand's worth to the winner, without repeats or deposits. There's no Tenhou yaku record to copy, so
+    // the yaku are left for the writer to score. Ura dora are only turned over for riichi hands.
+    fn record_agari(&mut self, game: &Game, seat: &Seat, discarder: Option<usize>, value: i32) {
+        if self.log.is_none() { return }
+        let game = Game { ura_dora_markers: game.ura_dora_markers.clone().filter(|_| seat.riichi.is_some()), ..game.clone() };
+        let fu = Hand::new(game.clone(), seat.clone()).fu();
+        self.record(MjlogEvent::Agari(MjlogAgari { game, seat: seat.clone(), winner: self.seat_of(seat.seat_wind),
+            discarder, fu, points: u32::try_from(value).unwrap_or(0), yaku: Vec::new(), yakuman: Vec::new() }));
+    }
+
+    // Copies the revealed dora indicators into the game state, logging any new ones.
+    fn reveal_dora(&mut self, dead_wall: &DeadWall, game: &mut Game) {
+        let shown = game.dora_markers.as_ref().map_or(0, Vec::len);
+        for tile in dead_wall.dora_markers().into_iter().skip(shown) { self.record(MjlogEvent::Dora(tile)) }
+        dead_wall.update(game);
+    }
+}
+
+///////////
+// tests //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(threads: usize) -> Simulation {
+        Simulation {
+            games: 12,
+            seed: 2024,
+            threads,
+            ruleset: RiichiRuleset::MajSoul,
+            agents: ["efficient", "efficient", "random", "efficient"].map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_simulation_is_reproducible(){
+        let report = simulate(&config(1)).unwrap();
+        assert_eq!(simulate(&config(3)).unwrap(), report);
+        assert_ne!(simulate(&Simulation { seed: 2025, ..config(2) }).unwrap(), report);
+
+        // every game's points end up with someone
+        let total: f64 = report.agents.iter().map(|a| a.average_score).sum();
+        assert!((total - f64::from(STARTING_SCORE * 4)).abs() < 1e-6);
+        for agent in &report.agents {
+            assert!((agent.placements.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            assert!(agent.hands >= 12);
+        }
+        // the random agent can't win much, and the efficient ones do
+        assert!(report.agents[0].win_rate + report.agents[1].win_rate + report.agents[3].win_rate > report.agents[2].win_rate);
+        assert!(report.agents[0].riichi_rate > 0.0);
+
+        assert!(simulate(&Simulation { agents: ["efficient", "efficient", "random", "nobody"].map(String::from), ..config(1) }).is_err());
+    }
+
+    #[test]
+    fn test_furiten(){
+        let tiles = |s: &str| mahjong_core::tiles::make_tiles_from_string(s).unwrap();
+        // waiting on m4 and m7
+        let mut player = Player { hand: tiles("m1,m2,m3,p4,p5,p6,s7,s8,s9,we,we,m5,m6").into_iter()
+            .map(|t| PhysicalTile::from_kind(TileId::from(t), 1).unwrap()).collect(),
+            melds: Vec::new(), meld_tiles: Vec::new(), discards: Vec::new(), riichi: None, passed: tiles("p1,s2"), called: false };
+        assert!(!is_furiten(&player));
+
+        // a passed m7 rules out a ron on m4 too, as does discarding it
+        player.passed.extend(tiles("m7"));
+        assert!(is_furiten(&player));
+        player.passed.clear();
+        player.discards.push(Discard { tile: tiles("m4")[0], called: false });
+        assert!(is_furiten(&player));
+    }
+
+    #[test]
+    fn test_game_log(){
+        let log = log_game(&config(1), 1).unwrap();
+        assert_eq!(log, log_game(&config(2), 1).unwrap());
+        assert_eq!(log.names, ["efficient", "efficient", "efficient", "random"].map(String::from));
+
+        // every hand opens with the deal and ends with a result, and each draw is followed by a discard or a kan
+        let inits = log.events.iter().filter(|e| matches!(e, MjlogEvent::Init { .. })).count();
+        let results = log.events.iter().filter(|e| matches!(e, MjlogEvent::Agari(_) | MjlogEvent::Ryuukyoku { .. })).count();
+        assert!(matches!(log.events[0], MjlogEvent::Init { dealer: 0, scores: [STARTING_SCORE, ..], .. }));
+        assert!(inits > 0 && results >= inits);
+        assert!(log.events.iter().any(|e| matches!(e, MjlogEvent::Discard { tsumogiri: true, .. })));
+        assert!(log.events.iter().any(|e| matches!(e, MjlogEvent::Riichi { step: 2, .. })));
+
+        let json = crate::tenhou_json::write_tenhou_json(&log.events, log.names.each_ref().map(String::as_str)).unwrap();
+        assert!(json.starts_with(r#"{"title":["FiveSou",""],"name":["efficient","efficient","efficient","random"]"#));
+    }
+}
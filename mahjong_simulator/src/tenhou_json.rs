@@ -0,0 +1,295 @@
+/*
+Writes games in the JSON format used by Tenhou's replay viewer (tenhou.net/6), so bot games can be watched in existing viewers.
+
+Each hand is logged as its header, starting scores, dora markers, then each player's starting hand, draws and discards.
+Calls are written in place of a draw (chi, pon, open kan) or a discard (closed and added kans), and results are
+scored by FiveSou rather than copied from anywhere.
+*/
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use mahjong_core::tiles::{Tile, Suit, Dragon, Wind};
+use mahjong_core::hand::{Hand, HandTrait, Meld, MeldKind};
+use mahjong_core::state::Game;
+use mahjong_core::yaku::{Yaku, YAKUMAN, YakuMetadata};
+use mahjong_core::scoring::{CountHan, HandScore, Payment, settle_win, find_pao};
+use mahjong_core::ryuukyoku::{Ryuukyoku, AbortiveDraw, exhaustive_draw};
+use mahjong_core::tenhou::MjlogEvent;
+use mahjong_core::errors::mahjong_errors::HandError;
+
+static WINDS: [Wind; 4] = [Wind::East, Wind::South, Wind::West, Wind::North];
+
+///////////////////////
+// structs and enums //
+///////////////////////
+
+// The whole file, in the order the viewer expects its fields.
+#[derive(Debug, Serialize)]
+struct TenhouLog<'a> {
+    title: [&'a str; 2],
+    name: [&'a str; 4],
+    rule: Rule,
+    log: Vec<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Rule {
+    disp: &'static str,
+    aka: u8,
+}
+
+// One hand of the log. Draws and discards mix tile codes with call strings, so they're kept as JSON values.
+#[derive(Debug, Default)]
+struct HandLog {
+    header: [u32; 3],       // round (0 is east 1), repeats, deposits
+    dealer: usize,
+    deposits: u8,           // deposits still to be won this hand
+    scores: [i32; 4],
+    dora: Vec<u8>,
+    ura_dora: Vec<u8>,
+    haipai: [Vec<u8>; 4],
+    draws: [Vec<Value>; 4],
+    discards: [Vec<Value>; 4],
+    riichi: [bool; 4],      // declared, waiting on the riichi discard
+    result: Vec<Value>,
+}
+
+/////////////////////
+// implementations //
+/////////////////////
+
+// Writes a whole game. Events before the first Init are ignored.
+//
+// # Errors
+//
+// Errors from scoring a win or draw are passed along.
+pub fn write_tenhou_json(events: &[MjlogEvent], names: [&str; 4]) -> Result<String, HandError> {
+    let mut hands: Vec<HandLog> = Vec::new();
+
+    for event in events {
+        if let MjlogEvent::Init { game, dealer, deposits, scores, hands: haipai } = event {
+            let round = game.round_wind as usize * 4 + dealer;
+            hands.push(HandLog {
+                header: [u32::try_from(round).map_err(|_| HandError::ValueError)?, u32::from(game.repeats), u32::from(*deposits)],
+                dealer: *dealer,
+                deposits: *deposits,
+                scores: *scores,
+                dora: game.dora_markers.iter().flatten().map(|t| tile_code(*t)).collect(),
+                haipai: haipai.clone().map(|h| h.into_iter().map(tile_code).collect()),
+                ..HandLog::default()
+            });
+            continue
+        }
+        let Some(hand) = hands.last_mut() else { continue };
+
+        match event {
+            MjlogEvent::Draw { player, tile } => hand.draws[*player].push(json!(tile_code(*tile))),
+            MjlogEvent::Discard { player, tile, tsumogiri } => {
+                let code = if *tsumogiri { 60 } else { tile_code(*tile) };
+                if hand.riichi[*player] {
+                    hand.riichi[*player] = false;
+                    hand.discards[*player].push(json!(format!("r{code}")));
+                } else { hand.discards[*player].push(json!(code)) }
+            },
+            MjlogEvent::Call { player, meld } => {
+                let relative = meld.called_from.map_or(0, |w| (w as usize + 4 - (player + 4 - hand.dealer) % 4) % 4);
+                let call = json!(call_string(meld, relative));
+                match meld.kind {
+                    Some(MeldKind::Ankan | MeldKind::Shouminkan) => hand.discards[*player].push(call),
+                    Some(MeldKind::Daiminkan) => {
+                        hand.draws[*player].push(call);
+                        hand.discards[*player].push(json!(0)); // the kan takes the place of a discard
+                    },
+                    _ => hand.draws[*player].push(call),
+                }
+            },
+            // the stick is only put down once the riichi discard passes without a ron
+            MjlogEvent::Riichi { player, step } => match step {
+                1 => hand.riichi[*player] = true,
+                2 => hand.deposits += 1,
+                _ => (),
+            },
+            MjlogEvent::Dora(tile) => hand.dora.push(tile_code(*tile)),
+            MjlogEvent::Agari(agari) => {
+                let wind = |player: usize| WINDS[(player + 4 - hand.dealer) % 4];
+                let player = |wind: Wind| (wind as usize + hand.dealer) % 4;
+
+                let points = settle_win(&agari.game, &agari.seat, agari.discarder.map(wind), hand.deposits)?;
+                hand.deposits = 0;
+                hand.ura_dora = agari.game.ura_dora_markers.iter().flatten().map(|t| tile_code(*t)).collect();
+
+                let scored = Hand::new(agari.game.clone(), agari.seat.clone());
+                let pao = find_pao(agari.game.ruleset, &agari.seat, scored.yaku()).map_or(agari.winner, |(w, _)| player(w));
+                let mut details = vec![json!(agari.winner), json!(agari.discarder.unwrap_or(agari.winner)), json!(pao),
+                    json!(agari_summary(&agari.game, &scored)?)];
+                details.extend(yaku_strings(&agari.game, &scored).into_iter().map(Value::from));
+
+                if hand.result.is_empty() { hand.result.push(json!("和了")) }
+                hand.result.push(json!(by_player(points, hand.dealer)));
+                hand.result.push(Value::from(details));
+            },
+            MjlogEvent::Ryuukyoku { game, seats, abortive } => {
+                let name = match abortive {
+                    Some(AbortiveDraw::KyuushuKyuuhai) => "九種九牌",
+                    Some(AbortiveDraw::SuufonRenda) => "四風連打",
+                    Some(AbortiveDraw::SuuchaRiichi) => "四家立直",
+                    Some(AbortiveDraw::Suukaikan) => "四開槓",
+                    Some(AbortiveDraw::Sanchahou) => "三家和了",
+                    None => "流局",
+                };
+                hand.result.push(json!(name));
+                if abortive.is_none() {
+                    if let Ryuukyoku::Exhaustive { nagashi, points, .. } = exhaustive_draw(game, seats)? {
+                        if !nagashi.is_empty() { hand.result[0] = json!("流し満貫") }
+                        hand.result.push(json!(by_player(points, hand.dealer)));
+                    }
+                }
+            },
+            MjlogEvent::Init { .. } => (),
+        }
+    }
+
+    let log = hands.into_iter().map(|hand| {
+        let mut fields = vec![json!(hand.header), json!(hand.scores), json!(hand.dora), json!(hand.ura_dora)];
+        for ((haipai, draws), discards) in hand.haipai.into_iter().zip(hand.draws).zip(hand.discards) {
+            fields.extend([json!(haipai), Value::from(draws), Value::from(discards)]);
+        }
+        fields.push(Value::from(hand.result));
+        Value::from(fields)
+    }).collect();
+
+    let file = TenhouLog { title: ["FiveSou", ""], name: names, rule: Rule { disp: "FiveSou", aka: 1 }, log };
+    serde_json::to_string(&file).map_err(|_| HandError::ValueError)
+}
+
+// Tenhou numbers tiles by suit (1 man, 2 pin, 3 sou, 4 honors) and then number. Red fives are 51-53.
+fn tile_code(tile: Tile) -> u8 {
+    match tile {
+        Tile::Number { suit, number, red } => {
+            let suit = match suit { Suit::Man => 1, Suit::Pin => 2, Suit::Sou => 3 };
+            #[allow(clippy::cast_sign_loss)]
+            if red { 50 + suit } else { suit * 10 + number as u8 }
+        },
+        Tile::Wind(wind) => 41 + wind as u8,
+        Tile::Dragon(Dragon::White) => 45,
+        Tile::Dragon(Dragon::Green) => 46,
+        Tile::Dragon(Dragon::Red) => 47,
+    }
+}
+
+// The letter marking a call goes before the called tile, and its position shows who it was called from:
+// first for the previous player, second for the player opposite, and last for the next player.
+// relative is the discarder's seat counted from the caller (1 is the next player, 3 the previous).
+fn call_string(meld: &Meld, relative: usize) -> String {
+    let tiles = meld.tiles.iter().flatten().map(|t| tile_code(*t)).collect::<Vec<_>>();
+    let called = meld.called_index.unwrap_or(0);
+    let others: Vec<String> = tiles.iter().take(3).enumerate().filter(|(i, _)| *i != called).map(|(_, t)| t.to_string()).collect();
+    let marked = |letter: &str, tile: u8| format!("{letter}{tile}");
+
+    match meld.kind {
+        Some(MeldKind::Chi) => format!("c{}{}", tiles[called], others.concat()),
+        Some(MeldKind::Ankan) => format!("{}{}{}a{}", tiles[0], tiles[1], tiles[2], tiles[3]),
+        Some(MeldKind::Daiminkan) => {
+            let mut parts: Vec<String> = tiles.iter().enumerate().filter(|(i, _)| *i != called).map(|(_, t)| t.to_string()).collect();
+            parts.insert(match relative { 3 => 0, 2 => 1, _ => 3 }, marked("m", tiles[called]));
+            parts.concat()
+        },
+        Some(MeldKind::Shouminkan) => {
+            let mut parts = others;
+            parts.insert(match relative { 3 => 0, 2 => 1, _ => 2 }, format!("k{}{}", tiles[3], tiles[called]));
+            parts.concat()
+        },
+        _ => {
+            let mut parts = others;
+            parts.insert(match relative { 3 => 0, 2 => 1, _ => 2 }, marked("p", tiles[called]));
+            parts.concat()
+        },
+    }
+}
+
+// ie "30符1000点", "満貫2000-4000点" or "30符500点∀"
+fn agari_summary(game: &Game, hand: &Hand) -> Result<String, HandError> {
+    let base = hand.base_points(game.ruleset)?;
+    let limit = match hand.han() {
+        13.. if hand.yaku().iter().any(|y| YAKUMAN.contains(y)) => "役満".to_string(),
+        13.. => "数え役満".to_string(),
+        11 | 12 => "三倍満".to_string(),
+        8..=10 => "倍満".to_string(),
+        6 | 7 => "跳満".to_string(),
+        _ if base >= 2000 => "満貫".to_string(),
+        _ => format!("{}符", hand.fu()),
+    };
+    let points = match hand.payment_split(game.ruleset, 0)? {
+        Payment::Ron(value) => format!("{value}点"),
+        Payment::DealerTsumo(value) => format!("{value}点∀"),
+        Payment::Tsumo { dealer, non_dealer } => format!("{non_dealer}-{dealer}点"),
+    };
+    Ok(limit + &points)
+}
+
+// ie "立直(1飜)" or "大三元(役満)"
+fn yaku_strings(game: &Game, hand: &Hand) -> Vec<String> {
+    let mut strings: Vec<String> = hand.yaku().iter().filter(|y| **y != Yaku::SpecialWait).map(|y| {
+        if YAKUMAN.contains(y) { format!("{}(役満)", y.info().kanji) }
+        else { format!("{}({}飜)", y.info().kanji, vec![*y].han(hand.is_open(), game.ruleset)) }
+    }).collect();
+    if hand.dora() > 0 && !hand.yaku().iter().any(|y| YAKUMAN.contains(y)) { strings.push(format!("ドラ({}飜)", hand.dora())) }
+    strings
+}
+
+// converts points indexed by seat wind into points indexed by player
+fn by_player(points: [i32; 4], dealer: usize) -> [i32; 4] {
+    let mut ordered = [0; 4];
+    for (wind, value) in points.iter().enumerate() { ordered[(wind + dealer) % 4] = *value }
+    ordered
+}
+
+///////////
+// tests //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mahjong_core::tenhou::read_mjlog;
+    use mahjong_core::rulesets::RiichiRuleset;
+    use mahjong_core::conversions::ConvertStrings;
+
+    #[test]
+    fn test_call_strings(){
+        assert_eq!(call_string(&"m2,m3*,m4".to_meld().unwrap(), 3), "c131214");
+        assert_eq!(call_string(&"dr*,dr,dr".to_meld().unwrap(), 3), "p474747");
+        assert_eq!(call_string(&"p5r*,p5,p5".to_meld().unwrap(), 2), "25p5225");
+        assert_eq!(call_string(&"s1*,s1,s1".to_meld().unwrap(), 1), "3131p31");
+        assert_eq!(call_string(&"!s1,s1,s1,s1".to_meld().unwrap(), 0), "313131a31");
+        assert_eq!(call_string(&"s1,s1*,s1,s1".to_meld().unwrap(), 2), "31m313131");
+        assert_eq!(call_string(&"s1,s1*,s1,s1".to_meld().unwrap(), 1), "313131m31");
+        assert_eq!(call_string(&"+s1,s1*,s1,s1".to_meld().unwrap(), 2), "31k313131");
+    }
+
+    #[test]
+    fn test_writing_log(){
+        let log = r#"<GO type="169"/>
+            <INIT seed="0,0,0,2,3,32" ten="250,250,250,250" oya="0" hai0="134,101,0,1,2,20,24,28,60,64,68,108,112"
+                hai1="133,132,4,8,12,40,44,48,92,96,76,77,120" hai2="3,5,6,7,9,10,11,13,14,15,17,18,19"
+                hai3="21,22,23,25,26,27,29,30,31,33,34,35,36"/>
+            <T50/><D134/><N who="1" m="51819" /><E120/><V37/><F37/><W38/><G38/><T39/><D101/>
+            <AGARI ba="0,0" hai="4,8,12,40,44,48,76,77,92,96,101" m="51819" machi="101" ten="30,1000,0" yaku="20,1"
+                doraHai="32" who="1" fromWho="0" />"#;
+        let events = read_mjlog(log, RiichiRuleset::Default).unwrap();
+        let json = write_tenhou_json(&events, ["A", "B", "C", "D\""]).unwrap();
+
+        assert!(json.starts_with("{\"title\":[\"FiveSou\",\"\"],\"name\":[\"A\",\"B\",\"C\",\"D\\\"\"]"));
+        assert!(json.contains("\"log\":[[[0,0,0],[25000,25000,25000,25000],[19],[],"));
+        assert!(json.contains("[11,12,12,12,13,13,13,14,14,14,15,15,15],[21],[60]")); // player 2's draw and tsumogiri
+        assert!(json.contains("[24,21],[47,38]")); // player 0's draws and discards
+        assert!(json.contains("[\"p474747\"],[44]")); // player 1 pons red dragon from player 0
+        assert!(json.contains("[\"和了\",[-1000,1000,0,0],[1,0,1,\"30符1000点\",\"役牌(1飜)\"]]"));
+
+        // a riichi discard that deals in never puts its stick down
+        let events = read_mjlog(&log.replace("<D101/>", "<REACH who=\"0\" step=\"1\"/><D101/>"), RiichiRuleset::Default).unwrap();
+        let json = write_tenhou_json(&events, ["A", "B", "C", "D"]).unwrap();
+        assert!(json.contains("[24,21],[47,\"r38\"]"));
+        assert!(json.contains("[\"和了\",[-1000,1000,0,0],"));
+    }
+}
@@ -0,0 +1,44 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+// player 1 pons red dragon off player 0, then rons on player 0's 8s. 1 han 30 fu.
+static GAME: [&str; 15] = [
+    r#"{"type":"start_game","names":["A","B","C","D"]}"#,
+    r#"{"type":"start_kyoku","bakaze":"E","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"dora_marker":"9m","scores":[25000,25000,25000,25000],"tehais":[["C","8s","1m","1m","1m","6m","7m","8m","7p","8p","9p","E","S"],["C","C","2m","3m","4m","2p","3p","4p","6s","7s","2s","2s","N"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"]]}"#,
+    r#"{"type":"tsumo","actor":0,"pai":"4p"}"#,
+    r#"{"type":"dahai","actor":0,"pai":"C","tsumogiri":false}"#,
+    r#"{"type":"pon","actor":1,"target":0,"pai":"C","consumed":["C","C"]}"#,
+    r#"{"type":"dahai","actor":1,"pai":"N","tsumogiri":false}"#,
+    r#"{"type":"tsumo","actor":2,"pai":"?"}"#,
+    r#"{"type":"dahai","actor":2,"pai":"1p","tsumogiri":true}"#,
+    r#"{"type":"tsumo","actor":3,"pai":"?"}"#,
+    r#"{"type":"dahai","actor":3,"pai":"1p","tsumogiri":true}"#,
+    r#"{"type":"tsumo","actor":0,"pai":"1p"}"#,
+    r#"{"type":"dahai","actor":0,"pai":"8s","tsumogiri":false}"#,
+    r#"{"type":"hora","actor":1,"target":0,"pai":"8s"}"#,
+    r#"{"type":"ryukyoku","reason":"fanpai"}"#,
+    r#"{"type":"dahai","actor":0,"pai":"0z","tsumogiri":false}"#,
+];
+
+#[test]
+fn mjai_over_stdio(){
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mahjong_simulator"))
+        .arg("mjai")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("simulator should start");
+
+    let mut stdin = child.stdin.take().unwrap();
+    for line in GAME { writeln!(stdin, "{line}").unwrap() }
+    drop(stdin);
+
+    let responses: Vec<String> = BufReader::new(child.stdout.take().unwrap()).lines().map(Result::unwrap).collect();
+    assert!(child.wait().unwrap().success());
+
+    assert_eq!(responses.len(), GAME.len());
+    assert!(responses[..12].iter().all(|r| r == r#"{"type":"none"}"#));
+    assert_eq!(responses[12], r#"{"type":"hora","actor":1,"target":0,"pai":"8s","yakus":[["yakuhai",1]],"fu":30,"fan":1,"hora_points":1000,"deltas":[-1000,1000,0,0],"scores":[24000,26000,25000,25000]}"#);
+    assert_eq!(responses[13], r#"{"type":"none"}"#);
+    assert!(responses[14].starts_with(r#"{"type":"error""#));
+}